@@ -0,0 +1,77 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const FIXTURES: [(&str, &str); 5] = [
+    ("test.css", include_str!("../../tests/test.css")),
+    ("test.html", include_str!("../../tests/test.html")),
+    ("test.json", include_str!("../../tests/test.json")),
+    ("test.rs", include_str!("../../tests/test.rs")),
+    ("test.ts", include_str!("../../tests/test.ts")),
+];
+
+/// A minified CSS bundle with everything on a single line.
+fn minified_css(size: usize) -> String {
+    let rule = ".a{color:#ff0;background:rgba(0,0,0,.5);border:1px solid hsl(120,50%,50%)}\
+                .b>.c:hover{margin:0 auto;transition:all .2s ease-in-out}";
+    rule.repeat(size / rule.len() + 1)
+}
+
+/// A single line with a lot of unclosed color functions and no colors.
+fn unclosed_functions(size: usize) -> String {
+    "rgb(".repeat(size / 4 + 1)
+}
+
+/// A large theme JSON file with one color per line.
+fn theme_json(lines: usize) -> String {
+    let mut text = String::from("{\n");
+    for i in 0..lines {
+        text.push_str(&format!(
+            "  \"editor.color{i}\": \"#{:06x}\",\n",
+            i * 97 % 0xffffff
+        ));
+    }
+    text.push('}');
+    text
+}
+
+fn bench_fixtures(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fixtures");
+    for (name, text) in FIXTURES {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), text, |b, text| {
+            b.iter(|| color_lsp::parse(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_synthetic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthetic");
+    group.sample_size(20);
+
+    for size in [50_000, 500_000] {
+        let text = minified_css(size);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("minified_css", size), &text, |b, text| {
+            b.iter(|| color_lsp::parse(black_box(text)))
+        });
+
+        let text = unclosed_functions(size);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("unclosed_functions", size),
+            &text,
+            |b, text| b.iter(|| color_lsp::parse(black_box(text))),
+        );
+    }
+
+    let text = theme_json(10_000);
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_with_input(BenchmarkId::new("theme_json", 10_000), &text, |b, text| {
+        b.iter(|| color_lsp::parse(black_box(text)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_fixtures, bench_synthetic);
+criterion_main!(benches);
@@ -0,0 +1,535 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+use crate::palette::PaletteConfig;
+use crate::parser::{HexLiteralOrder, ParseOptions};
+use crate::presentation::{ColorFormat, PresentationFormat};
+use crate::syntax_tree::check_queries;
+use crate::tailwind::TailwindConfig;
+
+/// The project config file names, discovered in the workspace root in order.
+pub(crate) const CONFIG_FILES: [&str; 2] = [".color-lsp.toml", "color-lsp.json"];
+
+/// The project config from `.color-lsp.toml` or `color-lsp.json` in the workspace root.
+///
+/// ```toml
+/// # Ignore files by glob patterns, relative to the workspace root.
+/// ignore = ["target/**", "**/*.min.css"]
+/// min_hex_length = 3
+/// # The channel order of 8-digit `0x` literals, "rgba" or "argb".
+/// hex_literal_order = "rgba"
+/// # The notation of the "Normalize colors" action, and whether to write hex digits in lowercase.
+/// normalize = "hex"
+/// lowercase_hex = true
+/// # Only detect the colors in the string literals, CSS values and color calls, by Tree-sitter.
+/// tree_sitter = true
+///
+/// [detectors]
+/// hex = true
+/// hex_literal = true
+/// functions = true
+/// named = true
+/// tailwind = true
+///
+/// # Override by language id or file glob.
+/// [languages.rust]
+/// detectors = { hex_literal = false }
+/// # More Tree-sitter queries, the colors are detected in the `@color` captures.
+/// queries = ["(const_item) @color"]
+/// normalize = "float_hsl"
+///
+/// [languages."*.kt"]
+/// hex_literal_order = "argb"
+///
+/// # The shader vectors, e.g. `vec3<f32>(1.0, 0.5, 0.2)`, are only detected when enabled.
+/// [languages."*.wgsl"]
+/// detectors = { shader = true }
+///
+/// # The approved colors, the others are reported.
+/// [palette]
+/// colors = ["#3366ff", "#333333"]
+/// tokens = "design/colors.tokens.json"
+///
+/// # The Tailwind CSS palette version, and the custom colors of the project.
+/// [tailwind]
+/// version = 4
+/// theme = "src/app.css"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    ignore: Vec<String>,
+    #[serde(flatten)]
+    rules: Rules,
+    languages: BTreeMap<String, Rules>,
+    palette: PaletteConfig,
+    tailwind: TailwindConfig,
+
+    #[serde(skip)]
+    ignore_set: GlobSet,
+    /// The `languages` keys that are file globs rather than language ids.
+    #[serde(skip)]
+    language_globs: Vec<(GlobMatcher, String)>,
+}
+
+/// The detector settings, unset fields are inherited.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+struct Rules {
+    detectors: Detectors,
+    min_hex_length: Option<usize>,
+    hex_literal_order: Option<HexLiteralOrder>,
+    normalize: Option<PresentationFormat>,
+    lowercase_hex: Option<bool>,
+    tree_sitter: Option<bool>,
+    /// The Tree-sitter queries added to the default ones, only by language id.
+    queries: Vec<String>,
+}
+
+/// Enable or disable the detectors, unset fields are inherited.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(crate) struct Detectors {
+    hex: Option<bool>,
+    hex_literal: Option<bool>,
+    functions: Option<bool>,
+    named: Option<bool>,
+    tailwind: Option<bool>,
+    shader: Option<bool>,
+}
+
+impl Config {
+    /// Load the config file from the workspace root, returns `Ok(None)` if there is none.
+    pub(crate) fn load(work_dir: &Path) -> Result<Option<Self>, String> {
+        let Some(path) = CONFIG_FILES
+            .iter()
+            .map(|name| work_dir.join(name))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_toml(&content)
+        };
+
+        config
+            .map(Some)
+            .map_err(|err| format!("Invalid config {}: {}", path.display(), err))
+    }
+
+    pub(crate) fn from_toml(content: &str) -> Result<Self, String> {
+        toml::from_str::<Self>(content)
+            .map_err(|err| err.to_string())?
+            .build()
+    }
+
+    pub(crate) fn from_json(content: &str) -> Result<Self, String> {
+        serde_json::from_str::<Self>(content)
+            .map_err(|err| err.to_string())?
+            .build()
+    }
+
+    /// Compile the glob patterns.
+    fn build(mut self) -> Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in self.ignore.iter() {
+            builder.add(Glob::new(pattern).map_err(|err| err.to_string())?);
+        }
+        self.ignore_set = builder.build().map_err(|err| err.to_string())?;
+
+        self.language_globs = self
+            .languages
+            .keys()
+            .filter(|key| key.contains(['*', '?', '[', '{', '/', '.']))
+            .map(|key| Ok((Glob::new(key)?.compile_matcher(), key.clone())))
+            .collect::<Result<_, globset::Error>>()
+            .map_err(|err| err.to_string())?;
+
+        if !self.rules.queries.is_empty() {
+            return Err("The Tree-sitter `queries` must be set by language id".into());
+        }
+        for (language_id, rules) in self.languages.iter() {
+            if !rules.queries.is_empty() {
+                check_queries(language_id, &rules.queries)?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub(crate) fn palette(&self) -> &PaletteConfig {
+        &self.palette
+    }
+
+    pub(crate) fn tailwind(&self) -> &TailwindConfig {
+        &self.tailwind
+    }
+
+    /// Whether the file is ignored, `path` is relative to the workspace root.
+    pub(crate) fn is_ignored(&self, path: Option<&Path>) -> bool {
+        path.is_some_and(|path| self.ignore_set.is_match(path))
+    }
+
+    /// Resolve the parse options for a document, based on `options`.
+    ///
+    /// The top level rules apply first, then the rules for the `language_id`,
+    /// then the rules of the matched file globs.
+    ///
+    /// The Tailwind CSS detector is turned off after the top level rules for the languages
+    /// without utility classes, their own rules can turn it on again.
+    pub(crate) fn parse_options(
+        &self,
+        mut options: ParseOptions,
+        language_id: &str,
+        path: Option<&Path>,
+    ) -> ParseOptions {
+        options.language_id = language_id.to_string();
+        if is_argb_language(language_id) {
+            options.hex_literal_order = HexLiteralOrder::Argb;
+        }
+        let mut rules = self.matched_rules(language_id, path);
+        if let Some(top_level) = rules.next() {
+            top_level.apply(&mut options);
+        }
+        if !is_tailwind_language(language_id) {
+            options.tailwind = false;
+        }
+        for rules in rules {
+            rules.apply(&mut options);
+        }
+
+        options
+    }
+
+    /// Resolve the notation to normalize the colors of a document into,
+    /// returns `None` if it's not configured.
+    pub(crate) fn normalize_format(
+        &self,
+        language_id: &str,
+        path: Option<&Path>,
+    ) -> Option<ColorFormat> {
+        let mut normalize = None;
+        let mut lowercase_hex = false;
+        let mut hex_literal_order = if is_argb_language(language_id) {
+            HexLiteralOrder::Argb
+        } else {
+            HexLiteralOrder::default()
+        };
+        for rules in self.matched_rules(language_id, path) {
+            normalize = rules.normalize.or(normalize);
+            lowercase_hex = rules.lowercase_hex.unwrap_or(lowercase_hex);
+            hex_literal_order = rules.hex_literal_order.unwrap_or(hex_literal_order);
+        }
+
+        let mut format = normalize?.color_format(hex_literal_order);
+        format.set_upper(!lowercase_hex);
+        Some(format)
+    }
+
+    /// The rules for a document in the order of precedence: the top level rules,
+    /// the rules for the `language_id`, then the rules of the matched file globs.
+    fn matched_rules<'a>(
+        &'a self,
+        language_id: &'a str,
+        path: Option<&'a Path>,
+    ) -> impl Iterator<Item = &'a Rules> + 'a {
+        let language = self
+            .languages
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(language_id))
+            .map(|(_, rules)| rules);
+        let globs = self
+            .language_globs
+            .iter()
+            .filter(move |(matcher, _)| path.is_some_and(|path| matcher.is_match(path)))
+            .map(|(_, key)| &self.languages[key]);
+
+        std::iter::once(&self.rules).chain(language).chain(globs)
+    }
+}
+
+/// The languages whose 8-digit `0x` literals are ARGB by default, as in Flutter and Compose.
+const ARGB_LANGUAGES: [&str; 2] = ["dart", "kotlin"];
+
+fn is_argb_language(language_id: &str) -> bool {
+    ARGB_LANGUAGES
+        .iter()
+        .any(|id| id.eq_ignore_ascii_case(language_id))
+}
+
+/// The languages of the Tailwind CSS utility classes, the detector is off for the others.
+const TAILWIND_LANGUAGES: [&str; 13] = [
+    "html",
+    "css",
+    "javascript",
+    "typescript",
+    "javascriptreact",
+    "typescriptreact",
+    "jsx",
+    "tsx",
+    "vue",
+    "vue.js",
+    "svelte",
+    "erb",
+    "tera (html)",
+];
+
+fn is_tailwind_language(language_id: &str) -> bool {
+    TAILWIND_LANGUAGES
+        .iter()
+        .any(|id| id.eq_ignore_ascii_case(language_id))
+}
+
+impl Detectors {
+    pub(crate) fn apply(&self, options: &mut ParseOptions) {
+        options.hex = self.hex.unwrap_or(options.hex);
+        options.hex_literal = self.hex_literal.unwrap_or(options.hex_literal);
+        options.functions = self.functions.unwrap_or(options.functions);
+        options.named = self.named.unwrap_or(options.named);
+        options.tailwind = self.tailwind.unwrap_or(options.tailwind);
+        options.shader = self.shader.unwrap_or(options.shader);
+    }
+}
+
+impl Rules {
+    fn apply(&self, options: &mut ParseOptions) {
+        self.detectors.apply(options);
+        options.min_hex_length = self.min_hex_length.unwrap_or(options.min_hex_length);
+        options.hex_literal_order = self.hex_literal_order.unwrap_or(options.hex_literal_order);
+        options.tree_sitter = self.tree_sitter.unwrap_or(options.tree_sitter);
+        options.queries.extend(self.queries.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use indoc::indoc;
+
+    use super::Config;
+    use crate::parser::{HexLiteralOrder, ParseOptions};
+    use crate::presentation::ColorFormat;
+
+    #[test]
+    fn test_parse_options() {
+        let config = Config::from_toml(indoc! {r#"
+            ignore = ["target/**", "**/*.min.css"]
+            min_hex_length = 4
+
+            [detectors]
+            named = false
+
+            [languages.rust]
+            detectors = { hex_literal = false }
+            min_hex_length = 6
+
+            [languages."*.kt"]
+            hex_literal_order = "argb"
+        "#})
+        .unwrap();
+
+        assert!(config.is_ignored(Some(Path::new("target/debug/a.css"))));
+        assert!(config.is_ignored(Some(Path::new("dist/app.min.css"))));
+        assert!(!config.is_ignored(Some(Path::new("src/app.css"))));
+        assert!(!config.is_ignored(None));
+
+        assert_eq!(
+            config.parse_options(
+                ParseOptions::default(),
+                "css",
+                Some(Path::new("src/app.css"))
+            ),
+            ParseOptions {
+                named: false,
+                min_hex_length: 4,
+                language_id: "css".into(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            config.parse_options(ParseOptions::default(), "Rust", None),
+            ParseOptions {
+                named: false,
+                hex_literal: false,
+                tailwind: false,
+                min_hex_length: 6,
+                language_id: "Rust".into(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            config.parse_options(
+                ParseOptions::default(),
+                "kotlin",
+                Some(Path::new("app/src/Theme.kt"))
+            ),
+            ParseOptions {
+                named: false,
+                tailwind: false,
+                min_hex_length: 4,
+                hex_literal_order: HexLiteralOrder::Argb,
+                language_id: "kotlin".into(),
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(
+            config
+                .parse_options(ParseOptions::default(), "dart", None)
+                .hex_literal_order,
+            HexLiteralOrder::Argb
+        );
+        let config = Config::from_toml(indoc! {r#"
+            [languages.dart]
+            hex_literal_order = "rgba"
+        "#})
+        .unwrap();
+        assert_eq!(
+            config
+                .parse_options(ParseOptions::default(), "dart", None)
+                .hex_literal_order,
+            HexLiteralOrder::Rgba
+        );
+
+        let config = Config::from_toml(indoc! {r#"
+            [languages.glsl]
+            detectors = { shader = true }
+
+            [languages."*.wgsl"]
+            detectors = { shader = true }
+        "#})
+        .unwrap();
+        let shader = |language_id: &str, path: &str| {
+            config
+                .parse_options(ParseOptions::default(), language_id, Some(Path::new(path)))
+                .shader
+        };
+        assert!(shader("glsl", "src/main.frag"));
+        assert!(shader("plaintext", "src/main.wgsl"));
+        assert!(!shader("rust", "src/main.rs"));
+
+        let config = Config::from_toml(indoc! {r#"
+            tree_sitter = true
+
+            [languages.rust]
+            queries = ["(const_item) @color"]
+        "#})
+        .unwrap();
+        let options = config.parse_options(ParseOptions::default(), "rust", None);
+        assert!(options.tree_sitter);
+        assert_eq!(options.queries, ["(const_item) @color"]);
+
+        let config = Config::from_toml(indoc! {r#"
+            [languages.markdown]
+            detectors = { tailwind = true }
+        "#})
+        .unwrap();
+        let tailwind = |language_id: &str| {
+            config
+                .parse_options(ParseOptions::default(), language_id, None)
+                .tailwind
+        };
+        assert!(tailwind("html"));
+        assert!(tailwind("typescriptreact"));
+        assert!(tailwind("Svelte"));
+        assert!(tailwind("javascript"));
+        assert!(tailwind("ERB"));
+        assert!(tailwind("Tera (HTML)"));
+        assert!(tailwind("markdown"));
+        assert!(!tailwind("rust"));
+        assert!(!tailwind("python"));
+
+        assert!(Config::from_toml(r#"queries = ["(string) @color"]"#).is_err());
+        assert!(Config::from_toml("[languages.rust]\nqueries = [\"(nope) @color\"]").is_err());
+    }
+
+    #[test]
+    fn test_from_json() {
+        let config = Config::from_json(
+            r#"{ "detectors": { "functions": false }, "languages": { "json": { "min_hex_length": 6 } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.parse_options(ParseOptions::default(), "json", None),
+            ParseOptions {
+                functions: false,
+                tailwind: false,
+                min_hex_length: 6,
+                language_id: "json".into(),
+                ..Default::default()
+            }
+        );
+
+        let config = Config::from_json(
+            r##"{ "palette": { "colors": ["#3366ff"], "tokens": "colors.tokens.json" } }"##,
+        )
+        .unwrap();
+        assert_eq!(config.palette().colors, ["#3366ff"]);
+        assert_eq!(
+            config.palette().tokens.as_deref(),
+            Some(Path::new("colors.tokens.json"))
+        );
+
+        let config = Config::from_toml(indoc! {r#"
+            detectors = { tailwind = false }
+            tailwind = { version = 3, theme = "tailwind.config.json" }
+        "#})
+        .unwrap();
+        assert!(
+            !config
+                .parse_options(ParseOptions::default(), "html", None)
+                .tailwind
+        );
+        assert_eq!(config.tailwind().version, Some(3));
+
+        assert!(Config::from_toml("ignore = [\"[\"]").is_err());
+        assert!(Config::from_toml("min_hex_length = \"6\"").is_err());
+    }
+
+    #[test]
+    fn test_normalize_format() {
+        let config = Config::from_toml(indoc! {r#"
+            normalize = "hex"
+            lowercase_hex = true
+
+            [languages.rust]
+            normalize = "float_hsl"
+
+            [languages."*.kt"]
+            normalize = "hex_literal"
+            lowercase_hex = false
+            hex_literal_order = "argb"
+        "#})
+        .unwrap();
+
+        assert_eq!(
+            config.normalize_format("css", None),
+            Some(ColorFormat::Hex {
+                upper: false,
+                alpha: false
+            })
+        );
+        assert_eq!(
+            config.normalize_format("rust", None),
+            Some(ColorFormat::FloatHsl { alpha: true })
+        );
+        assert_eq!(
+            config.normalize_format("kotlin", Some(Path::new("Theme.kt"))),
+            Some(ColorFormat::HexLiteral {
+                upper: true,
+                alpha: false,
+                order: HexLiteralOrder::Argb
+            })
+        );
+        assert_eq!(Config::default().normalize_format("css", None), None);
+    }
+}
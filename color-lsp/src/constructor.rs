@@ -0,0 +1,1010 @@
+use std::ops::Range;
+
+use csscolorparser::Color;
+
+use crate::parser::ParseOptions;
+use crate::utils::format_trimmed;
+
+/// A color constructor of a UI framework, to write an edited color back in the same form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Constructor {
+    /// Flutter and Compose `Color(0xFF336699)`, in ARGB order.
+    ArgbHex { upper: bool },
+    /// Flutter `Color.fromARGB(255, 51, 102, 153)`
+    FromArgb,
+    /// Flutter `Color.fromRGBO(51, 102, 153, 1.0)`
+    FromRgbo,
+    /// Compose `Color(51, 102, 153)` / `Color(51, 102, 153, 255)`, channels in 0..255.
+    ComposeInt { alpha: bool },
+    /// Compose `Color(0.2f, 0.4f, 0.6f)` / `Color(0.2f, 0.4f, 0.6f, 1f)`, channels in 0..1.
+    ComposeFloat { alpha: bool },
+    /// Swift labeled initializers, e.g. SwiftUI `Color(red: 0.2, green: 0.4, blue: 0.6)`,
+    /// UIKit `UIColor(hue: 0.6, saturation: 0.5, brightness: 0.6, alpha: 1)` or
+    /// `#colorLiteral(red: 0.2, green: 0.4, blue: 0.6, alpha: 1)`, channels in 0..1.
+    Labeled {
+        callee: &'static str,
+        /// The label of the first channel, e.g. `red`, `calibratedRed` or `hue`.
+        first: &'static str,
+        /// The label of the alpha, `alpha` or `opacity`, if written.
+        alpha: Option<&'static str>,
+        /// The RGB channels are written as fractions, e.g. `51 / 255`.
+        fraction: bool,
+    },
+    /// Shader vectors, e.g. WGSL `vec3<f32>(1.0, 0.5, 0.2)` or GLSL `vec4(0.1, 0.2, 0.3, 1.0)`,
+    /// channels in 0..1, the 3-component vectors are opaque.
+    Shader {
+        callee: &'static str,
+        /// The suffix of the float literals, e.g. `f` of HLSL `1.0f`.
+        suffix: Option<char>,
+    },
+    /// gpui `rgb(0x3366ff)` / `rgba(0x3366ffaa)`, in RGBA order.
+    GpuiHex { alpha: bool, upper: bool },
+    /// Rust constructors, e.g. bevy `Color::srgb(0.2, 0.4, 0.6)` or egui
+    /// `Color32::from_rgb(51, 102, 153)`.
+    Rust {
+        callee: &'static RustCallee,
+        alpha: bool,
+        /// The channels are floats in 0..1, otherwise `u8`.
+        float: bool,
+    },
+}
+
+/// The channel type of a Rust constructor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channels {
+    U8,
+    Float,
+    /// Generic over the channel type, as written, e.g. `Srgb::new(0.2, 0.4, 0.6)`.
+    Any,
+}
+
+/// A Rust color constructor.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RustCallee {
+    /// The crate, to offer the other constructors of the same crate.
+    krate: &'static str,
+    name: &'static str,
+    /// The constructor with alpha, e.g. `Color::srgba` for `Color::srgb`.
+    alpha_name: Option<&'static str>,
+    /// The channels are hue (in degrees), saturation and lightness.
+    hsl: bool,
+    channels: Channels,
+    /// The channels are an array, e.g. `Rgb([51, 102, 153])`.
+    array: bool,
+}
+
+#[rustfmt::skip]
+static RUST_CALLEES: [RustCallee; 7] = [
+    RustCallee { krate: "bevy", name: "Color::srgb", alpha_name: Some("Color::srgba"), hsl: false, channels: Channels::Float, array: false },
+    RustCallee { krate: "bevy", name: "Color::srgb_u8", alpha_name: Some("Color::srgba_u8"), hsl: false, channels: Channels::U8, array: false },
+    RustCallee { krate: "bevy", name: "Color::hsl", alpha_name: Some("Color::hsla"), hsl: true, channels: Channels::Float, array: false },
+    RustCallee { krate: "egui", name: "Color32::from_rgb", alpha_name: Some("Color32::from_rgba_unmultiplied"), hsl: false, channels: Channels::U8, array: false },
+    RustCallee { krate: "ratatui", name: "Color::Rgb", alpha_name: None, hsl: false, channels: Channels::U8, array: false },
+    RustCallee { krate: "palette", name: "Srgb::new", alpha_name: Some("Srgba::new"), hsl: false, channels: Channels::Any, array: false },
+    RustCallee { krate: "image", name: "Rgb", alpha_name: Some("Rgba"), hsl: false, channels: Channels::Any, array: true },
+];
+
+/// The callees of the constructors besides the Rust ones, the longer ones first.
+const CALLEES: [&str; 8] = [
+    "Color.fromARGB",
+    "Color.fromRGBO",
+    "#colorLiteral",
+    "UIColor",
+    "NSColor",
+    "Color",
+    "rgba",
+    "rgb",
+];
+
+/// The callees of the Swift labeled initializers.
+const SWIFT_CALLEES: [&str; 4] = ["#colorLiteral", "UIColor", "NSColor", "Color"];
+
+/// The vector constructors of GLSL, WGSL and HLSL, the longer ones first.
+const SHADER_CALLEES: [&str; 14] = [
+    "vec3<f32>",
+    "vec4<f32>",
+    "vec3<f16>",
+    "vec4<f16>",
+    "vec3f",
+    "vec4f",
+    "vec3h",
+    "vec4h",
+    "vec3",
+    "vec4",
+    "float3",
+    "float4",
+    "half3",
+    "half4",
+];
+
+/// The labels of the first channel, RGB with their HSB counterparts.
+const RGB_LABELS: [(&str, Option<&str>); 4] = [
+    ("red", Some("hue")),
+    ("calibratedRed", Some("calibratedHue")),
+    ("deviceRed", Some("deviceHue")),
+    ("srgbRed", None),
+];
+
+/// The maximum length of the arguments of a constructor call.
+const MAX_CALL_LEN: usize = 256;
+
+fn is_ident_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Match a constructor call whose `(` is at `paren`, returns the byte range of the call
+/// from the callee to the `)`, and the color.
+///
+/// The shader vectors are only matched with `options.shader`, the others with `options.functions`.
+pub(crate) fn match_constructor(
+    line_text: &str,
+    paren: usize,
+    options: &ParseOptions,
+) -> Option<(Range<usize>, Color)> {
+    let before = &line_text[..paren];
+    // The Rust callees first, as `Rgb` is a suffix of ratatui `Color::Rgb`.
+    let rust_callees = RUST_CALLEES
+        .iter()
+        .flat_map(|callee| [Some(callee.name), callee.alpha_name])
+        .flatten();
+    let callees = rust_callees
+        .chain(CALLEES)
+        .filter(|_| options.functions)
+        .chain(SHADER_CALLEES.into_iter().filter(|_| options.shader));
+    let start = callees.into_iter().find_map(|callee| {
+        let start = before.strip_suffix(callee).map(str::len)?;
+        let prev = start.checked_sub(1).map(|ix| line_text.as_bytes()[ix]);
+        (!prev.is_some_and(is_ident_char)).then_some(start)
+    })?;
+
+    let mut depth = 0;
+    // The calls are short, don't rescan a long line for every unclosed `(`.
+    let close = line_text[paren..]
+        .bytes()
+        .take(MAX_CALL_LEN)
+        .position(|b| {
+            match b {
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?
+        + paren;
+
+    let (_, color) = Constructor::parse(&line_text[start..=close])?;
+    Some((start..close + 1, color))
+}
+
+/// Split the arguments of a call, e.g. `Color(1, 2, 3)` into `("Color", ["1", "2", "3"])`.
+fn split_call(call: &str) -> Option<(&str, Vec<&str>)> {
+    let open = call.find('(')?;
+    let args = call[open + 1..].strip_suffix(')')?.trim();
+    let args = if args.is_empty() {
+        vec![]
+    } else {
+        args.split(',').map(str::trim).collect()
+    };
+    Some((call[..open].trim_end(), args))
+}
+
+/// Parse an integer channel in 0..255, e.g. `51` or `0xFF`.
+fn parse_int(arg: &str) -> Option<u8> {
+    match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+/// Whether the argument is a float literal, e.g. `0.2`, `0.2f` or `1f`.
+fn is_float(arg: &str) -> bool {
+    arg.contains('.') || arg.ends_with(['f', 'F'])
+}
+
+/// Parse a float channel in 0..1, e.g. `0.2`, `0.2f` or `1f`.
+fn parse_unit(arg: &str) -> Option<f32> {
+    arg.trim_end_matches(['f', 'F'])
+        .parse()
+        .ok()
+        .filter(|value| (0.0..=1.0).contains(value))
+}
+
+/// Parse a channel in 0..1, also written as a fraction of 255, e.g. `0.2` or `51 / 255`,
+/// returns whether it's a fraction.
+fn parse_fraction(arg: &str) -> Option<(f32, bool)> {
+    let Some((numerator, denominator)) = arg.split_once('/') else {
+        return parse_unit(arg).map(|value| (value, false));
+    };
+    if denominator.trim().parse::<f32>().ok()? != 255. {
+        return None;
+    }
+    let numerator: f32 = numerator.trim().parse().ok()?;
+    (0.0..=255.)
+        .contains(&numerator)
+        .then_some((numerator / 255., true))
+}
+
+/// The HSB counterpart of an RGB label or the other way around, e.g. `hue` for `red`.
+fn counterpart(label: &str) -> Option<&'static str> {
+    RGB_LABELS.iter().find_map(|(rgb, hsb)| match *hsb {
+        _ if *rgb == label => *hsb,
+        Some(hsb) if hsb == label => Some(*rgb),
+        _ => None,
+    })
+}
+
+fn is_hsb(label: &str) -> bool {
+    RGB_LABELS.iter().any(|(_, hsb)| *hsb == Some(label))
+}
+
+/// Parse the arguments of a Swift labeled initializer, e.g. `["red: 0.2", "green: 0.4", ...]`.
+fn parse_labeled(callee: &str, args: &[&str]) -> Option<(Constructor, Color)> {
+    let callee = *SWIFT_CALLEES.iter().find(|swift| **swift == callee)?;
+    let pairs = args
+        .iter()
+        .map(|arg| {
+            let (label, value) = arg.split_once(':')?;
+            Some((label.trim(), value.trim()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if !(3..=4).contains(&pairs.len()) {
+        return None;
+    }
+
+    let first = RGB_LABELS
+        .iter()
+        .flat_map(|(rgb, hsb)| [Some(*rgb), *hsb])
+        .flatten()
+        .find(|label| *label == pairs[0].0)?;
+    let hsb = is_hsb(first);
+    let labels = if hsb {
+        ["saturation", "brightness"]
+    } else {
+        ["green", "blue"]
+    };
+    if [pairs[1].0, pairs[2].0] != labels {
+        return None;
+    }
+    let alpha = match pairs.get(3) {
+        Some((label, _)) => Some(*["alpha", "opacity"].iter().find(|alpha| *alpha == label)?),
+        None => None,
+    };
+
+    let (x, fraction) = parse_fraction(pairs[0].1)?;
+    let (y, _) = parse_fraction(pairs[1].1)?;
+    let (z, _) = parse_fraction(pairs[2].1)?;
+    let a = match pairs.get(3) {
+        Some((_, value)) => parse_fraction(value)?.0,
+        None => 1.,
+    };
+    let color = if hsb {
+        Color::from_hsva(x * 360., y, z, a)
+    } else {
+        Color::new(x, y, z, a)
+    };
+
+    let constructor = Constructor::Labeled {
+        callee,
+        first,
+        alpha,
+        fraction: fraction && !hsb,
+    };
+    Some((constructor, color))
+}
+
+/// Parse a Rust `u8` literal, e.g. `51`, `51u8` or `0x33`.
+fn rust_u8(arg: &str) -> Option<u8> {
+    parse_int(arg.strip_suffix("u8").unwrap_or(arg).trim_end_matches('_'))
+}
+
+/// Whether the argument is a Rust float literal, e.g. `0.2`, `1.` or `1f32`.
+fn is_rust_float(arg: &str) -> bool {
+    !arg.starts_with("0x") && (arg.contains('.') || arg.ends_with("f32") || arg.ends_with("f64"))
+}
+
+/// Parse a Rust float literal, e.g. `0.2`, `1.` or `1f32`.
+fn rust_float(arg: &str) -> Option<f32> {
+    let arg = arg
+        .strip_suffix("f32")
+        .or_else(|| arg.strip_suffix("f64"))
+        .unwrap_or(arg);
+    arg.trim_end_matches('_').parse().ok()
+}
+
+/// The number of components of a shader vector, by the prefix, e.g. 4 of `vec4<f32>`.
+fn shader_len(callee: &str) -> usize {
+    let size = callee.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    if size.starts_with('3') {
+        3
+    } else {
+        4
+    }
+}
+
+/// Parse the arguments of a shader vector, e.g. `vec3<f32>` with `["1.0", "0.5", "0.2"]`,
+/// all the components must be float literals in 0..1.
+fn parse_shader(name: &str, args: &[&str]) -> Option<(Constructor, Color)> {
+    let callee = *SHADER_CALLEES.iter().find(|callee| **callee == name)?;
+    if args.len() != shader_len(callee) {
+        return None;
+    }
+
+    let suffix = args[0]
+        .chars()
+        .last()
+        .filter(|c| matches!(c, 'f' | 'F' | 'h'));
+    let values = args
+        .iter()
+        .map(|arg| {
+            let digits = arg.trim_end_matches(['f', 'F', 'h']);
+            let is_float = digits.contains('.') || digits.len() < arg.len();
+            if !is_float || !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+                return None;
+            }
+            parse_unit(digits)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let color = Color::new(
+        values[0],
+        values[1],
+        values[2],
+        values.get(3).copied().unwrap_or(1.),
+    );
+    Some((Constructor::Shader { callee, suffix }, color))
+}
+
+/// Parse the arguments of a Rust constructor, e.g. `Color::srgb` with `["0.2", "0.4", "0.6"]`.
+fn parse_rust(name: &str, args: &[&str]) -> Option<(Constructor, Color)> {
+    let (callee, alpha) = RUST_CALLEES.iter().find_map(|callee| {
+        if callee.name == name {
+            Some((callee, false))
+        } else {
+            (callee.alpha_name == Some(name)).then_some((callee, true))
+        }
+    })?;
+
+    let mut args = args.to_vec();
+    if callee.array {
+        let first = args.first_mut()?;
+        *first = first.strip_prefix('[')?.trim_start();
+        let last = args.last_mut()?;
+        *last = last.strip_suffix(']')?.trim_end();
+    }
+    if args.len() != 3 + alpha as usize {
+        return None;
+    }
+
+    let float = match callee.channels {
+        Channels::U8 => false,
+        Channels::Float => true,
+        Channels::Any => args.iter().all(|arg| is_rust_float(arg)),
+    };
+    let values = args
+        .iter()
+        .enumerate()
+        .map(|(ix, arg)| {
+            if !float {
+                return rust_u8(arg).map(|value| value as f32 / 255.);
+            }
+            let max = if callee.hsl && ix == 0 { 360. } else { 1. };
+            rust_float(arg).filter(|value| (0.0..=max).contains(value))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let a = values.get(3).copied().unwrap_or(1.);
+    let color = if callee.hsl {
+        Color::from_hsla(values[0], values[1], values[2], a)
+    } else {
+        Color::new(values[0], values[1], values[2], a)
+    };
+    Some((
+        Constructor::Rust {
+            callee,
+            alpha,
+            float,
+        },
+        color,
+    ))
+}
+
+/// A float literal with a decimal point, e.g. `1.0` or `0.5`, as Dart doubles and shaders.
+fn decimal(value: f32) -> String {
+    let value = format_trimmed(value, 3, true);
+    if value.contains('.') {
+        value
+    } else {
+        format!("{}.0", value)
+    }
+}
+
+/// A Kotlin float literal, e.g. `1f` or `0.5f`.
+fn kotlin_float(value: f32) -> String {
+    format!("{}f", format_trimmed(value, 3, true))
+}
+
+impl Constructor {
+    /// Parse a constructor call, e.g. `Color.fromARGB(255, 51, 102, 153)`.
+    pub(crate) fn parse(call: &str) -> Option<(Self, Color)> {
+        let (callee, args) = split_call(call)?;
+        let ints = || {
+            args.iter()
+                .map(|arg| parse_int(arg))
+                .collect::<Option<Vec<_>>>()
+        };
+        let units = || {
+            args.iter()
+                .map(|arg| parse_unit(arg))
+                .collect::<Option<Vec<_>>>()
+        };
+
+        if args.first().is_some_and(|arg| arg.contains(':')) {
+            return parse_labeled(callee, &args);
+        }
+        if let Some(parsed) = parse_rust(callee, &args).or_else(|| parse_shader(callee, &args)) {
+            return Some(parsed);
+        }
+
+        match (callee, args.len()) {
+            ("Color.fromARGB", 4) => {
+                let [a, r, g, b] = ints_of(&args)?;
+                Some((Self::FromArgb, Color::from_rgba8(r, g, b, a)))
+            }
+            ("Color.fromRGBO", 4) => {
+                let [r, g, b] = ints_of(&args[..3])?;
+                let opacity = parse_unit(args[3])?;
+                let mut color = Color::from_rgba8(r, g, b, 255);
+                color.a = opacity;
+                Some((Self::FromRgbo, color))
+            }
+            ("rgb" | "rgba", 1) => {
+                let digits = args[0].strip_prefix("0x")?;
+                let alpha = callee == "rgba";
+                if digits.len() != if alpha { 8 } else { 6 } {
+                    return None;
+                }
+                let value = u32::from_str_radix(digits, 16).ok()?;
+                let [r, g, b, a] = if alpha {
+                    value.to_be_bytes()
+                } else {
+                    (value << 8 | 0xff).to_be_bytes()
+                };
+                let upper = !digits.chars().any(|c| c.is_ascii_lowercase());
+                Some((
+                    Self::GpuiHex { alpha, upper },
+                    Color::from_rgba8(r, g, b, a),
+                ))
+            }
+            ("Color", 1) => {
+                let digits = args[0]
+                    .strip_prefix("0x")
+                    .or_else(|| args[0].strip_prefix("0X"))?;
+                // Kotlin `Long` literals, e.g. `0xFF336699L`
+                let digits = digits.strip_suffix('L').unwrap_or(digits);
+                if digits.len() != 8 {
+                    return None;
+                }
+                let [a, r, g, b] = u32::from_str_radix(digits, 16).ok()?.to_be_bytes();
+                let upper = !digits.chars().any(|c| c.is_ascii_lowercase());
+                Some((Self::ArgbHex { upper }, Color::from_rgba8(r, g, b, a)))
+            }
+            ("Color", 3 | 4) if args.iter().all(|arg| is_float(arg)) => {
+                let channels = units()?;
+                let alpha = channels.get(3).copied();
+                let color = Color::new(channels[0], channels[1], channels[2], alpha.unwrap_or(1.));
+                Some((
+                    Self::ComposeFloat {
+                        alpha: alpha.is_some(),
+                    },
+                    color,
+                ))
+            }
+            ("Color", 3 | 4) => {
+                let channels = ints()?;
+                let alpha = channels.get(3).copied();
+                let color =
+                    Color::from_rgba8(channels[0], channels[1], channels[2], alpha.unwrap_or(255));
+                Some((
+                    Self::ComposeInt {
+                        alpha: alpha.is_some(),
+                    },
+                    color,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Format the color in this constructor.
+    pub(crate) fn format(&self, color: &Color) -> String {
+        let [r, g, b, a] = color.to_rgba8();
+        let translucent = a < 255;
+
+        match *self {
+            Self::ArgbHex { upper } => {
+                let digits = format!("{:02x}{:02x}{:02x}{:02x}", a, r, g, b);
+                if upper {
+                    format!("Color(0x{})", digits.to_ascii_uppercase())
+                } else {
+                    format!("Color(0x{})", digits)
+                }
+            }
+            Self::FromArgb => format!("Color.fromARGB({}, {}, {}, {})", a, r, g, b),
+            Self::FromRgbo => {
+                format!("Color.fromRGBO({}, {}, {}, {})", r, g, b, decimal(color.a))
+            }
+            Self::ComposeInt { alpha } => {
+                if alpha || translucent {
+                    format!("Color({}, {}, {}, {})", r, g, b, a)
+                } else {
+                    format!("Color({}, {}, {})", r, g, b)
+                }
+            }
+            Self::ComposeFloat { alpha } => {
+                let (r, g, b) = (
+                    kotlin_float(color.r),
+                    kotlin_float(color.g),
+                    kotlin_float(color.b),
+                );
+                if alpha || translucent {
+                    format!("Color({}, {}, {}, {})", r, g, b, kotlin_float(color.a))
+                } else {
+                    format!("Color({}, {}, {})", r, g, b)
+                }
+            }
+            Self::Labeled {
+                callee,
+                first,
+                alpha,
+                fraction,
+            } => {
+                let unit = |value: f32| format_trimmed(value, 3, true);
+                let (labels, channels) = if is_hsb(first) {
+                    let [h, s, v, _] = color.to_hsva();
+                    let h = if h.is_nan() {
+                        0.
+                    } else {
+                        h.rem_euclid(360.) / 360.
+                    };
+                    (["saturation", "brightness"], [unit(h), unit(s), unit(v)])
+                } else if fraction {
+                    let fraction = |value: u8| format!("{} / 255", value);
+                    (["green", "blue"], [fraction(r), fraction(g), fraction(b)])
+                } else {
+                    (["green", "blue"], [color.r, color.g, color.b].map(unit))
+                };
+
+                let mut text = format!(
+                    "{}({}: {}, {}: {}, {}: {}",
+                    callee, first, channels[0], labels[0], channels[1], labels[1], channels[2]
+                );
+                let alpha = alpha.or_else(|| translucent.then(|| default_alpha_label(callee)));
+                if let Some(alpha) = alpha {
+                    text.push_str(&format!(", {}: {}", alpha, unit(color.a)));
+                }
+                text.push(')');
+                text
+            }
+            Self::Shader { callee, suffix } => {
+                let float = |value: f32| {
+                    let mut text = decimal(value);
+                    text.extend(suffix);
+                    text
+                };
+                let mut channels = [color.r, color.g, color.b].map(float).to_vec();
+                if shader_len(callee) == 4 {
+                    channels.push(float(color.a));
+                }
+                format!("{}({})", callee, channels.join(", "))
+            }
+            Self::GpuiHex { alpha, upper } => {
+                let (name, digits) = if alpha || translucent {
+                    ("rgba", format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a))
+                } else {
+                    ("rgb", format!("{:02x}{:02x}{:02x}", r, g, b))
+                };
+                if upper {
+                    format!("{}(0x{})", name, digits.to_ascii_uppercase())
+                } else {
+                    format!("{}(0x{})", name, digits)
+                }
+            }
+            Self::Rust {
+                callee,
+                alpha,
+                float,
+            } => {
+                let alpha_name = callee.alpha_name.filter(|_| alpha || translucent);
+                let mut channels = if callee.hsl {
+                    let [h, s, l, _] = color.to_hsla();
+                    let h = if h.is_nan() { 0. } else { h.rem_euclid(360.) };
+                    vec![
+                        format_trimmed(h, 1, false),
+                        format_trimmed(s, 3, false),
+                        format_trimmed(l, 3, false),
+                    ]
+                } else if float {
+                    [color.r, color.g, color.b]
+                        .map(|value| format_trimmed(value, 3, false))
+                        .to_vec()
+                } else {
+                    [r, g, b].map(|value| value.to_string()).to_vec()
+                };
+                if alpha_name.is_some() {
+                    channels.push(if float {
+                        format_trimmed(color.a, 3, false)
+                    } else {
+                        a.to_string()
+                    });
+                }
+
+                let name = alpha_name.unwrap_or(callee.name);
+                let channels = channels.join(", ");
+                if callee.array {
+                    format!("{}([{}])", name, channels)
+                } else {
+                    format!("{}({})", name, channels)
+                }
+            }
+        }
+    }
+
+    /// The name of the form, e.g. `Color.fromARGB()`.
+    pub(crate) fn notation(&self) -> String {
+        match self {
+            Self::ArgbHex { .. } => "Color(0xAARRGGBB)".into(),
+            Self::FromArgb => "Color.fromARGB()".into(),
+            Self::FromRgbo => "Color.fromRGBO()".into(),
+            Self::ComposeInt { .. } => "Color() ints".into(),
+            Self::ComposeFloat { .. } => "Color() floats".into(),
+            Self::Labeled {
+                callee,
+                first,
+                alpha,
+                fraction,
+            } => {
+                let labels = if is_hsb(first) {
+                    "saturation:brightness:"
+                } else {
+                    "green:blue:"
+                };
+                let alpha = alpha.map(|alpha| format!("{}:", alpha)).unwrap_or_default();
+                let notation = format!("{}({}:{}{})", callee, first, labels, alpha);
+                if *fraction {
+                    format!("{} / 255", notation)
+                } else {
+                    notation
+                }
+            }
+            Self::Shader { callee, .. } => format!("{}()", callee),
+            Self::GpuiHex { alpha, .. } => {
+                format!("{}(0x)", if *alpha { "rgba" } else { "rgb" })
+            }
+            Self::Rust {
+                callee,
+                alpha,
+                float,
+            } => {
+                let name = callee.alpha_name.filter(|_| *alpha).unwrap_or(callee.name);
+                match callee.channels {
+                    Channels::Any if *float => format!("{}() floats", name),
+                    Channels::Any => format!("{}() u8", name),
+                    _ => format!("{}()", name),
+                }
+            }
+        }
+    }
+
+    /// The other constructors of the same framework, offered by the color picker.
+    pub(crate) fn alternatives(&self) -> Vec<Self> {
+        match *self {
+            // Both Flutter and Compose.
+            Self::ArgbHex { .. } => vec![],
+            Self::FromArgb | Self::FromRgbo => vec![
+                Self::ArgbHex { upper: true },
+                Self::FromArgb,
+                Self::FromRgbo,
+            ],
+            Self::ComposeInt { .. } | Self::ComposeFloat { .. } => vec![
+                Self::ArgbHex { upper: true },
+                Self::ComposeInt { alpha: false },
+                Self::ComposeFloat { alpha: false },
+            ],
+            // The RGB in 0..1 and in fractions of 255, and the HSB with the same callee.
+            Self::Labeled {
+                callee,
+                first,
+                alpha,
+                ..
+            } => {
+                let rgb = if is_hsb(first) {
+                    counterpart(first)
+                } else {
+                    Some(first)
+                };
+                let hsb = rgb.and_then(counterpart);
+                let labeled = |first, fraction| Self::Labeled {
+                    callee,
+                    first,
+                    alpha,
+                    fraction,
+                };
+                rgb.into_iter()
+                    .flat_map(|rgb| [labeled(rgb, false), labeled(rgb, true)])
+                    .chain(hsb.map(|hsb| labeled(hsb, false)))
+                    .collect()
+            }
+            Self::Shader { .. } | Self::GpuiHex { .. } => vec![],
+            // The constructors of the same crate, both in floats and `u8` if generic.
+            Self::Rust { callee, alpha, .. } => RUST_CALLEES
+                .iter()
+                .filter(|other| other.krate == callee.krate)
+                .flat_map(|other| {
+                    let floats: &[bool] = match other.channels {
+                        Channels::U8 => &[false],
+                        Channels::Float => &[true],
+                        Channels::Any => &[true, false],
+                    };
+                    floats.iter().map(move |float| Self::Rust {
+                        callee: other,
+                        alpha,
+                        float: *float,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn set_upper(&mut self, value: bool) {
+        if let Self::ArgbHex { upper } | Self::GpuiHex { upper, .. } = self {
+            *upper = value;
+        }
+    }
+
+    pub(crate) fn set_alpha(&mut self, value: bool) {
+        match self {
+            Self::ComposeInt { alpha }
+            | Self::ComposeFloat { alpha }
+            | Self::GpuiHex { alpha, .. }
+            | Self::Rust { alpha, .. } => *alpha = value,
+            // Only the alpha of SwiftUI `Color` is optional.
+            Self::Labeled { callee, alpha, .. } if value || *callee == "Color" => {
+                *alpha = value.then(|| alpha.unwrap_or(default_alpha_label(callee)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The label of the alpha of a Swift initializer, `opacity` for SwiftUI `Color`.
+fn default_alpha_label(callee: &str) -> &'static str {
+    if callee == "Color" {
+        "opacity"
+    } else {
+        "alpha"
+    }
+}
+
+/// Parse `N` integer channels.
+fn ints_of<const N: usize>(args: &[&str]) -> Option<[u8; N]> {
+    let values = args
+        .iter()
+        .map(|arg| parse_int(arg))
+        .collect::<Option<Vec<_>>>()?;
+    values.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use csscolorparser::Color;
+
+    use super::{match_constructor, Constructor};
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn test_parse() {
+        let cases = [
+            ("Color(0xFF336699)", "#336699"),
+            ("Color(0x80336699)", "#33669980"),
+            ("Color(0xFF336699L)", "#336699"),
+            ("Color.fromARGB(255, 51, 102, 153)", "#336699"),
+            ("Color.fromRGBO(51, 102, 153, 0.5)", "#33669980"),
+            ("Color(51, 102, 153)", "#336699"),
+            ("Color(0.2f, 0.4f, 0.6f, 1f)", "#336699"),
+            ("Color(red: 0.2, green: 0.4, blue: 0.6)", "#336699"),
+            (
+                "Color(red: 0.2, green: 0.4, blue: 0.6, opacity: 0.5)",
+                "#33669980",
+            ),
+            (
+                "UIColor(red: 51 / 255, green: 102 / 255, blue: 153 / 255, alpha: 1)",
+                "#336699",
+            ),
+            (
+                "NSColor(calibratedRed: 0.2, green: 0.4, blue: 0.6, alpha: 1)",
+                "#336699",
+            ),
+            (
+                "Color(hue: 0.583, saturation: 0.667, brightness: 0.6)",
+                "#336699",
+            ),
+            (
+                "#colorLiteral(red: 0.2, green: 0.4, blue: 0.6, alpha: 1)",
+                "#336699",
+            ),
+            ("rgb(0x336699)", "#336699"),
+            ("rgba(0x33669980)", "#33669980"),
+            ("Color::srgb(0.2, 0.4, 0.6)", "#336699"),
+            ("Color::srgba_u8(51, 102, 153, 128)", "#33669980"),
+            ("Color::hsl(210., 0.5, 0.4)", "#336699"),
+            ("Color32::from_rgb(51, 102, 153)", "#336699"),
+            (
+                "Color32::from_rgba_unmultiplied(51, 102, 153, 128)",
+                "#33669980",
+            ),
+            ("Color::Rgb(51, 102, 153)", "#336699"),
+            ("Srgb::new(0.2, 0.4, 0.6)", "#336699"),
+            ("Srgba::new(51, 102, 153, 255)", "#336699"),
+            ("Rgb([51, 102, 153])", "#336699"),
+            ("Rgba([0.2, 0.4, 0.6, 1.])", "#336699"),
+            ("vec3<f32>(1.0, 0.5, 0.2)", "#ff8033"),
+            ("vec4(0.2, 0.4, 0.6, 0.5)", "#33669980"),
+            ("vec4<f32>(0.2, 0.4, 0.6, 0.5)", "#33669980"),
+            ("vec4<f16>(0.2, 0.4, 0.6, 1.0)", "#336699"),
+            ("float4(0.2f, 0.4f, 0.6f, 1.0f)", "#336699"),
+        ];
+        for (call, hex) in cases {
+            let (constructor, color) = Constructor::parse(call).unwrap();
+            assert_eq!(color.to_css_hex(), hex, "{}", call);
+            if call != "Color(0xFF336699L)" {
+                assert_eq!(constructor.format(&color), call);
+            }
+        }
+
+        for call in [
+            "Color(0x336699)",
+            "Color.fromARGB(255, 51, 102)",
+            "Color.fromARGB(256, 51, 102, 153)",
+            "Color.fromRGBO(51, 102, 153, 2.0)",
+            "Color(1.5f, 0f, 0f)",
+            "Color(red)",
+            "Color(red: 0.2, blue: 0.4, green: 0.6)",
+            "Color(red: 51 / 100, green: 0.4, blue: 0.6)",
+            "UIColor(white: 0.5, alpha: 1)",
+            "#colorLiteral(red: 1.2, green: 0.4, blue: 0.6, alpha: 1)",
+            "rgb(0x33669980)",
+            "rgb(51, 102, 153)",
+            "Color::srgb(51, 102, 153)",
+            "Color::srgb_u8(0.2, 0.4, 0.6)",
+            "Color::hsl(400., 0.5, 0.4)",
+            "Color::Rgb(51, 102)",
+            "Rgb(51, 102, 153)",
+            "vec3(1, 0.5, 0.2)",
+            "vec3(1.5, 0.5, 0.2)",
+            "vec4(1.0, 0.5, 0.2)",
+            "vec4<f32>(1.0, 0.5, 0.2)",
+            "vec3<f32>(0.1, 0.2, 0.3, 1.0)",
+            "vec3f(x, 0.5, 0.2)",
+        ] {
+            assert_eq!(Constructor::parse(call), None, "{}", call);
+        }
+    }
+
+    #[test]
+    fn test_match_constructor() {
+        let options = ParseOptions::default();
+        let matched = |line: &'static str, paren: usize| {
+            match_constructor(line, paren, &options).map(|(range, _)| &line[range])
+        };
+
+        let line = "let c = ratatui::style::Color::Rgb(51, 102, 153); image::Rgb([0, 0, 0]);";
+        assert_eq!(
+            matched(line, line.find('(').unwrap()),
+            Some("Color::Rgb(51, 102, 153)")
+        );
+        let paren = line.rfind("Rgb(").unwrap() + 3;
+        assert_eq!(matched(line, paren), Some("Rgb([0, 0, 0])"));
+
+        let line = "final c = const Color(0xFF336699); ui.Color.fromARGB(255, 0, 0, 0) UIColor(1)";
+        assert_eq!(
+            matched(line, line.find('(').unwrap()),
+            Some("Color(0xFF336699)")
+        );
+        let paren = line.find("fromARGB(").unwrap() + 8;
+        assert_eq!(matched(line, paren), Some("Color.fromARGB(255, 0, 0, 0)"));
+        assert_eq!(matched(line, line.rfind('(').unwrap()), None);
+
+        // The shader vectors only with the option.
+        let line = "vec3(1.0, 0.5, 0.2)";
+        assert_eq!(matched(line, 4), None);
+        let options = ParseOptions {
+            shader: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            match_constructor(line, 4, &options).map(|(range, _)| &line[range]),
+            Some(line)
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        let mut constructor = Constructor::ComposeInt { alpha: false };
+        assert_eq!(constructor.format(&color), "Color(255, 0, 0, 128)");
+        constructor.set_alpha(true);
+        assert_eq!(
+            constructor.format(&Color::from_rgba8(255, 0, 0, 255)),
+            "Color(255, 0, 0, 255)"
+        );
+        assert_eq!(
+            Constructor::ArgbHex { upper: false }.format(&color),
+            "Color(0x80ff0000)"
+        );
+        assert_eq!(
+            Constructor::FromRgbo.format(&Color::from_rgba8(255, 0, 0, 255)),
+            "Color.fromRGBO(255, 0, 0, 1.0)"
+        );
+
+        let (mut constructor, _) =
+            Constructor::parse("Color(red: 51 / 255, green: 102 / 255, blue: 153 / 255)").unwrap();
+        assert_eq!(
+            constructor.format(&color),
+            "Color(red: 255 / 255, green: 0 / 255, blue: 0 / 255, opacity: 0.502)"
+        );
+        assert_eq!(constructor.notation(), "Color(red:green:blue:) / 255");
+        constructor.set_alpha(false);
+        assert_eq!(
+            constructor
+                .alternatives()
+                .iter()
+                .map(|alternative| alternative.format(&Color::from_rgba8(255, 0, 0, 255)))
+                .collect::<Vec<_>>(),
+            [
+                "Color(red: 1, green: 0, blue: 0)",
+                "Color(red: 255 / 255, green: 0 / 255, blue: 0 / 255)",
+                "Color(hue: 0, saturation: 1, brightness: 1)",
+            ]
+        );
+
+        let translucent = Color::from_rgba8(51, 102, 153, 128);
+        let (constructor, _) = Constructor::parse("rgb(0x3366FF)").unwrap();
+        assert_eq!(constructor.format(&translucent), "rgba(0x33669980)");
+        let (constructor, _) = Constructor::parse("Color32::from_rgb(0, 0, 0)").unwrap();
+        assert_eq!(
+            constructor.format(&translucent),
+            "Color32::from_rgba_unmultiplied(51, 102, 153, 128)"
+        );
+        let (constructor, _) = Constructor::parse("Color::Rgb(0, 0, 0)").unwrap();
+        assert_eq!(constructor.format(&translucent), "Color::Rgb(51, 102, 153)");
+        let (constructor, _) = Constructor::parse("Color::srgb(0., 0., 0.)").unwrap();
+        assert_eq!(
+            constructor
+                .alternatives()
+                .iter()
+                .map(|alternative| alternative.format(&Color::from_rgba8(255, 0, 0, 255)))
+                .collect::<Vec<_>>(),
+            [
+                "Color::srgb(1., 0., 0.)",
+                "Color::srgb_u8(255, 0, 0)",
+                "Color::hsl(0., 1., 0.5)",
+            ]
+        );
+        let (constructor, _) = Constructor::parse("Rgb([0, 0, 0])").unwrap();
+        assert_eq!(constructor.notation(), "Rgb() u8");
+        assert_eq!(
+            constructor.format(&translucent),
+            "Rgba([51, 102, 153, 128])"
+        );
+
+        // The alpha of UIKit is required.
+        let (mut constructor, _) =
+            Constructor::parse("UIColor(hue: 0.5, saturation: 1, brightness: 1, alpha: 1)")
+                .unwrap();
+        constructor.set_alpha(false);
+        assert_eq!(
+            constructor.format(&Color::from_rgba8(0, 0, 255, 255)),
+            "UIColor(hue: 0.667, saturation: 1, brightness: 1, alpha: 1)"
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use std::ops::Range;
+
+use csscolorparser::Color;
+use serde::Deserialize;
+
+use crate::parser::{parse_in_scope, ColorNode, ParseOptions};
+use crate::syntax_tree::Scope;
+
+/// The WCAG 2.1 conformance level for normal text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WcagLevel {
+    #[default]
+    Aa,
+    Aaa,
+}
+
+impl WcagLevel {
+    /// The minimum contrast ratio of normal text.
+    pub(crate) fn min_ratio(&self) -> f32 {
+        match self {
+            Self::Aa => 4.5,
+            Self::Aaa => 7.,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Aa => "AA",
+            Self::Aaa => "AAA",
+        }
+    }
+}
+
+/// The WCAG 2.1 relative luminance of an opaque sRGB color.
+pub(crate) fn relative_luminance(color: &Color) -> f32 {
+    let channel = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * channel(color.r) + 0.7152 * channel(color.g) + 0.0722 * channel(color.b)
+}
+
+/// The WCAG 2.1 contrast ratio in 1..21, the order of the colors doesn't matter.
+pub(crate) fn contrast_ratio(a: &Color, b: &Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// The APCA (0.0.98G-4g) lightness contrast `Lc` of the `text` color on the `background`,
+/// positive for dark text on light background, negative for light text on dark background.
+pub(crate) fn apca_contrast(text: &Color, background: &Color) -> f32 {
+    const BLACK_THRESHOLD: f32 = 0.022;
+    const BLACK_CLAMP: f32 = 1.414;
+    const DELTA_Y_MIN: f32 = 0.0005;
+    const SCALE: f32 = 1.14;
+    const OFFSET: f32 = 0.027;
+    const LOW_CLIP: f32 = 0.1;
+
+    let luminance = |color: &Color| {
+        let y = 0.2126729 * color.r.powf(2.4)
+            + 0.7151522 * color.g.powf(2.4)
+            + 0.0721750 * color.b.powf(2.4);
+        if y < BLACK_THRESHOLD {
+            y + (BLACK_THRESHOLD - y).powf(BLACK_CLAMP)
+        } else {
+            y
+        }
+    };
+
+    let (text, background) = (luminance(text), luminance(background));
+    if (background - text).abs() < DELTA_Y_MIN {
+        return 0.;
+    }
+
+    let lc = if background > text {
+        let sapc = (background.powf(0.56) - text.powf(0.57)) * SCALE;
+        if sapc < LOW_CLIP {
+            0.
+        } else {
+            sapc - OFFSET
+        }
+    } else {
+        let sapc = (background.powf(0.65) - text.powf(0.62)) * SCALE;
+        if sapc > -LOW_CLIP {
+            0.
+        } else {
+            sapc + OFFSET
+        }
+    };
+
+    lc * 100.
+}
+
+/// Composite a translucent color over an opaque `background`.
+pub(crate) fn blend(color: &Color, background: &Color) -> Color {
+    let mix = |c: f32, b: f32| c * color.a + b * (1. - color.a);
+    Color::new(
+        mix(color.r, background.r),
+        mix(color.g, background.g),
+        mix(color.b, background.b),
+        1.,
+    )
+}
+
+/// A color of a foreground/background pair.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PairedColor {
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range in the line.
+    pub(crate) range: Range<usize>,
+    pub(crate) color: Color,
+}
+
+/// A foreground color with too low contrast on its background.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ContrastIssue {
+    pub(crate) foreground: PairedColor,
+    pub(crate) background: PairedColor,
+    pub(crate) message: String,
+}
+
+/// The thresholds of the contrast check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ContrastOptions {
+    pub(crate) level: WcagLevel,
+    /// The minimum absolute APCA `Lc`, not checked if `None`.
+    pub(crate) apca_min_lc: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Foreground,
+    Background,
+}
+
+/// Check the contrast of the foreground/background colors in the same CSS rule or JSON object,
+/// e.g. `color` with `background-color`, or `"editor.foreground"` with `"editor.background"`.
+///
+/// The translucent foreground colors are blended over the background,
+/// the pairs with a translucent background are skipped.
+pub(crate) fn check_contrast(
+    text: &str,
+    parse_options: &ParseOptions,
+    scope: Option<&Scope>,
+    options: &ContrastOptions,
+) -> Vec<ContrastIssue> {
+    let mut nodes = parse_in_scope(text, parse_options, scope);
+    nodes.sort_by_key(|node| node.position);
+
+    // (block, prefix, role, color)
+    let mut entries: Vec<(usize, String, Role, PairedColor)> = vec![];
+    let mut nodes = nodes.into_iter().peekable();
+    // The stack of the open `{` blocks, the ids are unique in the document.
+    let mut blocks = vec![0];
+    let mut next_block = 1;
+    for (ix, line_text) in text.lines().enumerate() {
+        let bytes = line_text.as_bytes();
+        let mut offset = 0;
+        while let Some(node) = nodes.next_if(|node| node.position.line as usize == ix) {
+            let start = node.position.character as usize;
+            for &byte in &bytes[offset.min(start)..start] {
+                match byte {
+                    b'{' => {
+                        blocks.push(next_block);
+                        next_block += 1;
+                    }
+                    b'}' if blocks.len() > 1 => {
+                        blocks.pop();
+                    }
+                    _ => {}
+                }
+            }
+            offset = offset.max(start);
+
+            if let Some((prefix, role)) = key_before(&line_text[..start]).and_then(split_role) {
+                entries.push((
+                    *blocks.last().unwrap_or(&0),
+                    prefix,
+                    role,
+                    paired_color(&node, ix),
+                ));
+            }
+        }
+
+        for &byte in &bytes[offset.min(bytes.len())..] {
+            match byte {
+                b'{' => {
+                    blocks.push(next_block);
+                    next_block += 1;
+                }
+                b'}' if blocks.len() > 1 => {
+                    blocks.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut issues = vec![];
+    for (block, prefix, role, foreground) in entries.iter() {
+        if *role != Role::Foreground {
+            continue;
+        }
+        // The last background of the same block wins, as in CSS.
+        let Some((_, _, _, background)) = entries
+            .iter()
+            .rev()
+            .find(|(b, p, r, _)| b == block && p == prefix && *r == Role::Background)
+        else {
+            continue;
+        };
+        if background.color.a < 1. {
+            continue;
+        }
+
+        if let Some(message) = contrast_message(&foreground.color, &background.color, options) {
+            issues.push(ContrastIssue {
+                foreground: foreground.clone(),
+                background: background.clone(),
+                message,
+            });
+        }
+    }
+
+    issues
+}
+
+fn paired_color(node: &ColorNode, line: usize) -> PairedColor {
+    let start = node.position.character as usize;
+    PairedColor {
+        line,
+        range: start..start + node.matched.len(),
+        color: node.color.clone(),
+    }
+}
+
+/// Describe the contrast failures of `foreground` on `background`, `None` if it passes.
+fn contrast_message(
+    foreground: &Color,
+    background: &Color,
+    options: &ContrastOptions,
+) -> Option<String> {
+    let foreground = blend(foreground, background);
+    let mut failures = vec![];
+
+    let ratio = contrast_ratio(&foreground, background);
+    let min_ratio = options.level.min_ratio();
+    if ratio < min_ratio {
+        failures.push(format!(
+            "contrast ratio {:.2}:1 is below WCAG {} ({}:1)",
+            ratio,
+            options.level.name(),
+            min_ratio
+        ));
+    }
+
+    if let Some(min_lc) = options.apca_min_lc {
+        let lc = apca_contrast(&foreground, background);
+        if lc.abs() < min_lc {
+            failures.push(format!("APCA Lc {:.1} is below {}", lc, min_lc));
+        }
+    }
+
+    if failures.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Low contrast on background {}: {}",
+        background.to_css_hex(),
+        failures.join(", ")
+    ))
+}
+
+/// Return the key before the value at the end of `text`, e.g. `color` in `a { color: `
+/// or `editor.foreground` in `"editor.foreground": "`.
+fn key_before(text: &str) -> Option<&str> {
+    let text = text.trim_end();
+    let text = text.strip_suffix(['"', '\'']).unwrap_or(text).trim_end();
+    let text = text.strip_suffix([':', '='])?.trim_end();
+    let text = text.strip_suffix(['"', '\'']).unwrap_or(text);
+
+    let start = text
+        .bytes()
+        .rposition(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'$')))
+        .map_or(0, |ix| ix + 1);
+    let key = &text[start..];
+    (!key.is_empty()).then_some(key)
+}
+
+/// Split a key into the prefix and the role, e.g. `editor.foreground` into `("editor", Foreground)`.
+fn split_role(key: &str) -> Option<(String, Role)> {
+    const BACKGROUND: [&str; 5] = [
+        "background-color",
+        "background_color",
+        "backgroundcolor",
+        "background",
+        "bg",
+    ];
+    const FOREGROUND: [&str; 6] = [
+        "text-color",
+        "text_color",
+        "textcolor",
+        "foreground",
+        "text",
+        "fg",
+    ];
+
+    let key = key.to_ascii_lowercase();
+    // `color` is the text color only by itself, not in `border-color`.
+    if key == "color" {
+        return Some((String::new(), Role::Foreground));
+    }
+    // `background-color` also ends with `color`, so match the backgrounds first.
+    let (prefix, role) = BACKGROUND
+        .iter()
+        .find_map(|suffix| Some((key.strip_suffix(suffix)?, Role::Background)))
+        .or_else(|| {
+            FOREGROUND
+                .iter()
+                .find_map(|suffix| Some((key.strip_suffix(suffix)?, Role::Foreground)))
+        })?;
+
+    // Only split at a word boundary, e.g. not `context`.
+    let prefix = match prefix.strip_suffix(['.', '-', '_']) {
+        Some(prefix) => prefix,
+        None if prefix.is_empty() => prefix,
+        None => return None,
+    };
+    Some((prefix.to_string(), role))
+}
+
+#[cfg(test)]
+mod tests {
+    use csscolorparser::Color;
+    use indoc::indoc;
+
+    use super::{
+        apca_contrast, check_contrast, contrast_ratio, key_before, split_role, ContrastOptions,
+        Role, WcagLevel,
+    };
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn test_contrast_ratio() {
+        let white = Color::new(1., 1., 1., 1.);
+        let black = Color::new(0., 0., 0., 1.);
+        assert!((contrast_ratio(&white, &black) - 21.).abs() < 0.01);
+        assert!((contrast_ratio(&black, &white) - 21.).abs() < 0.01);
+        assert!((contrast_ratio(&white, &white) - 1.).abs() < 0.01);
+
+        let gray = Color::from_html("#777777").unwrap();
+        assert!((contrast_ratio(&gray, &white) - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_apca_contrast() {
+        let white = Color::new(1., 1., 1., 1.);
+        let black = Color::new(0., 0., 0., 1.);
+        assert!((apca_contrast(&black, &white) - 106.04).abs() < 0.1);
+        assert!((apca_contrast(&white, &black) + 107.88).abs() < 0.1);
+        assert_eq!(apca_contrast(&white, &white), 0.);
+
+        let gray = Color::from_html("#888888").unwrap();
+        assert!((apca_contrast(&gray, &white) - 63.06).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_split_role() {
+        assert_eq!(key_before("a { color: "), Some("color"));
+        assert_eq!(
+            key_before(r#"  "editor.foreground": ""#),
+            Some("editor.foreground")
+        );
+        assert_eq!(key_before("let c = "), Some("c"));
+        assert_eq!(key_before("rgb("), None);
+
+        assert_eq!(split_role("color"), Some(("".into(), Role::Foreground)));
+        assert_eq!(
+            split_role("background-color"),
+            Some(("".into(), Role::Background))
+        );
+        assert_eq!(
+            split_role("editor.foreground"),
+            Some(("editor".into(), Role::Foreground))
+        );
+        assert_eq!(
+            split_role("button_bg"),
+            Some(("button".into(), Role::Background))
+        );
+        assert_eq!(split_role("border-color"), None);
+        assert_eq!(split_role("context"), None);
+    }
+
+    #[test]
+    fn test_check_contrast() {
+        let options = ContrastOptions {
+            level: WcagLevel::Aa,
+            apca_min_lc: None,
+        };
+        let text = indoc! {r##"
+            .a { color: #777; background-color: #fff; }
+            .b { color: #000; background: white; }
+            .c { color: #777; }
+            .d {
+              background: #000;
+              .e { color: #111; background: #222; }
+              color: rgba(255, 255, 255, 0.2);
+            }
+            {
+              "editor.foreground": "#aaaaaa",
+              "editor.background": "#ffffff",
+              "sideBar.foreground": "#aaaaaa"
+            }
+        "##};
+
+        let issues = check_contrast(text, &ParseOptions::default(), None, &options)
+            .into_iter()
+            .map(|issue| (issue.foreground.line, issue.background.line, issue.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                (
+                    0,
+                    0,
+                    "Low contrast on background #ffffff: contrast ratio 4.48:1 is below WCAG AA (4.5:1)"
+                        .to_string()
+                ),
+                (
+                    5,
+                    5,
+                    "Low contrast on background #222222: contrast ratio 1.19:1 is below WCAG AA (4.5:1)"
+                        .into()
+                ),
+                (
+                    6,
+                    4,
+                    "Low contrast on background #000000: contrast ratio 1.66:1 is below WCAG AA (4.5:1)"
+                        .into()
+                ),
+                (
+                    9,
+                    10,
+                    "Low contrast on background #ffffff: contrast ratio 2.32:1 is below WCAG AA (4.5:1)"
+                        .into()
+                ),
+            ]
+        );
+
+        let options = ContrastOptions {
+            level: WcagLevel::Aa,
+            apca_min_lc: Some(75.),
+        };
+        let issues = check_contrast(
+            ".a { color: #767676; background: #fff; }",
+            &ParseOptions::default(),
+            None,
+            &options,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].message,
+            "Low contrast on background #ffffff: APCA Lc 71.6 is below 75"
+        );
+    }
+}
@@ -0,0 +1,1346 @@
+use std::fmt;
+use std::ops::Range;
+
+use csscolorparser::Color;
+
+use crate::utils::format_trimmed;
+
+type Matrix = [[f64; 3]; 3];
+
+// The conversion matrices of https://www.w3.org/TR/css-color-4/#color-conversion-code
+#[rustfmt::skip]
+const SRGB_TO_XYZ: Matrix = [
+    [0.41239079926595934, 0.357584339383878, 0.1804807884018343],
+    [0.21263900587151027, 0.715168678767756, 0.07219231536073371],
+    [0.01933081871559182, 0.11919477979462598, 0.9505321522496607],
+];
+#[rustfmt::skip]
+const XYZ_TO_SRGB: Matrix = [
+    [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+    [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+    [0.05563007969699366, -0.20397695888897652, 1.0569715142428786],
+];
+#[rustfmt::skip]
+const P3_TO_XYZ: Matrix = [
+    [0.4865709486482162, 0.26566769316909306, 0.1982172852343625],
+    [0.2289745640697488, 0.6917385218365064, 0.079286914093745],
+    [0.0, 0.04511338185890264, 1.043944368900976],
+];
+#[rustfmt::skip]
+const XYZ_TO_P3: Matrix = [
+    [2.493496911941425, -0.9313836179191239, -0.40271078445071684],
+    [-0.8294889695615747, 1.7626640603183463, 0.023624685841943577],
+    [0.03584583024378447, -0.07617238926804182, 0.9568845240076872],
+];
+#[rustfmt::skip]
+const A98_TO_XYZ: Matrix = [
+    [0.5766690429101305, 0.1855582379065463, 0.1882286462349947],
+    [0.29734497525053605, 0.6273635662554661, 0.07529145849399788],
+    [0.02703136138641234, 0.07068885253582723, 0.9913375368376388],
+];
+#[rustfmt::skip]
+const XYZ_TO_A98: Matrix = [
+    [2.0415879038107465, -0.5650069742788596, -0.34473135077832956],
+    [-0.9692436362808795, 1.8759675015077202, 0.04155505740717557],
+    [0.013444280632031142, -0.11836239223101838, 1.0151749943912054],
+];
+#[rustfmt::skip]
+const PROPHOTO_TO_XYZ_D50: Matrix = [
+    [0.7977666449006423, 0.13518129740053308, 0.0313477341283922],
+    [0.2880748288194013, 0.711835234241873, 0.00008993693872564],
+    [0.0, 0.0, 0.8251046025104602],
+];
+#[rustfmt::skip]
+const XYZ_D50_TO_PROPHOTO: Matrix = [
+    [1.3457868816471583, -0.25557208737979464, -0.05110186497554526],
+    [-0.5446307051249019, 1.5082477428451468, 0.02052744743642139],
+    [0.0, 0.0, 1.2119675456389452],
+];
+#[rustfmt::skip]
+const REC2020_TO_XYZ: Matrix = [
+    [0.6369580483012914, 0.14461690358620832, 0.1688809751641721],
+    [0.2627002120112671, 0.6779980715188708, 0.05930171646986196],
+    [0.0, 0.028072693049087428, 1.060985057710791],
+];
+#[rustfmt::skip]
+const XYZ_TO_REC2020: Matrix = [
+    [1.716651187971268, -0.355670783776392, -0.25336628137366],
+    [-0.666684351832489, 1.616481236634939, 0.0157685458139111],
+    [0.017639857445311, -0.042770613257809, 0.942103121235474],
+];
+// Bradford chromatic adaptation between the D65 and D50 white points.
+#[rustfmt::skip]
+const D65_TO_D50: Matrix = [
+    [1.0479297925449969, 0.022946870601609652, -0.05019226628920524],
+    [0.02962780877005599, 0.9904344267538799, -0.017073799063418826],
+    [-0.009243040646204504, 0.015055191490298152, 0.7518742814281371],
+];
+#[rustfmt::skip]
+const D50_TO_D65: Matrix = [
+    [0.955473421488075, -0.02309845494876471, 0.06325924320057072],
+    [-0.0283697093338637, 1.0099953980813041, 0.021041441191917323],
+    [0.012314014864481998, -0.020507649298898964, 1.330365926242124],
+];
+#[rustfmt::skip]
+const XYZ_TO_LMS: Matrix = [
+    [0.819022437996703, 0.3619062600528904, -0.1288737815209879],
+    [0.0329836539323885, 0.9292868615863434, 0.0361446663506424],
+    [0.0481771893596242, 0.2642395317527308, 0.6335478284694309],
+];
+#[rustfmt::skip]
+const LMS_TO_XYZ: Matrix = [
+    [1.2268798758459243, -0.5578149944602171, 0.2813910456659647],
+    [-0.0405757452148008, 1.112286803280317, -0.0717110580655164],
+    [-0.0763729366746601, -0.4214933324022432, 1.5869240198367816],
+];
+#[rustfmt::skip]
+const LMS_TO_OKLAB: Matrix = [
+    [0.210454268309314, 0.7936177747023054, -0.0040720430116193],
+    [1.9779985324311684, -2.42859224204858, 0.450593709617411],
+    [0.0259040424655478, 0.7827717124575296, -0.8086757549230774],
+];
+#[rustfmt::skip]
+const OKLAB_TO_LMS: Matrix = [
+    [1.0, 0.3963377773761749, 0.2158037573099136],
+    [1.0, -0.1055613458156586, -0.0638541728258133],
+    [1.0, -0.0894841775298119, -1.2914855480194092],
+];
+
+/// The white point of D50, for Lab.
+const D50_WHITE: [f64; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+/// The functions that may be written in CSS Color 4/5 syntax, see [`evaluate`].
+const FUNCTIONS: [&str; 11] = [
+    "rgb",
+    "rgba",
+    "hsl",
+    "hsla",
+    "hwb",
+    "lab",
+    "lch",
+    "oklab",
+    "oklch",
+    "color",
+    "color-mix",
+];
+
+/// The maximum length of a function call, with the nested colors.
+const MAX_CALL_LEN: usize = 512;
+
+/// A color space of CSS Color 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Space {
+    Srgb,
+    SrgbLinear,
+    DisplayP3,
+    A98Rgb,
+    ProphotoRgb,
+    Rec2020,
+    XyzD50,
+    XyzD65,
+    Lab,
+    Lch,
+    Oklab,
+    Oklch,
+    /// Hue, saturation and lightness, the saturation and lightness in 0..100.
+    Hsl,
+    /// Hue, whiteness and blackness, the whiteness and blackness in 0..100.
+    Hwb,
+}
+
+impl Space {
+    /// The space of a name, e.g. `display-p3` of `color()` or `oklch` of `color-mix()`.
+    fn from_name(name: &str) -> Option<Self> {
+        let space = match name.to_ascii_lowercase().as_str() {
+            "srgb" => Self::Srgb,
+            "srgb-linear" => Self::SrgbLinear,
+            "display-p3" => Self::DisplayP3,
+            "a98-rgb" => Self::A98Rgb,
+            "prophoto-rgb" => Self::ProphotoRgb,
+            "rec2020" => Self::Rec2020,
+            "xyz-d50" => Self::XyzD50,
+            "xyz" | "xyz-d65" => Self::XyzD65,
+            "lab" => Self::Lab,
+            "lch" => Self::Lch,
+            "oklab" => Self::Oklab,
+            "oklch" => Self::Oklch,
+            "hsl" => Self::Hsl,
+            "hwb" => Self::Hwb,
+            _ => return None,
+        };
+        Some(space)
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Srgb => "srgb",
+            Self::SrgbLinear => "srgb-linear",
+            Self::DisplayP3 => "display-p3",
+            Self::A98Rgb => "a98-rgb",
+            Self::ProphotoRgb => "prophoto-rgb",
+            Self::Rec2020 => "rec2020",
+            Self::XyzD50 => "xyz-d50",
+            Self::XyzD65 => "xyz-d65",
+            Self::Lab => "lab",
+            Self::Lch => "lch",
+            Self::Oklab => "oklab",
+            Self::Oklch => "oklch",
+            Self::Hsl => "hsl",
+            Self::Hwb => "hwb",
+        }
+    }
+
+    /// Whether the space is one of the predefined spaces of `color()`.
+    fn is_predefined(&self) -> bool {
+        !matches!(
+            self,
+            Self::Lab | Self::Lch | Self::Oklab | Self::Oklch | Self::Hsl | Self::Hwb
+        )
+    }
+
+    fn hue_index(&self) -> Option<usize> {
+        match self {
+            Self::Lch | Self::Oklch => Some(2),
+            Self::Hsl | Self::Hwb => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Convert the coordinates into XYZ D65, the missing components are 0.
+    fn coords_to_xyz(self, coords: [f64; 3]) -> [f64; 3] {
+        let coords = coords.map(|value| if value.is_nan() { 0. } else { value });
+        match self {
+            Self::Srgb => mul(&SRGB_TO_XYZ, coords.map(srgb_to_linear)),
+            Self::SrgbLinear => mul(&SRGB_TO_XYZ, coords),
+            Self::DisplayP3 => mul(&P3_TO_XYZ, coords.map(srgb_to_linear)),
+            Self::A98Rgb => mul(
+                &A98_TO_XYZ,
+                coords.map(|value| signed_pow(value, 563. / 256.)),
+            ),
+            Self::ProphotoRgb => mul(
+                &D50_TO_D65,
+                mul(&PROPHOTO_TO_XYZ_D50, coords.map(prophoto_to_linear)),
+            ),
+            Self::Rec2020 => mul(&REC2020_TO_XYZ, coords.map(rec2020_to_linear)),
+            Self::XyzD50 => mul(&D50_TO_D65, coords),
+            Self::XyzD65 => coords,
+            Self::Lab => mul(&D50_TO_D65, lab_to_xyz_d50(coords)),
+            Self::Lch => Self::Lab.coords_to_xyz(lch_to_lab(coords)),
+            Self::Oklab => mul(&LMS_TO_XYZ, mul(&OKLAB_TO_LMS, coords).map(|v| v * v * v)),
+            Self::Oklch => Self::Oklab.coords_to_xyz(lch_to_lab(coords)),
+            Self::Hsl => Self::Srgb.coords_to_xyz(hsl_to_srgb(coords)),
+            Self::Hwb => Self::Srgb.coords_to_xyz(hwb_to_srgb(coords)),
+        }
+    }
+
+    /// Convert XYZ D65 into the coordinates, the hue of an achromatic color is missing.
+    fn xyz_to_coords(self, xyz: [f64; 3]) -> [f64; 3] {
+        match self {
+            Self::Srgb => mul(&XYZ_TO_SRGB, xyz).map(srgb_from_linear),
+            Self::SrgbLinear => mul(&XYZ_TO_SRGB, xyz),
+            Self::DisplayP3 => mul(&XYZ_TO_P3, xyz).map(srgb_from_linear),
+            Self::A98Rgb => mul(&XYZ_TO_A98, xyz).map(|value| signed_pow(value, 256. / 563.)),
+            Self::ProphotoRgb => {
+                mul(&XYZ_D50_TO_PROPHOTO, mul(&D65_TO_D50, xyz)).map(prophoto_from_linear)
+            }
+            Self::Rec2020 => mul(&XYZ_TO_REC2020, xyz).map(rec2020_from_linear),
+            Self::XyzD50 => mul(&D65_TO_D50, xyz),
+            Self::XyzD65 => xyz,
+            Self::Lab => xyz_d50_to_lab(mul(&D65_TO_D50, xyz)),
+            Self::Lch => lab_to_lch(Self::Lab.xyz_to_coords(xyz), 0.01),
+            Self::Oklab => mul(&LMS_TO_OKLAB, mul(&XYZ_TO_LMS, xyz).map(f64::cbrt)),
+            Self::Oklch => lab_to_lch(Self::Oklab.xyz_to_coords(xyz), 0.0001),
+            Self::Hsl => srgb_to_hsl(Self::Srgb.xyz_to_coords(xyz)),
+            Self::Hwb => srgb_to_hwb(Self::Srgb.xyz_to_coords(xyz)),
+        }
+    }
+}
+
+fn mul(m: &Matrix, [x, y, z]: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * x + row[1] * y + row[2] * z)
+}
+
+fn signed_pow(value: f64, exponent: f64) -> f64 {
+    value.signum() * value.abs().powf(exponent)
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value.abs() <= 0.04045 {
+        value / 12.92
+    } else {
+        signed_pow((value.abs() + 0.055) / 1.055, 2.4) * value.signum()
+    }
+}
+
+fn srgb_from_linear(value: f64) -> f64 {
+    if value.abs() <= 0.0031308 {
+        value * 12.92
+    } else {
+        value.signum() * (1.055 * value.abs().powf(1. / 2.4) - 0.055)
+    }
+}
+
+fn prophoto_to_linear(value: f64) -> f64 {
+    if value.abs() <= 16. / 512. {
+        value / 16.
+    } else {
+        signed_pow(value, 1.8)
+    }
+}
+
+fn prophoto_from_linear(value: f64) -> f64 {
+    if value.abs() >= 1. / 512. {
+        signed_pow(value, 1. / 1.8)
+    } else {
+        value * 16.
+    }
+}
+
+const REC2020_ALPHA: f64 = 1.09929682680944;
+const REC2020_BETA: f64 = 0.018053968510807;
+
+fn rec2020_to_linear(value: f64) -> f64 {
+    if value.abs() < REC2020_BETA * 4.5 {
+        value / 4.5
+    } else {
+        value.signum() * ((value.abs() + REC2020_ALPHA - 1.) / REC2020_ALPHA).powf(1. / 0.45)
+    }
+}
+
+fn rec2020_from_linear(value: f64) -> f64 {
+    if value.abs() > REC2020_BETA {
+        value.signum() * (REC2020_ALPHA * value.abs().powf(0.45) - (REC2020_ALPHA - 1.))
+    } else {
+        value * 4.5
+    }
+}
+
+fn xyz_d50_to_lab(xyz: [f64; 3]) -> [f64; 3] {
+    const EPSILON: f64 = 216. / 24389.;
+    const KAPPA: f64 = 24389. / 27.;
+    let [fx, fy, fz] = [0, 1, 2].map(|i| {
+        let value = xyz[i] / D50_WHITE[i];
+        if value > EPSILON {
+            value.cbrt()
+        } else {
+            (KAPPA * value + 16.) / 116.
+        }
+    });
+    [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+}
+
+fn lab_to_xyz_d50([l, a, b]: [f64; 3]) -> [f64; 3] {
+    const EPSILON: f64 = 216. / 24389.;
+    const KAPPA: f64 = 24389. / 27.;
+    let fy = (l + 16.) / 116.;
+    let fx = a / 500. + fy;
+    let fz = fy - b / 200.;
+    let inverse = |f: f64| {
+        if f.powi(3) > EPSILON {
+            f.powi(3)
+        } else {
+            (116. * f - 16.) / KAPPA
+        }
+    };
+    let y = if l > KAPPA * EPSILON {
+        fy.powi(3)
+    } else {
+        l / KAPPA
+    };
+    [
+        inverse(fx) * D50_WHITE[0],
+        y * D50_WHITE[1],
+        inverse(fz) * D50_WHITE[2],
+    ]
+}
+
+fn lch_to_lab([l, c, h]: [f64; 3]) -> [f64; 3] {
+    let h = if h.is_nan() { 0. } else { h.to_radians() };
+    [l, c * h.cos(), c * h.sin()]
+}
+
+/// The hue is missing when the chroma is below `epsilon`.
+fn lab_to_lch([l, a, b]: [f64; 3], epsilon: f64) -> [f64; 3] {
+    let c = a.hypot(b);
+    let h = if c < epsilon {
+        f64::NAN
+    } else {
+        b.atan2(a).to_degrees().rem_euclid(360.)
+    };
+    [l, c, h]
+}
+
+fn hsl_to_srgb([h, s, l]: [f64; 3]) -> [f64; 3] {
+    let h = if h.is_nan() { 0. } else { h.rem_euclid(360.) };
+    let (s, l) = (s / 100., l / 100.);
+    let f = |n: f64| {
+        let k = (n + h / 30.) % 12.;
+        let a = s * l.min(1. - l);
+        l - a * (k - 3.).min(9. - k).clamp(-1., 1.)
+    };
+    [f(0.), f(8.), f(4.)]
+}
+
+fn srgb_to_hsl([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (min + max) / 2.;
+    let d = max - min;
+    let (mut h, mut s) = (f64::NAN, 0.);
+    if d.abs() > 1e-9 {
+        s = if l == 0. || l == 1. {
+            0.
+        } else {
+            (max - l) / l.min(1. - l)
+        };
+        h = if max == r {
+            (g - b) / d + if g < b { 6. } else { 0. }
+        } else if max == g {
+            (b - r) / d + 2.
+        } else {
+            (r - g) / d + 4.
+        } * 60.;
+    }
+    if s < 0. {
+        h += 180.;
+        s = s.abs();
+    }
+    [h.rem_euclid(360.), s * 100., l * 100.]
+}
+
+fn hwb_to_srgb([h, w, b]: [f64; 3]) -> [f64; 3] {
+    let (w, b) = (w / 100., b / 100.);
+    if w + b >= 1. {
+        return [w / (w + b); 3];
+    }
+    hsl_to_srgb([h, 100., 50.]).map(|value| value * (1. - w - b) + w)
+}
+
+fn srgb_to_hwb(rgb: [f64; 3]) -> [f64; 3] {
+    let [h, ..] = srgb_to_hsl(rgb);
+    let white = rgb[0].min(rgb[1]).min(rgb[2]);
+    let black = 1. - rgb[0].max(rgb[1]).max(rgb[2]);
+    [h, white * 100., black * 100.]
+}
+
+/// A color in a space, the missing (`none`) components are `NaN`.
+#[derive(Debug, Clone, Copy)]
+struct CssColor {
+    space: Space,
+    coords: [f64; 3],
+    alpha: f64,
+}
+
+/// How a color out of the sRGB gamut was mapped for display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GamutMapping {
+    /// The channels were clipped, as the difference is not noticeable.
+    Clipped,
+    /// The OKLCH chroma was reduced, keeping the lightness and hue, then clipped.
+    Chroma { from: f64, to: f64 },
+}
+
+impl fmt::Display for GamutMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clipped => write!(f, "Out of the sRGB gamut, clipped for display."),
+            Self::Chroma { from, to } => write!(
+                f,
+                "Out of the sRGB gamut, mapped for display by reducing the OKLCH chroma from {} to {}.",
+                format_trimmed(*from as f32, 3, true),
+                format_trimmed(*to as f32, 3, true)
+            ),
+        }
+    }
+}
+
+impl CssColor {
+    fn from_srgb(color: &Color) -> Self {
+        Self {
+            space: Space::Srgb,
+            coords: [color.r, color.g, color.b].map(f64::from),
+            alpha: color.a as f64,
+        }
+    }
+
+    fn convert(&self, space: Space) -> Self {
+        if self.space == space {
+            return *self;
+        }
+        Self {
+            space,
+            coords: space.xyz_to_coords(self.space.coords_to_xyz(self.coords)),
+            alpha: self.alpha,
+        }
+    }
+
+    /// The sRGB color for display, mapped into the gamut by the CSS Color 4 algorithm,
+    /// see <https://www.w3.org/TR/css-color-4/#binsearch>.
+    fn to_srgb(self) -> (Color, Option<GamutMapping>) {
+        let alpha = if self.alpha.is_nan() {
+            0.
+        } else {
+            self.alpha.clamp(0., 1.)
+        };
+        let srgb = |[r, g, b]: [f64; 3]| Color::new(r as f32, g as f32, b as f32, alpha as f32);
+
+        let rgb = self
+            .convert(Space::Srgb)
+            .coords
+            .map(|value| if value.is_nan() { 0. } else { value });
+        if in_gamut(rgb) {
+            return (srgb(clip(rgb)), None);
+        }
+
+        let [l, c, h] = self.convert(Space::Oklch).coords;
+        if l >= 1. || l <= 0. {
+            return (srgb(clip(rgb)), Some(GamutMapping::Clipped));
+        }
+
+        const JND: f64 = 0.02;
+        const EPSILON: f64 = 0.0001;
+        let to_rgb = |c: f64| Space::Oklch.coords_to_xyz([l, c, h]);
+        let delta_e = |xyz: [f64; 3], clipped: [f64; 3]| {
+            let a = Space::Oklab.xyz_to_coords(xyz);
+            let b = Space::Oklab.xyz_to_coords(Space::Srgb.coords_to_xyz(clipped));
+            ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+        };
+
+        let mut clipped = clip(rgb);
+        if delta_e(self.convert(Space::XyzD65).coords, clipped) < JND {
+            return (srgb(clipped), Some(GamutMapping::Clipped));
+        }
+
+        let (mut min, mut max) = (0., c);
+        let mut min_in_gamut = true;
+        let mut chroma = c;
+        while max - min > EPSILON {
+            chroma = (min + max) / 2.;
+            let xyz = to_rgb(chroma);
+            let rgb = Space::Srgb.xyz_to_coords(xyz);
+            if min_in_gamut && in_gamut(rgb) {
+                min = chroma;
+                continue;
+            }
+            clipped = clip(rgb);
+            let e = delta_e(xyz, clipped);
+            if e < JND {
+                if JND - e < EPSILON {
+                    break;
+                }
+                min_in_gamut = false;
+                min = chroma;
+            } else {
+                max = chroma;
+            }
+        }
+
+        let mapping = GamutMapping::Chroma {
+            from: c,
+            to: chroma,
+        };
+        (srgb(clipped), Some(mapping))
+    }
+}
+
+fn in_gamut(rgb: [f64; 3]) -> bool {
+    const EPSILON: f64 = 0.00001;
+    rgb.iter()
+        .all(|value| (-EPSILON..=1. + EPSILON).contains(value))
+}
+
+fn clip(rgb: [f64; 3]) -> [f64; 3] {
+    rgb.map(|value| value.clamp(0., 1.))
+}
+
+/// Match a CSS Color 4/5 function whose `(` is at `paren`, i.e. `color()`, `color-mix()`
+/// or a relative color like `rgb(from #336699 r g calc(b + 20))`, returns the byte range
+/// of the call and the color.
+pub(crate) fn match_css_color(line_text: &str, paren: usize) -> Option<(Range<usize>, Color)> {
+    let bytes = line_text.as_bytes();
+    let start = bytes[..paren]
+        .iter()
+        .rposition(|b| !(b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_'))
+        .map_or(0, |ix| ix + 1);
+    if !FUNCTIONS.contains(&line_text[start..paren].to_ascii_lowercase().as_str()) {
+        return None;
+    }
+
+    let mut depth = 0;
+    let close = bytes[paren..].iter().take(MAX_CALL_LEN).position(|b| {
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })? + paren;
+
+    let (color, _) = evaluate(&line_text[start..=close])?;
+    Some((start..close + 1, color))
+}
+
+/// Evaluate a CSS Color 4/5 function, i.e. `color()`, `color-mix()` or a relative color,
+/// returns the sRGB color, and how it was mapped if it's out of the sRGB gamut.
+pub(crate) fn evaluate(text: &str) -> Option<(Color, Option<GamutMapping>)> {
+    let (name, _) = split_function(text)?;
+    if name != "color" && !is_expression(text) {
+        return None;
+    }
+
+    Some(parse_color(text)?.to_srgb())
+}
+
+/// Whether the color is computed from other colors, i.e. `color-mix()` or a relative color
+/// like `rgb(from red r g b)`, so it can't be rewritten in another notation.
+pub(crate) fn is_expression(text: &str) -> bool {
+    split_function(text).is_some_and(|(name, args)| {
+        name == "color-mix"
+            || args
+                .trim_start()
+                .split_once(char::is_whitespace)
+                .is_some_and(|(first, _)| first.eq_ignore_ascii_case("from"))
+    })
+}
+
+/// The predefined space of an absolute `color()`, e.g. `display-p3` of `color(display-p3 1 0 0)`.
+pub(crate) fn predefined_space(text: &str) -> Option<Space> {
+    let (name, args) = split_function(text)?;
+    if name != "color" {
+        return None;
+    }
+    let space = Space::from_name(tokens(args).first()?)?;
+    space.is_predefined().then_some(space)
+}
+
+/// Write a color with `color()` in a predefined space, e.g. `color(display-p3 1 0 0)`.
+pub(crate) fn format_predefined(space: Space, color: &Color) -> String {
+    let [x, y, z] = CssColor::from_srgb(color).convert(space).coords;
+    let coords = [x, y, z].map(|value| format_trimmed(value as f32, 4, true));
+    if color.a < 1. {
+        format!(
+            "color({} {} / {})",
+            space.name(),
+            coords.join(" "),
+            format_trimmed(color.a, 3, true)
+        )
+    } else {
+        format!("color({} {})", space.name(), coords.join(" "))
+    }
+}
+
+/// Split a function call into the lowercase name and the arguments.
+fn split_function(text: &str) -> Option<(String, &str)> {
+    let text = text.trim();
+    let open = text.find('(')?;
+    let args = text[open + 1..].strip_suffix(')')?;
+    Some((text[..open].trim_end().to_ascii_lowercase(), args))
+}
+
+/// Split the arguments by `sep` at the top level, outside of the nested functions.
+fn split_top_level(args: &str, sep: u8) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (ix, b) in args.bytes().enumerate() {
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ if b == sep && depth == 0 => {
+                parts.push(args[start..ix].trim());
+                start = ix + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// Split the arguments by whitespace at the top level, with the `/` as a token.
+fn tokens(args: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut depth = 0;
+    let mut start = None;
+    for (ix, b) in args.bytes().enumerate() {
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ => {}
+        }
+        let is_separator = depth == 0 && (b.is_ascii_whitespace() || b == b'/');
+        match (is_separator, start) {
+            (true, Some(s)) => {
+                tokens.push(&args[s..ix]);
+                start = None;
+            }
+            (false, None) => start = Some(ix),
+            _ => {}
+        }
+        if depth == 0 && b == b'/' {
+            tokens.push("/");
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&args[s..]);
+    }
+    tokens
+}
+
+/// A channel of a color function.
+#[derive(Debug, Clone, Copy)]
+struct Channel {
+    /// The keyword of the channel in the relative colors, e.g. `r`.
+    keyword: &'static str,
+    /// The value of `100%`.
+    percent: f64,
+    hue: bool,
+}
+
+const fn channel(keyword: &'static str, percent: f64) -> Channel {
+    Channel {
+        keyword,
+        percent,
+        hue: false,
+    }
+}
+
+const fn hue(keyword: &'static str) -> Channel {
+    Channel {
+        keyword,
+        percent: 0.,
+        hue: true,
+    }
+}
+
+const ALPHA: Channel = channel("alpha", 1.);
+
+/// The space, the channels and the scale of the channel values of a function, e.g. the
+/// values of `rgb()` are in 0..255.
+fn function_channels(name: &str) -> Option<(Space, [Channel; 3], f64)> {
+    let channels = match name {
+        "rgb" | "rgba" => (
+            Space::Srgb,
+            [channel("r", 255.), channel("g", 255.), channel("b", 255.)],
+            255.,
+        ),
+        "hsl" | "hsla" => (
+            Space::Hsl,
+            [hue("h"), channel("s", 100.), channel("l", 100.)],
+            1.,
+        ),
+        "hwb" => (
+            Space::Hwb,
+            [hue("h"), channel("w", 100.), channel("b", 100.)],
+            1.,
+        ),
+        "lab" => (
+            Space::Lab,
+            [channel("l", 100.), channel("a", 125.), channel("b", 125.)],
+            1.,
+        ),
+        "lch" => (
+            Space::Lch,
+            [channel("l", 100.), channel("c", 150.), hue("h")],
+            1.,
+        ),
+        "oklab" => (
+            Space::Oklab,
+            [channel("l", 1.), channel("a", 0.4), channel("b", 0.4)],
+            1.,
+        ),
+        "oklch" => (
+            Space::Oklch,
+            [channel("l", 1.), channel("c", 0.4), hue("h")],
+            1.,
+        ),
+        _ => return None,
+    };
+    Some(channels)
+}
+
+/// Parse a color, the CSS Color 4/5 functions are evaluated in their spaces,
+/// the others are parsed by csscolorparser in sRGB.
+fn parse_color(text: &str) -> Option<CssColor> {
+    let text = text.trim();
+    let parsed = split_function(text).and_then(|(name, args)| match name.as_str() {
+        "color-mix" => parse_color_mix(args),
+        _ => parse_function(&name, args),
+    });
+    parsed.or_else(|| {
+        // The colors are ASCII, csscolorparser may panic on slicing multi-byte chars.
+        let color = csscolorparser::parse(text)
+            .ok()
+            .filter(|_| text.is_ascii())?;
+        Some(CssColor::from_srgb(&color))
+    })
+}
+
+/// Parse a function in the modern syntax, absolute or relative, e.g. `lab(50 20 -30)`,
+/// `color(display-p3 1 0 0)` or `hsl(from red calc(h + 120) s l)`.
+fn parse_function(name: &str, args: &str) -> Option<CssColor> {
+    let mut tokens = tokens(args);
+    let origin = match tokens.first() {
+        Some(first) if first.eq_ignore_ascii_case("from") => {
+            let origin = parse_color(tokens.get(1)?)?;
+            tokens.drain(..2);
+            Some(origin)
+        }
+        _ => None,
+    };
+
+    let (space, channels, scale) = if name == "color" {
+        let space = Space::from_name(tokens.first()?)?;
+        if !space.is_predefined() {
+            return None;
+        }
+        tokens.remove(0);
+        let channels = match space {
+            Space::XyzD50 | Space::XyzD65 => [channel("x", 1.), channel("y", 1.), channel("z", 1.)],
+            _ => [channel("r", 1.), channel("g", 1.), channel("b", 1.)],
+        };
+        (space, channels, 1.)
+    } else {
+        function_channels(name)?
+    };
+
+    // The channel values of the origin, by keyword.
+    let mut variables = vec![];
+    if let Some(origin) = origin {
+        let origin = origin.convert(space);
+        for (channel, value) in channels.iter().zip(origin.coords) {
+            variables.push((channel.keyword, zero_if_nan(value) * scale));
+        }
+        variables.push((ALPHA.keyword, zero_if_nan(origin.alpha)));
+    }
+
+    let (values, alpha) = match tokens.as_slice() {
+        [x, y, z] => ([*x, *y, *z], None),
+        [x, y, z, "/", alpha] => ([*x, *y, *z], Some(*alpha)),
+        _ => return None,
+    };
+    let mut coords = [0.; 3];
+    for (ix, (value, channel)) in values.iter().zip(channels.iter()).enumerate() {
+        coords[ix] = resolve(value, channel, &variables)? / scale;
+    }
+    let alpha = match alpha {
+        Some(alpha) => resolve(alpha, &ALPHA, &variables)?,
+        None => variables
+            .iter()
+            .find(|(keyword, _)| *keyword == ALPHA.keyword)
+            .map_or(1., |(_, value)| *value),
+    };
+
+    Some(CssColor {
+        space,
+        coords,
+        alpha,
+    })
+}
+
+fn zero_if_nan(value: f64) -> f64 {
+    if value.is_nan() {
+        0.
+    } else {
+        value
+    }
+}
+
+/// Resolve a channel value: `none`, a keyword of the relative colors, a number, a percentage,
+/// an angle for the hue, or a `calc()` of them.
+fn resolve(value: &str, channel: &Channel, variables: &[(&str, f64)]) -> Option<f64> {
+    if value.eq_ignore_ascii_case("none") {
+        return Some(f64::NAN);
+    }
+    if let Some((_, value)) = variables
+        .iter()
+        .find(|(keyword, _)| value.eq_ignore_ascii_case(keyword))
+    {
+        return Some(*value);
+    }
+    if let Some(expression) = value
+        .strip_prefix("calc(")
+        .and_then(|value| value.strip_suffix(')'))
+    {
+        return Calc::new(expression, channel, variables).evaluate();
+    }
+    number(value, channel)
+}
+
+/// Parse a number, a percentage of the channel, or an angle in degrees for the hue.
+fn number(value: &str, channel: &Channel) -> Option<f64> {
+    if let Some(percent) = value.strip_suffix('%') {
+        if channel.hue {
+            return None;
+        }
+        return percent
+            .parse::<f64>()
+            .ok()
+            .map(|v| v / 100. * channel.percent);
+    }
+
+    let units = [
+        ("deg", 1.),
+        ("grad", 0.9),
+        ("rad", 180. / std::f64::consts::PI),
+        ("turn", 360.),
+    ];
+    if channel.hue {
+        for (unit, factor) in units {
+            if let Some(angle) = value.strip_suffix(unit) {
+                return angle.parse::<f64>().ok().map(|v| v * factor);
+            }
+        }
+    }
+    value.parse().ok()
+}
+
+/// A `calc()` expression of numbers, keywords and `+ - * /` with parentheses.
+struct Calc<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+    channel: &'a Channel,
+    variables: &'a [(&'a str, f64)],
+}
+
+impl<'a> Calc<'a> {
+    fn new(expression: &'a str, channel: &'a Channel, variables: &'a [(&'a str, f64)]) -> Self {
+        let mut tokens = vec![];
+        let bytes = expression.as_bytes();
+        let mut ix = 0;
+        while ix < bytes.len() {
+            let b = bytes[ix];
+            let is_operand = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'%' | b'_');
+            // A sign is a part of the number after an operator, e.g. `-1` in `b * -1`.
+            let is_sign = matches!(b, b'+' | b'-')
+                && bytes
+                    .get(ix + 1)
+                    .is_some_and(|next| next.is_ascii_digit() || *next == b'.')
+                && tokens
+                    .last()
+                    .is_none_or(|last| matches!(*last, "+" | "-" | "*" | "/" | "("));
+            if b.is_ascii_whitespace() {
+                ix += 1;
+            } else if is_operand(b) || is_sign {
+                let len = bytes[ix + 1..]
+                    .iter()
+                    .take_while(|b| is_operand(**b))
+                    .count();
+                let token = &expression[ix..ix + 1 + len];
+                ix += 1 + len;
+                // The nested `calc(` is a parenthesis.
+                if token.eq_ignore_ascii_case("calc") && bytes.get(ix) == Some(&b'(') {
+                    continue;
+                }
+                tokens.push(token);
+            } else {
+                // An operator or a parenthesis, any other char is kept whole to fail the parsing.
+                let len = expression[ix..].chars().next().map_or(1, char::len_utf8);
+                tokens.push(&expression[ix..ix + len]);
+                ix += len;
+            }
+        }
+
+        Self {
+            tokens,
+            pos: 0,
+            channel,
+            variables,
+        }
+    }
+
+    fn evaluate(mut self) -> Option<f64> {
+        let value = self.sum()?;
+        (self.pos == self.tokens.len()).then_some(value)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn sum(&mut self) -> Option<f64> {
+        let mut value = self.product()?;
+        while let Some(op @ ("+" | "-")) = self.tokens.get(self.pos).copied() {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = if op == "+" { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    fn product(&mut self) -> Option<f64> {
+        let mut value = self.operand()?;
+        while let Some(op @ ("*" | "/")) = self.tokens.get(self.pos).copied() {
+            self.pos += 1;
+            let rhs = self.operand()?;
+            value = if op == "*" { value * rhs } else { value / rhs };
+        }
+        Some(value)
+    }
+
+    fn operand(&mut self) -> Option<f64> {
+        match self.next()? {
+            "(" => {
+                let value = self.sum()?;
+                (self.next()? == ")").then_some(value)
+            }
+            "-" => self.operand().map(|value| -value),
+            token => resolve(token, self.channel, self.variables).filter(|v| !v.is_nan()),
+        }
+    }
+}
+
+/// The hue interpolation method of `color-mix()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HueMethod {
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+/// Parse `color-mix(in <space> [<method> hue], <color> [<percentage>], <color> [<percentage>])`.
+fn parse_color_mix(args: &str) -> Option<CssColor> {
+    let [interpolation, first, second] = split_top_level(args, b',').try_into().ok()?;
+    let (space, method) = match tokens(interpolation).as_slice() {
+        ["in", space] => (Space::from_name(space)?, HueMethod::Shorter),
+        ["in", space, method, "hue"] => {
+            let method = match method.to_ascii_lowercase().as_str() {
+                "shorter" => HueMethod::Shorter,
+                "longer" => HueMethod::Longer,
+                "increasing" => HueMethod::Increasing,
+                "decreasing" => HueMethod::Decreasing,
+                _ => return None,
+            };
+            let space = Space::from_name(space)?;
+            space.hue_index()?;
+            (space, method)
+        }
+        _ => return None,
+    };
+
+    let (first, p1) = parse_mix_color(first)?;
+    let (second, p2) = parse_mix_color(second)?;
+    // Normalize the percentages, a sum below 100% makes the result translucent.
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (0.5, 0.5),
+        (Some(p1), None) => (p1, 1. - p1),
+        (None, Some(p2)) => (1. - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let sum = p1 + p2;
+    if sum <= 0. {
+        return None;
+    }
+    let alpha_multiplier = sum.min(1.);
+    let (p1, p2) = (p1 / sum, p2 / sum);
+
+    let a = first.convert(space);
+    let b = second.convert(space);
+    let (mut ca, mut cb) = (a.coords, b.coords);
+    // A missing component takes the value of the other color.
+    for ix in 0..3 {
+        if ca[ix].is_nan() {
+            ca[ix] = cb[ix];
+        }
+        if cb[ix].is_nan() {
+            cb[ix] = ca[ix];
+        }
+    }
+    let (alpha_a, alpha_b) = match (a.alpha.is_nan(), b.alpha.is_nan()) {
+        (true, true) => (1., 1.),
+        (true, false) => (b.alpha, b.alpha),
+        (false, true) => (a.alpha, a.alpha),
+        (false, false) => (a.alpha, b.alpha),
+    };
+
+    let hue_index = space.hue_index();
+    if let Some(ix) = hue_index {
+        fix_hues(&mut ca[ix], &mut cb[ix], method);
+    }
+
+    let alpha = alpha_a * p1 + alpha_b * p2;
+    let mut coords = [0.; 3];
+    for ix in 0..3 {
+        coords[ix] = if Some(ix) == hue_index {
+            (ca[ix] * p1 + cb[ix] * p2).rem_euclid(360.)
+        } else if alpha == 0. {
+            ca[ix] * p1 + cb[ix] * p2
+        } else {
+            // Interpolated with the premultiplied alpha.
+            (ca[ix] * alpha_a * p1 + cb[ix] * alpha_b * p2) / alpha
+        };
+    }
+
+    Some(CssColor {
+        space,
+        coords,
+        alpha: alpha * alpha_multiplier,
+    })
+}
+
+/// Parse a color of `color-mix()` with the optional percentage before or after it,
+/// e.g. `red 40%`, the percentage is in 0..1.
+fn parse_mix_color(text: &str) -> Option<(CssColor, Option<f64>)> {
+    let percentage = |token: &str| {
+        let value = token.strip_suffix('%')?.parse::<f64>().ok()?;
+        Some((0.0..=100.).contains(&value).then_some(value / 100.))
+    };
+    let tokens = tokens(text);
+    let (color, percent) = match tokens.as_slice() {
+        [color] => (*color, None),
+        [color, last] => match (percentage(last), percentage(color)) {
+            (Some(percent), _) => (*color, Some(percent?)),
+            (None, Some(percent)) => (*last, Some(percent?)),
+            (None, None) => return None,
+        },
+        _ => return None,
+    };
+    Some((parse_color(color)?, percent))
+}
+
+/// Adjust the hues to interpolate by the method, see
+/// <https://www.w3.org/TR/css-color-4/#hue-interpolation>.
+fn fix_hues(h1: &mut f64, h2: &mut f64, method: HueMethod) {
+    if h1.is_nan() || h2.is_nan() {
+        return;
+    }
+    let diff = *h2 - *h1;
+    match method {
+        HueMethod::Shorter if diff > 180. => *h1 += 360.,
+        HueMethod::Shorter if diff < -180. => *h2 += 360.,
+        HueMethod::Longer if diff > 0. && diff < 180. => *h1 += 360.,
+        HueMethod::Longer if diff > -180. && diff <= 0. => *h2 += 360.,
+        HueMethod::Increasing if diff < 0. => *h2 += 360.,
+        HueMethod::Decreasing if diff > 0. => *h1 += 360.,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csscolorparser::Color;
+
+    use super::{evaluate, format_predefined, match_css_color, predefined_space, GamutMapping};
+    use super::{parse_color, Space};
+
+    fn hex(text: &str) -> String {
+        evaluate(text).unwrap().0.to_css_hex()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(hex("color(srgb 0.2 0.4 0.6)"), "#336699");
+        assert_eq!(hex("color(srgb 20% 40% 60% / 50%)"), "#33669980");
+        assert_eq!(hex("color(srgb-linear 0.2 0.4 0.6)"), "#7caacb");
+        assert_eq!(hex("color(display-p3 0.2 0.4 0.6)"), "#1b689d");
+        assert_eq!(hex("color(xyz-d65 0.9505 1 1.089)"), "#ffffff");
+        assert_eq!(hex("color(xyz-d50 0.9642 1 0.8252)"), "#ffffff");
+        assert_eq!(hex("color(rec2020 1 1 1)"), "#ffffff");
+        assert_eq!(hex("color(a98-rgb 1 1 1)"), "#ffffff");
+        assert_eq!(hex("color(prophoto-rgb 1 1 1)"), "#ffffff");
+
+        assert_eq!(hex("color-mix(in srgb, red, blue)"), "#800080");
+        assert_eq!(hex("color-mix(in srgb, red 25%, blue)"), "#4000bf");
+        assert_eq!(hex("color-mix(in srgb, 25% red, blue)"), "#4000bf");
+        assert_eq!(hex("color-mix(in srgb, red 20%, blue 20%)"), "#80008066");
+        assert_eq!(hex("color-mix(in srgb, red, transparent)"), "#ff000080");
+        assert_eq!(
+            hex("color-mix(in hsl longer hue, hsl(0 100% 50%), hsl(120 100% 50%))"),
+            "#0000ff"
+        );
+        assert_eq!(
+            hex("color-mix(in srgb, color-mix(in srgb, red, blue), rgb(0 255 0))"),
+            "#408040"
+        );
+
+        assert_eq!(hex("rgb(from #336699 r g calc(b + 20))"), "#3366ad");
+        assert_eq!(hex("rgb(from #336699 r g b / 50%)"), "#33669980");
+        assert_eq!(hex("hsl(from red calc(h + 120) s l)"), "#00ff00");
+        assert_eq!(hex("hwb(from red h w b)"), "#ff0000");
+        assert_eq!(hex("lab(from #336699 l a b)"), "#336699");
+        assert_eq!(hex("oklch(from #336699 l c h)"), "#336699");
+        assert_eq!(hex("oklch(from #336699 calc(l * 2) 0 h)"), "#ffffff");
+        assert_eq!(hex("color(from red srgb calc(r - 0.2) g b)"), "#cc0000");
+        assert_eq!(
+            hex("rgb(from rgb(from red r 255 b) calc((r + g) / 2) 0 0)"),
+            "#ff0000"
+        );
+
+        for text in [
+            "rgb(255, 0, 0)",
+            "color(unknown 1 0 0)",
+            "color(lab 50 0 0)",
+            "color(srgb 1 0)",
+            "color-mix(in srgb, red)",
+            "color-mix(in srgb longer hue, red, blue)",
+            "color-mix(in srgb, red 0%, blue 0%)",
+            "color-mix(in srgb, red 120%, blue)",
+            "rgb(from var(--brand) r g b)",
+            "rgb(from red r g calc(b +))",
+        ] {
+            assert_eq!(evaluate(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_gamut_mapping() {
+        let (color, mapping) = evaluate("color(display-p3 1 0 0)").unwrap();
+        let Some(GamutMapping::Chroma { from, to }) = mapping else {
+            panic!("{:?}", mapping);
+        };
+        assert!(from > to, "{} {}", from, to);
+        assert_eq!(color.to_rgba8()[0], 255);
+        assert_eq!(
+            mapping.unwrap().to_string(),
+            format!(
+                "Out of the sRGB gamut, mapped for display by reducing the OKLCH chroma from {} to {}.",
+                format_trimmed(from as f32),
+                format_trimmed(to as f32)
+            )
+        );
+
+        let (_, mapping) = evaluate("color(srgb 1.001 0 0)").unwrap();
+        assert_eq!(mapping, Some(GamutMapping::Clipped));
+        let (color, mapping) = evaluate("color(srgb 1.5 1.5 1.5)").unwrap();
+        assert_eq!(mapping, Some(GamutMapping::Clipped));
+        assert_eq!(color.to_css_hex(), "#ffffff");
+        assert_eq!(evaluate("color(display-p3 0.5 0.5 0.5)").unwrap().1, None);
+
+        let (_, mapping) = evaluate("color-mix(in oklch, red 40%, blue)").unwrap();
+        let mix = parse_color("color-mix(in oklch, red 40%, blue)").unwrap();
+        assert_eq!(mix.space, Space::Oklch);
+        let [l, c, h] = mix.coords;
+        assert!((l - 0.5224).abs() < 0.001, "{}", l);
+        assert!((c - 0.2907).abs() < 0.001, "{}", c);
+        assert!((h - 314.1).abs() < 0.1, "{}", h);
+        assert!(mapping.is_some());
+
+        fn format_trimmed(value: f32) -> String {
+            crate::utils::format_trimmed(value, 3, true)
+        }
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        assert_eq!(evaluate("rgb(from red r g calc(b × 2))"), None);
+        assert_eq!(evaluate("rgb(from red r g calc(b * 2é))"), None);
+        assert_eq!(evaluate("color(display-p3 1 0 0é)"), None);
+        assert_eq!(evaluate("color-mix(in srgb, rouge✓, blue)"), None);
+
+        assert_eq!(evaluate("hsl(from red calc(h + 120°) s l)"), None);
+        assert_eq!(evaluate("rgb(from 红 r g b)"), None);
+        assert_eq!(evaluate("color(srgb 1 0 0 / ５0%)"), None);
+        assert_eq!(evaluate("color-mix(in srgb, red, blue ５0%)"), None);
+
+        let line = "a { color: rgb(from red r g calc(b × 2)); }";
+        assert_eq!(match_css_color(line, line.find('(').unwrap()), None);
+        assert_eq!(crate::parser::parse(line).len(), 1);
+
+        let line = "é { color: color(srgb 1 0 0) }";
+        let (range, _) = match_css_color(line, line.find('(').unwrap()).unwrap();
+        assert_eq!(&line[range], "color(srgb 1 0 0)");
+    }
+
+    #[test]
+    fn test_none() {
+        assert_eq!(hex("color(srgb none 0.4 0.6)"), "#006699");
+        assert_eq!(
+            hex("oklch(from #336699 l c none)"),
+            hex("oklch(from #336699 l c 0)")
+        );
+        // The missing component takes the value of the other color.
+        assert_eq!(
+            hex("color-mix(in srgb, color(srgb none 0 0), color(srgb 1 0 0))"),
+            "#ff0000"
+        );
+        assert_eq!(
+            hex("color-mix(in hsl, hsl(none 100% 50%), hsl(120 100% 50%))"),
+            "#00ff00"
+        );
+
+        assert_eq!(evaluate("rgb(from red r g calc(none + 1))"), None);
+        assert_eq!(evaluate("rgb(from red r g calc(b + none))"), None);
+    }
+
+    #[test]
+    fn test_hue_interpolation() {
+        let mix = |method: &str, from: u32, to: u32| {
+            hex(&format!(
+                "color-mix(in hsl{method}, hsl({from} 100% 50%), hsl({to} 100% 50%))"
+            ))
+        };
+        assert_eq!(mix("", 0, 120), "#ffff00");
+        assert_eq!(mix("", 30, 330), "#ff0000");
+        assert_eq!(mix(" shorter hue", 0, 120), "#ffff00");
+        assert_eq!(mix(" shorter hue", 30, 330), "#ff0000");
+        assert_eq!(mix(" increasing hue", 0, 120), "#ffff00");
+        assert_eq!(mix(" increasing hue", 120, 0), "#0000ff");
+        assert_eq!(mix(" decreasing hue", 0, 120), "#0000ff");
+        assert_eq!(mix(" decreasing hue", 120, 0), "#ffff00");
+        assert_eq!(evaluate("color-mix(in hsl sideways hue, red, blue)"), None);
+    }
+
+    #[test]
+    fn test_malformed_relative() {
+        for text in [
+            "rgb(from)",
+            "rgb(from red)",
+            "rgb(from red r g)",
+            "rgb(from red r g b b)",
+            "rgb(from notacolor r g b)",
+            "rgb(from from red r g b)",
+            "rgb(from red, r, g, b)",
+            "rgb(from red x g b)",
+            "rgb(from red r g b /)",
+            "rgb(from red r g b / 50% 1)",
+            "rgb(from red r g calc())",
+            "rgb(from red r g calc(b * (2))",
+            "color(from red r g b)",
+            "color(from red unknown r g b)",
+        ] {
+            assert_eq!(evaluate(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_match_css_color() {
+        let line = "a { color: color-mix(in srgb, red 40%, var(--x)); b: rgb(from red r g calc(b + 20)); }";
+        let matched = |paren: usize| match_css_color(line, paren).map(|(range, _)| &line[range]);
+        assert_eq!(matched(line.find("mix(").unwrap() + 3), None);
+        let paren = line.find("rgb(from").unwrap() + 3;
+        assert_eq!(matched(paren), Some("rgb(from red r g calc(b + 20))"));
+
+        let line = "x: xcolor(srgb 1 0 0), color(srgb 1 0 0)";
+        assert_eq!(match_css_color(line, line.find('(').unwrap()), None);
+        let paren = line.rfind('(').unwrap();
+        assert_eq!(
+            match_css_color(line, paren).map(|(range, color)| (&line[range], color)),
+            Some(("color(srgb 1 0 0)", Color::new(1., 0., 0., 1.)))
+        );
+    }
+
+    #[test]
+    fn test_format_predefined() {
+        assert_eq!(
+            predefined_space("color(display-p3 1 0 0)"),
+            Some(Space::DisplayP3)
+        );
+        assert_eq!(predefined_space("color(from red srgb r g b)"), None);
+        assert_eq!(predefined_space("color(oklch 1 0 0)"), None);
+
+        let color = Color::from_rgba8(51, 102, 153, 255);
+        assert_eq!(
+            format_predefined(Space::Srgb, &color),
+            "color(srgb 0.2 0.4 0.6)"
+        );
+        let text = format_predefined(Space::DisplayP3, &color);
+        assert_eq!(hex(&text), "#336699");
+        assert_eq!(
+            format_predefined(Space::Srgb, &Color::new(1., 0., 0., 0.5)),
+            "color(srgb 1 0 0 / 0.5)"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use csscolorparser::Color;
+
+/// The CIEDE2000 difference of two colors, ignoring the alpha, see [`ciede2000_lab`].
+pub(crate) fn ciede2000(a: &Color, b: &Color) -> f32 {
+    ciede2000_lab(lab(a), lab(b)) as f32
+}
+
+fn lab(color: &Color) -> [f64; 3] {
+    let [l, a, b, _] = color.to_laba();
+    [l as f64, a as f64, b as f64]
+}
+
+/// The CIEDE2000 difference of two Lab colors, see <https://hajim.rochester.edu/ece/sites/gsharma/ciede2000/>.
+///
+/// Below 1 the difference is not perceptible, around 2 it is only noticed side by side.
+fn ciede2000_lab([l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]) -> f64 {
+    let pow7_25 = 25f64.powi(7);
+    let c_bar = (a1.hypot(b1) + a2.hypot(b2)) / 2.;
+    let g = 0.5 * (1. - (c_bar.powi(7) / (c_bar.powi(7) + pow7_25)).sqrt());
+    let (a1, a2) = ((1. + g) * a1, (1. + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |b: f64, a: f64| {
+        if a == 0. && b == 0. {
+            0.
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.)
+        }
+    };
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = match h2 - h1 {
+        _ if c1 * c2 == 0. => 0.,
+        diff if diff > 180. => diff - 360.,
+        diff if diff < -180. => diff + 360.,
+        diff => diff,
+    };
+    let delta_h = 2. * (c1 * c2).sqrt() * (delta_h / 2.).to_radians().sin();
+
+    let l_bar = (l1 + l2) / 2.;
+    let c_bar = (c1 + c2) / 2.;
+    let h_bar = if c1 * c2 == 0. {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180. {
+        (h1 + h2) / 2.
+    } else if h1 + h2 < 360. {
+        (h1 + h2 + 360.) / 2.
+    } else {
+        (h1 + h2 - 360.) / 2.
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 1. - 0.17 * cos(h_bar - 30.) + 0.24 * cos(2. * h_bar) + 0.32 * cos(3. * h_bar + 6.)
+        - 0.2 * cos(4. * h_bar - 63.);
+    let delta_theta = 30. * (-((h_bar - 275.) / 25.).powi(2)).exp();
+    let r_c = 2. * (c_bar.powi(7) / (c_bar.powi(7) + pow7_25)).sqrt();
+    let s_l = 1. + 0.015 * (l_bar - 50.).powi(2) / (20. + (l_bar - 50.).powi(2)).sqrt();
+    let s_c = 1. + 0.045 * c_bar;
+    let s_h = 1. + 0.015 * c_bar * t;
+    let r_t = -(2. * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+/// A group of nearly the same colors, to consolidate into the canonical one.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cluster {
+    /// The most used color of the group.
+    pub(crate) canonical: [u8; 4],
+    /// The other colors with their distance to the canonical one.
+    pub(crate) duplicates: Vec<([u8; 4], f32)>,
+}
+
+/// The colors used in the workspace, ranked by their number of usages.
+pub(crate) struct ColorUsages {
+    /// The RGBA, the number of usages and the Lab of the colors, the most used first.
+    colors: Vec<([u8; 4], usize, [f64; 3])>,
+}
+
+impl ColorUsages {
+    pub(crate) fn new(counts: HashMap<[u8; 4], usize>) -> Self {
+        let mut colors = counts
+            .into_iter()
+            .map(|(rgba, count)| {
+                (
+                    rgba,
+                    count,
+                    lab(&Color::from_rgba8(rgba[0], rgba[1], rgba[2], rgba[3])),
+                )
+            })
+            .collect::<Vec<_>>();
+        colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Self { colors }
+    }
+
+    /// The canonical color of a near-duplicate, that is the most used color with the same
+    /// alpha whose distance is below `threshold`, with the distance.
+    ///
+    /// Returns `None` if no color ranks before `rgba` within the threshold.
+    pub(crate) fn canonical(&self, rgba: [u8; 4], threshold: f32) -> Option<([u8; 4], f32)> {
+        let (_, _, lab) = self.colors.iter().find(|(other, ..)| *other == rgba)?;
+        self.colors
+            .iter()
+            .take_while(|(other, ..)| *other != rgba)
+            .filter(|(other, ..)| other[3] == rgba[3])
+            .map(|(other, _, other_lab)| (*other, ciede2000_lab(*other_lab, *lab) as f32))
+            .find(|(_, distance)| *distance < threshold)
+    }
+
+    /// Group the near-duplicates by their canonical color, the most used groups first.
+    pub(crate) fn clusters(&self, threshold: f32) -> Vec<Cluster> {
+        let mut clusters: Vec<Cluster> = vec![];
+        for (rgba, ..) in self.colors.iter() {
+            let Some((canonical, distance)) = self.canonical(*rgba, threshold) else {
+                continue;
+            };
+            match clusters
+                .iter_mut()
+                .find(|cluster| cluster.canonical == canonical)
+            {
+                Some(cluster) => cluster.duplicates.push((*rgba, distance)),
+                None => clusters.push(Cluster {
+                    canonical,
+                    duplicates: vec![(*rgba, distance)],
+                }),
+            }
+        }
+
+        let rank = |rgba: [u8; 4]| self.colors.iter().position(|(other, ..)| *other == rgba);
+        clusters.sort_by_key(|cluster| rank(cluster.canonical));
+        clusters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use csscolorparser::Color;
+
+    use super::{ciede2000_lab, lab, Cluster, ColorUsages};
+
+    #[test]
+    fn test_ciede2000() {
+        // From the test data of Sharma, Wu and Dalal.
+        let pairs = [
+            ([50., 2.6772, -79.7751], [50., 0., -82.7485], 2.0425),
+            ([50., -1., 2.], [50., 0., 0.], 2.3669),
+            ([50., 2.5, 0.], [73., 25., -18.], 27.1492),
+            ([2.0776, 0.0795, -1.135], [0.9033, -0.0636, -0.5514], 0.9082),
+        ];
+        for (a, b, expected) in pairs {
+            let delta_e = ciede2000_lab(a, b);
+            assert!(
+                (delta_e - expected).abs() < 1e-4,
+                "{} != {}",
+                delta_e,
+                expected
+            );
+        }
+
+        let gray = lab(&Color::from_rgba8(0x33, 0x33, 0x33, 255));
+        assert_eq!(ciede2000_lab(gray, gray), 0.);
+        let delta_e = ciede2000_lab(gray, lab(&Color::from_rgba8(0x34, 0x34, 0x34, 255)));
+        assert!(delta_e > 0. && delta_e < 1., "{}", delta_e);
+    }
+
+    #[test]
+    fn test_clusters() {
+        let usages = ColorUsages::new(HashMap::from([
+            ([0x33, 0x33, 0x33, 255], 5),
+            ([0x34, 0x34, 0x34, 255], 2),
+            ([52, 51, 52, 255], 1),
+            ([0x34, 0x34, 0x34, 128], 1),
+            ([0xff, 0, 0, 255], 3),
+            ([0xfe, 0, 0, 255], 3),
+        ]));
+
+        assert_eq!(usages.canonical([0x33, 0x33, 0x33, 255], 2.), None);
+        assert_eq!(
+            usages
+                .canonical([0x34, 0x34, 0x34, 255], 2.)
+                .map(|(rgba, _)| rgba),
+            Some([0x33, 0x33, 0x33, 255])
+        );
+        // A different alpha is not a duplicate.
+        assert_eq!(usages.canonical([0x34, 0x34, 0x34, 128], 2.), None);
+        assert_eq!(usages.canonical([0x34, 0x34, 0x34, 255], 0.1), None);
+
+        let clusters = usages
+            .clusters(2.)
+            .into_iter()
+            .map(
+                |Cluster {
+                     canonical,
+                     duplicates,
+                 }| {
+                    (
+                        canonical,
+                        duplicates.into_iter().map(|(rgba, _)| rgba).collect(),
+                    )
+                },
+            )
+            .collect::<Vec<([u8; 4], Vec<_>)>>();
+        assert_eq!(
+            clusters,
+            [
+                (
+                    [0x33, 0x33, 0x33, 255],
+                    vec![[0x34, 0x34, 0x34, 255], [52, 51, 52, 255]]
+                ),
+                ([0xfe, 0, 0, 255], vec![[0xff, 0, 0, 255]]),
+            ]
+        );
+    }
+}
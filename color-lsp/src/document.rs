@@ -0,0 +1,434 @@
+use std::ops::{Range, RangeInclusive};
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{
+    self, ClientCapabilities, ColorInformation, PositionEncodingKind,
+    TextDocumentContentChangeEvent, TextDocumentItem, Url,
+};
+
+/// The encoding of `Position::character`, negotiated with the client in `initialize`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PositionEncoding {
+    Utf8,
+    /// The LSP default when the client does not send `general.positionEncodings`.
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Pick the first encoding in the client's preference order that we support.
+    pub(crate) fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_ref())
+            .and_then(|encodings| {
+                encodings.iter().find_map(|kind| match kind.as_str() {
+                    "utf-8" => Some(Self::Utf8),
+                    "utf-16" => Some(Self::Utf16),
+                    "utf-32" => Some(Self::Utf32),
+                    _ => None,
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn kind(&self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+            Self::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Convert the byte offset in `line_text` into the character offset in this encoding.
+    pub(crate) fn character(&self, line_text: &str, byte_offset: usize) -> u32 {
+        let text = &line_text[..byte_offset.min(line_text.len())];
+        match self {
+            Self::Utf8 => text.len() as u32,
+            Self::Utf16 => text.encode_utf16().count() as u32,
+            Self::Utf32 => text.chars().count() as u32,
+        }
+    }
+
+    /// Convert the byte range in `line_text` into the LSP range in this encoding.
+    pub(crate) fn range(
+        &self,
+        line_text: &str,
+        line: usize,
+        range: Range<usize>,
+    ) -> lsp_types::Range {
+        lsp_types::Range::new(
+            lsp_types::Position::new(line as u32, self.character(line_text, range.start)),
+            lsp_types::Position::new(line as u32, self.character(line_text, range.end)),
+        )
+    }
+}
+
+/// An opened text document, backed by a rope to apply incremental edits cheaply.
+///
+/// Only `\n` breaks the lines, a lone `\r` is a part of the line, as in `str::lines()` of the
+/// scanners, so the lines of an edit match the lines of a full scan.
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub(crate) uri: Url,
+    pub(crate) language_id: String,
+    pub(crate) version: i32,
+    rope: Rope,
+}
+
+/// The lines touched by an edit, all 0-based and inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LineEdit {
+    /// The first changed line.
+    pub(crate) start: usize,
+    /// The last changed line before the edit.
+    pub(crate) old_end: usize,
+    /// The last changed line after the edit.
+    pub(crate) new_end: usize,
+}
+
+impl Document {
+    pub(crate) fn new(item: TextDocumentItem) -> Self {
+        Self {
+            uri: item.uri,
+            language_id: item.language_id,
+            version: item.version,
+            rope: Rope::from_str(&item.text),
+        }
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    /// Return the text of the given lines, including the line breaks.
+    pub(crate) fn lines_text(&self, lines: RangeInclusive<usize>) -> String {
+        let start = self
+            .rope
+            .line_to_char(*lines.start().min(&self.rope.len_lines()));
+        let end = self
+            .rope
+            .line_to_char((*lines.end() + 1).min(self.rope.len_lines()));
+
+        self.rope.slice(start..end).to_string()
+    }
+
+    /// Return the text in the given range.
+    pub(crate) fn text_at(&self, range: lsp_types::Range, encoding: PositionEncoding) -> String {
+        let start = self.char_index(range.start, encoding);
+        let end = self.char_index(range.end, encoding).max(start);
+
+        self.rope.slice(start..end).to_string()
+    }
+
+    /// Apply a change from `textDocument/didChange`.
+    ///
+    /// Returns the touched lines for a ranged edit, or `None` when the whole text is replaced.
+    pub(crate) fn apply_change(
+        &mut self,
+        change: &TextDocumentContentChangeEvent,
+        encoding: PositionEncoding,
+    ) -> Option<LineEdit> {
+        let Some(range) = change.range else {
+            self.rope = Rope::from_str(&change.text);
+            return None;
+        };
+
+        let start = self.char_index(range.start, encoding);
+        let end = self.char_index(range.end, encoding).max(start);
+        let start_line = self.rope.char_to_line(start);
+        let old_end = self.rope.char_to_line(end);
+
+        self.rope.remove(start..end);
+        self.rope.insert(start, &change.text);
+
+        let new_end = self.rope.char_to_line(start + change.text.chars().count());
+
+        Some(LineEdit {
+            start: start_line,
+            old_end,
+            new_end,
+        })
+    }
+
+    /// Convert a LSP position in `encoding` into a char index of the rope.
+    fn char_index(&self, position: lsp_types::Position, encoding: PositionEncoding) -> usize {
+        let line = position.line as usize;
+        if line >= self.rope.len_lines() {
+            return self.rope.len_chars();
+        }
+
+        let line_start = self.rope.line_to_char(line);
+        let mut line_text = self.rope.line(line);
+        // Positions after the end of line are clamped to before the line break
+        while line_text
+            .chars_at(line_text.len_chars())
+            .prev()
+            .is_some_and(|c| c == '\n' || c == '\r')
+        {
+            line_text = line_text.slice(..line_text.len_chars() - 1);
+        }
+        let character = position.character as usize;
+
+        line_start
+            + match encoding {
+                PositionEncoding::Utf8 => {
+                    line_text.byte_to_char(character.min(line_text.len_bytes()))
+                }
+                PositionEncoding::Utf16 => {
+                    line_text.utf16_cu_to_char(character.min(line_text.len_utf16_cu()))
+                }
+                PositionEncoding::Utf32 => character.min(line_text.len_chars()),
+            }
+    }
+}
+
+impl LineEdit {
+    /// Replace the colors on the touched lines with `rescanned` ones,
+    /// and shift the colors after the edit to their new lines.
+    pub(crate) fn splice(
+        &self,
+        colors: &mut Vec<ColorInformation>,
+        rescanned: Vec<ColorInformation>,
+    ) {
+        let delta = self.new_end as i64 - self.old_end as i64;
+
+        colors.retain(|info| {
+            let line = info.range.start.line as usize;
+            line < self.start || line > self.old_end
+        });
+
+        for info in colors.iter_mut() {
+            if info.range.start.line as usize > self.old_end {
+                info.range.start.line = (info.range.start.line as i64 + delta) as u32;
+                info.range.end.line = (info.range.end.line as i64 + delta) as u32;
+            }
+        }
+
+        colors.extend(rescanned);
+        colors.sort_by_key(|info| info.range.start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{
+        ClientCapabilities, Color, ColorInformation, GeneralClientCapabilities, Position,
+        PositionEncodingKind, Range, TextDocumentContentChangeEvent, TextDocumentItem, Url,
+    };
+
+    use super::{Document, LineEdit, PositionEncoding};
+
+    fn document(text: &str) -> Document {
+        Document::new(TextDocumentItem::new(
+            Url::parse("file:///test.css").unwrap(),
+            "css".into(),
+            1,
+            text.into(),
+        ))
+    }
+
+    fn change(range: ((u32, u32), (u32, u32)), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(range.0 .0, range.0 .1),
+                Position::new(range.1 .0, range.1 .1),
+            )),
+            range_length: None,
+            text: text.into(),
+        }
+    }
+
+    fn info(line: u32, character: u32) -> ColorInformation {
+        ColorInformation {
+            range: Range::new(
+                Position::new(line, character),
+                Position::new(line, character + 4),
+            ),
+            color: Color {
+                red: 0.,
+                green: 0.,
+                blue: 0.,
+                alpha: 1.,
+            },
+        }
+    }
+
+    #[test]
+    fn test_apply_change() {
+        let mut doc = document("a {\n  color: #fff;\n}\n");
+
+        let edit = doc.apply_change(
+            &change(((1, 9), (1, 13)), "#000000"),
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(doc.text(), "a {\n  color: #000000;\n}\n");
+        assert_eq!(
+            edit,
+            Some(LineEdit {
+                start: 1,
+                old_end: 1,
+                new_end: 1
+            })
+        );
+
+        let edit = doc.apply_change(
+            &change(((0, 3), (0, 3)), "\n  background: red;"),
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(
+            doc.text(),
+            "a {\n  background: red;\n  color: #000000;\n}\n"
+        );
+        assert_eq!(
+            edit,
+            Some(LineEdit {
+                start: 0,
+                old_end: 0,
+                new_end: 1
+            })
+        );
+
+        let edit = doc.apply_change(&change(((0, 3), (2, 17)), ""), PositionEncoding::Utf16);
+        assert_eq!(doc.text(), "a {\n}\n");
+        assert_eq!(
+            edit,
+            Some(LineEdit {
+                start: 0,
+                old_end: 2,
+                new_end: 0
+            })
+        );
+
+        let edit = doc.apply_change(
+            &TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "b {}".into(),
+            },
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(doc.text(), "b {}");
+        assert_eq!(edit, None);
+    }
+
+    #[test]
+    fn test_carriage_returns() {
+        let mut doc = document("a { color: red; }\r\nb { color: #fff; }\n");
+
+        let edit = doc.apply_change(
+            &change(((0, 17), (0, 17)), "\rc { color: blue; }"),
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(
+            edit,
+            Some(LineEdit {
+                start: 0,
+                old_end: 0,
+                new_end: 0
+            })
+        );
+        assert_eq!(
+            doc.lines_text(0..=0),
+            "a { color: red; }\rc { color: blue; }\r\n"
+        );
+
+        let edit = doc.apply_change(&change(((1, 0), (1, 0)), "d {}\r"), PositionEncoding::Utf16);
+        assert_eq!(
+            edit,
+            Some(LineEdit {
+                start: 1,
+                old_end: 1,
+                new_end: 1
+            })
+        );
+        let text = doc.text();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "a { color: red; }\rc { color: blue; }",
+                "d {}\rb { color: #fff; }"
+            ]
+        );
+        assert_eq!(doc.lines_text(1..=1), "d {}\rb { color: #fff; }\n");
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let mut doc = document("\"😀中\": \"#fff\"\n");
+        // 😀 is 2 UTF-16 code units, 中 is 1.
+        let range = Range::new(Position::new(0, 8), Position::new(0, 12));
+        assert_eq!(doc.text_at(range, PositionEncoding::Utf16), "#fff");
+
+        doc.apply_change(&change(((0, 8), (0, 12)), "#000"), PositionEncoding::Utf16);
+        assert_eq!(doc.text(), "\"😀中\": \"#000\"\n");
+        assert_eq!(doc.lines_text(0..=0), "\"😀中\": \"#000\"\n");
+
+        // Clamp the character after the end of line
+        doc.apply_change(&change(((0, 100), (0, 100)), ","), PositionEncoding::Utf16);
+        assert_eq!(doc.text(), "\"😀中\": \"#000\",\n");
+    }
+
+    #[test]
+    fn test_position_encoding() {
+        let line = "\"😀中\": \"#fff\"";
+        let byte_offset = line.find('#').unwrap();
+        assert_eq!(PositionEncoding::Utf8.character(line, byte_offset), 12);
+        assert_eq!(PositionEncoding::Utf16.character(line, byte_offset), 8);
+        assert_eq!(PositionEncoding::Utf32.character(line, byte_offset), 7);
+
+        let doc = document(line);
+        for (encoding, start) in [
+            (PositionEncoding::Utf8, 12),
+            (PositionEncoding::Utf16, 8),
+            (PositionEncoding::Utf32, 7),
+        ] {
+            let range = Range::new(Position::new(0, start), Position::new(0, start + 4));
+            assert_eq!(doc.text_at(range, encoding), "#fff");
+        }
+
+        let capabilities = |encodings: Option<Vec<PositionEncodingKind>>| ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: encodings,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            PositionEncoding::negotiate(&ClientCapabilities::default()),
+            PositionEncoding::Utf16
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&capabilities(Some(vec![
+                "utf-7".into(),
+                PositionEncodingKind::UTF32,
+                PositionEncodingKind::UTF8,
+            ]))),
+            PositionEncoding::Utf32
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&capabilities(None)),
+            PositionEncoding::Utf16
+        );
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut colors = vec![info(0, 0), info(2, 1), info(3, 2), info(5, 0)];
+        let edit = LineEdit {
+            start: 2,
+            old_end: 3,
+            new_end: 5,
+        };
+        edit.splice(&mut colors, vec![info(4, 3)]);
+
+        let positions = colors
+            .iter()
+            .map(|info| (info.range.start.line, info.range.start.character))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![(0, 0), (4, 3), (7, 0)]);
+    }
+}
@@ -1,4 +1,21 @@
+mod config;
+mod constructor;
+mod contrast;
+mod css_color;
+mod delta_e;
+mod document;
+mod lint;
+mod lsp;
+mod palette;
 mod parser;
+mod presentation;
+mod settings;
+mod syntax_tree;
+mod tailwind;
 mod utils;
+mod variables;
+mod workspace;
 
+pub use lsp::start;
 pub use parser::*;
+pub use tailwind::{TailwindTheme, TailwindVersion};
@@ -0,0 +1,376 @@
+use std::ops::Range;
+
+use crate::parser::{ParseOptions, COLOR_FUNCTIONS, VALUE_END_CHARS};
+use crate::syntax_tree::Scope;
+use crate::utils::format_trimmed;
+
+/// A near-miss color literal that is not detected as a color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lint {
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range in the line.
+    pub(crate) range: Range<usize>,
+    pub(crate) kind: LintKind,
+    pub(crate) message: String,
+    /// The replacement of the `range`, if there is an obvious one.
+    pub(crate) fix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LintKind {
+    /// `#12345`
+    InvalidHexLength,
+    /// `rgb(300, 0, 0)`
+    OutOfRange,
+    /// `hsl(120, 50, 50)`
+    MissingPercent,
+    /// `rgb(255, 0, 0`
+    UnclosedFunction,
+    /// gpui `hsla(0.3, 1.5, 0.5, 1.)`
+    FloatOutOfRange,
+}
+
+impl LintKind {
+    /// The diagnostic code.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidHexLength => "invalid-hex-length",
+            Self::OutOfRange => "out-of-range",
+            Self::MissingPercent => "missing-percent",
+            Self::UnclosedFunction => "unclosed-function",
+            Self::FloatOutOfRange => "float-out-of-range",
+        }
+    }
+}
+
+/// Find the malformed color literals in the `text`, for the detectors enabled in `options`.
+///
+/// In Tree-sitter mode, only the lints in the `scope` built from the `text` are kept.
+pub(crate) fn lint(text: &str, options: &ParseOptions, scope: Option<&Scope>) -> Vec<Lint> {
+    let mut lints = vec![];
+    for (ix, line_text) in text.lines().enumerate() {
+        lint_line(line_text, ix, options, &mut lints);
+    }
+
+    if let Some(scope) = scope {
+        lints.retain(|lint| scope.contains(lint.line, lint.range.clone()));
+    }
+    lints
+}
+
+fn lint_line(line_text: &str, ix: usize, options: &ParseOptions, lints: &mut Vec<Lint>) {
+    let bytes = line_text.as_bytes();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        match bytes[offset] {
+            b'#' if options.hex => {
+                let len = bytes[offset + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .count();
+                let end = offset + 1 + len;
+                if matches!(len, 5 | 7)
+                    && !bytes.get(end).copied().is_some_and(is_ident_char)
+                    && is_value_start(&line_text[..offset])
+                {
+                    lints.push(Lint {
+                        line: ix,
+                        range: offset..end,
+                        kind: LintKind::InvalidHexLength,
+                        message: format!(
+                            "Invalid hex color `{}`, expected 3, 4, 6 or 8 digits",
+                            &line_text[offset..end]
+                        ),
+                        fix: None,
+                    });
+                }
+                offset = end;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' if options.functions => {
+                let len = bytes[offset..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphabetic())
+                    .count();
+                let end = offset + len;
+                let fname = &line_text[offset..end];
+                let is_call = bytes.get(end) == Some(&b'(')
+                    && !offset
+                        .checked_sub(1)
+                        .is_some_and(|prev| is_ident_char(bytes[prev]));
+                if is_call && COLOR_FUNCTIONS.contains(&fname.to_ascii_lowercase().as_str()) {
+                    offset = lint_function(line_text, ix, offset..end, options, lints);
+                } else {
+                    offset = end;
+                }
+            }
+            _ => offset += 1,
+        }
+    }
+}
+
+/// Lint the color function call, whose name is at `name`, returns the offset to continue.
+fn lint_function(
+    line_text: &str,
+    ix: usize,
+    name: Range<usize>,
+    options: &ParseOptions,
+    lints: &mut Vec<Lint>,
+) -> usize {
+    let open = name.end;
+    let fname = line_text[name.clone()].to_ascii_lowercase();
+
+    let Some(close) = line_text[open..].find(')').map(|pos| open + pos) else {
+        // The arguments end at the end of the value, or the end of line.
+        let end = line_text[open..]
+            .bytes()
+            .position(|b| VALUE_END_CHARS.contains(&b))
+            .map_or(line_text.len(), |pos| open + pos);
+        let args = line_text[open + 1..end].trim_end();
+        // A multi-line call, e.g. `hsla(` or `rgb(0.2,` at the end of line.
+        if args.trim().is_empty() || args.ends_with(',') {
+            return end;
+        }
+
+        let end = open + 1 + args.len();
+        lints.push(Lint {
+            line: ix,
+            range: name.start..end,
+            kind: LintKind::UnclosedFunction,
+            message: format!("Unclosed `{}(`", &line_text[name.clone()]),
+            fix: Some(format!("{})", &line_text[name.start..end])),
+        });
+        return end;
+    };
+
+    let args_text = &line_text[open + 1..close];
+    if args_text.contains('(') {
+        // e.g. `rgb(var(--r), 0, 0)`
+        return close + 1;
+    }
+
+    let args = split_args(args_text, open + 1);
+    let Some(values) = args
+        .iter()
+        .map(|range| parse_arg(&line_text[range.clone()]))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return close + 1;
+    };
+    if !matches!(values.len(), 3 | 4) {
+        return close + 1;
+    }
+
+    let mut push = |range: &Range<usize>, kind: LintKind, message: String, fix: String| {
+        lints.push(Lint {
+            line: ix,
+            range: range.clone(),
+            kind,
+            message,
+            fix: Some(fix),
+        });
+    };
+
+    // Rust float literals always have a `.`, see `ColorFormat::detect`, while CSS
+    // allows decimals like `rgb(255.0, 128.5, 0.0)`, so only gpui calls in Rust are 0..1.
+    let is_gpui = options.language_id.eq_ignore_ascii_case("rust")
+        && args
+            .iter()
+            .zip(values.iter())
+            .all(|(range, (_, percent))| !percent && line_text[range.clone()].contains('.'));
+    if is_gpui {
+        for (range, (value, _)) in args.iter().zip(values.iter()) {
+            if *value > 1. {
+                push(
+                    range,
+                    LintKind::FloatOutOfRange,
+                    format!("`{}` is out of range 0..1", &line_text[range.clone()]),
+                    "1.".into(),
+                );
+            }
+        }
+        return close + 1;
+    }
+
+    let legacy = args_text.contains(',');
+    for (i, (range, (value, percent))) in args.iter().zip(values.iter()).enumerate() {
+        let arg = &line_text[range.clone()];
+        let (value, percent) = (*value, *percent);
+
+        let max = match (fname.as_str(), i, percent) {
+            (_, 3, true) => 100.,
+            (_, 3, false) => 1.,
+            ("rgb" | "rgba", _, true) => 100.,
+            ("rgb" | "rgba", _, false) => 255.,
+            ("hsl" | "hsla", 1 | 2, false) if legacy => {
+                push(
+                    range,
+                    LintKind::MissingPercent,
+                    format!("Missing `%` after `{}`", arg),
+                    format!("{}%", arg),
+                );
+                continue;
+            }
+            _ => continue,
+        };
+
+        if !(0. ..=max).contains(&value) {
+            let clamped = format_trimmed(value.clamp(0., max), 3, true);
+            let suffix = if percent { "%" } else { "" };
+            push(
+                range,
+                LintKind::OutOfRange,
+                format!("`{}` is out of range 0..{}{}", arg, max, suffix),
+                format!("{}{}", clamped, suffix),
+            );
+        }
+    }
+
+    close + 1
+}
+
+/// Split the function arguments by `,`, `/` or whitespace,
+/// returns the byte ranges offset by `offset`.
+fn split_args(args: &str, offset: usize) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = None;
+    for (ix, b) in args.bytes().enumerate() {
+        let is_separator = b == b',' || b == b'/' || b.is_ascii_whitespace();
+        match (is_separator, start) {
+            (true, Some(s)) => {
+                ranges.push(offset + s..offset + ix);
+                start = None;
+            }
+            (false, None) => start = Some(ix),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push(offset + s..offset + args.len());
+    }
+    ranges
+}
+
+/// Parse a numeric argument like `255`, `50%` or `0.5`, returns the value and whether it's a percentage.
+fn parse_arg(arg: &str) -> Option<(f32, bool)> {
+    match arg.strip_suffix('%') {
+        Some(number) => number.parse().ok().map(|value| (value, true)),
+        None => arg.parse().ok().map(|value| (value, false)),
+    }
+}
+
+fn is_ident_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'-'
+}
+
+/// Whether a value starts after `before`, e.g. `color: `, `"` or `= `,
+/// so `#12345` in prose like `fixes #12345` is not a color.
+fn is_value_start(before: &str) -> bool {
+    before
+        .trim_end()
+        .bytes()
+        .last()
+        .is_none_or(|b| matches!(b, b':' | b'"' | b'\'' | b'=' | b',' | b'(' | b'['))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::{lint, LintKind, Scope};
+    use crate::parser::ParseOptions;
+
+    fn lints(text: &str) -> Vec<(usize, &str, LintKind, Option<String>)> {
+        lints_with(text, &ParseOptions::default())
+    }
+
+    fn rust_lints(text: &str) -> Vec<(usize, &str, LintKind, Option<String>)> {
+        let options = ParseOptions {
+            language_id: "rust".into(),
+            ..Default::default()
+        };
+        lints_with(text, &options)
+    }
+
+    fn lints_with<'a>(
+        text: &'a str,
+        options: &ParseOptions,
+    ) -> Vec<(usize, &'a str, LintKind, Option<String>)> {
+        lint(text, options, Scope::new(text, options).as_ref())
+            .into_iter()
+            .map(|lint| {
+                let line_text = text.lines().nth(lint.line).unwrap();
+                (lint.line, &line_text[lint.range], lint.kind, lint.fix)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let text = indoc! {r#"
+            .a { color: #12345; background: #1234567 }
+            .b { color: rgb(300, 0, 0); border-color: rgba(0, 0, 0, 150%) }
+            .c { color: hsl(120, 50, 50); }
+            .d { color: rgb(255, 0, 0; }
+        "#};
+
+        assert_eq!(
+            lints(text),
+            [
+                (0, "#12345", LintKind::InvalidHexLength, None),
+                (0, "#1234567", LintKind::InvalidHexLength, None),
+                (1, "300", LintKind::OutOfRange, Some("255".into())),
+                (1, "150%", LintKind::OutOfRange, Some("100%".into())),
+                (2, "50", LintKind::MissingPercent, Some("50%".into())),
+                (2, "50", LintKind::MissingPercent, Some("50%".into())),
+                (
+                    3,
+                    "rgb(255, 0, 0",
+                    LintKind::UnclosedFunction,
+                    Some("rgb(255, 0, 0)".into())
+                ),
+            ]
+        );
+
+        assert_eq!(
+            rust_lints("let c = hsla(0.3, 1.5, 0.5, 1.);"),
+            [(0, "1.5", LintKind::FloatOutOfRange, Some("1.".into()))]
+        );
+    }
+
+    #[test]
+    fn test_lint_valid() {
+        let text = indoc! {r#"
+            .a { color: #123456; background: #1234; }
+            .b { color: rgb(255 0 0 / 50%); fill: rgba(255, 0, 0, 0.5) }
+            .c { color: hsl(120 50 50); border-color: hsla(120, 50%, 50%, 1) }
+            .d { color: rgb(var(--r), 0, 0); }
+            .e { color: rgb(255.0, 128.5, 0.0); }
+            let c = rgb(0xff0000);
+            let c = hsla(
+            let c = rgba(0.1,
+            Fixes #12345 and &#12345;
+            fn my_rgb(a: f32) {}
+        "#};
+
+        assert_eq!(lints(text), []);
+        assert_eq!(
+            rust_lints("let c = hsla(0.3, 1., 0.5, 1.); let d = rgb(0.2, 0.4, 0.6);"),
+            []
+        );
+    }
+
+    #[test]
+    fn test_lint_with_options() {
+        let options = ParseOptions {
+            hex: false,
+            functions: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint("color: #12345; color: rgb(300, 0, 0)", &options, None),
+            []
+        );
+    }
+}
@@ -1,21 +1,93 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use serde::Deserialize;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::{self, *};
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
+use crate::config::{Config, CONFIG_FILES};
+use crate::contrast::check_contrast;
+use crate::css_color::evaluate;
+use crate::delta_e::ColorUsages;
+use crate::document::{Document, LineEdit, PositionEncoding};
+use crate::lint::lint;
+use crate::palette::Palette;
+use crate::parser::{try_parse_color, HexLiteralOrder, ParseOptions};
+use crate::presentation::{color_conversions, color_presentations, normalize_color, rewrite_color};
+use crate::settings::{DiagnosticsSettings, Settings, SETTINGS_SECTION};
+use crate::syntax_tree::Scope;
+use crate::tailwind::TailwindTheme;
 use crate::utils::color_summary;
+use crate::variables::{
+    parse_usages, parse_var, parse_variables, resolve_usage, splice_usages, Declaration, Syntax,
+    Usage, Variables, MAX_DEPTH,
+};
+use crate::workspace::{find_files, index_file, is_workspace_file, language_id, IndexedColor};
 
 const LSP_NAME: &str = "ColorLSP";
+/// The delay of the diagnostics after a change, the whole document is checked.
+const DIAGNOSTICS_DELAY: Duration = Duration::from_millis(300);
 
+const FIX_ALL_KIND: CodeActionKind = CodeActionKind::new("source.fixAll.colorLsp");
+const ORGANIZE_COLORS_KIND: CodeActionKind = CodeActionKind::new("source.organizeColors");
+/// Normalize the colors of a document, the argument is `NormalizeColorsArgs`.
+const NORMALIZE_COLORS_COMMAND: &str = "color-lsp.normalizeColors";
+
+#[derive(Debug, Deserialize)]
+struct NormalizeColorsArgs {
+    uri: Url,
+    /// Only report the number of edits, without applying them.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Cluster the near-duplicate colors of the workspace, the optional argument is
+/// `NearDuplicatesArgs`.
+const FIND_NEAR_DUPLICATES_COMMAND: &str = "color-lsp.findNearDuplicates";
+
+#[derive(Debug, Default, Deserialize)]
+struct NearDuplicatesArgs {
+    /// The CIEDE2000 threshold, `diagnostics.near_duplicate_threshold` by default.
+    threshold: Option<f32>,
+}
+
+/// The Tree-sitter scope of a document, and the version it was built from.
+type VersionedScope = (i32, Option<Arc<Scope>>);
+
+/// The state is shared by the clones, to run the background tasks.
+#[derive(Clone)]
 struct Backend {
     client: Client,
-    work_dir: RwLock<PathBuf>,
-    documents: RwLock<HashMap<Url, Arc<TextDocumentItem>>>,
-    diagnostics: RwLock<HashMap<Url, Vec<Diagnostic>>>,
-    colors: RwLock<HashMap<Url, Vec<ColorInformation>>>,
+    work_dir: Arc<RwLock<PathBuf>>,
+    position_encoding: Arc<RwLock<PositionEncoding>>,
+    config: Arc<RwLock<Config>>,
+    /// The approved colors of the config, empty if there is no palette.
+    palette: Arc<RwLock<Palette>>,
+    /// The Tailwind CSS colors of the config.
+    tailwind_theme: Arc<RwLock<Arc<TailwindTheme>>>,
+    settings: Arc<RwLock<Settings>>,
+    /// Whether the client supports the `workspace/configuration` request.
+    supports_configuration: Arc<RwLock<bool>>,
+    documents: Arc<RwLock<HashMap<Url, Document>>>,
+    diagnostics: Arc<RwLock<HashMap<Url, Vec<Diagnostic>>>>,
+    colors: Arc<RwLock<HashMap<Url, Vec<ColorInformation>>>>,
+    /// The variables of the opened documents, the workspace stylesheets and design tokens.
+    variables: Arc<RwLock<BTreeMap<Url, Variables>>>,
+    /// The `var()` usages of the opened documents.
+    usages: Arc<RwLock<HashMap<Url, Vec<Usage>>>>,
+    /// The Tree-sitter scopes of the opened documents, by the version they were scanned at.
+    scopes: Arc<RwLock<HashMap<Url, VersionedScope>>>,
+    /// The colors of the workspace files not opened.
+    color_index: Arc<RwLock<BTreeMap<Url, Vec<IndexedColor>>>>,
+    /// The file changes received while the workspace is indexed, they are applied after it.
+    pending_changes: Arc<Mutex<Option<Vec<FileEvent>>>>,
+    /// Incremented by each indexing of the workspace, only the latest one is kept.
+    index_generation: Arc<AtomicUsize>,
 }
 
 #[allow(unused)]
@@ -28,16 +100,163 @@ impl Backend {
         *self.work_dir.write().unwrap() = work_dir;
     }
 
-    fn upsert_document(&self, doc: Arc<TextDocumentItem>) {
-        let uri = doc.uri.clone();
-        self.documents
-            .write()
+    fn position_encoding(&self) -> PositionEncoding {
+        *self.position_encoding.read().unwrap()
+    }
+
+    /// Load the project config file, its palette and Tailwind CSS theme from the work dir,
+    /// and rescan the opened documents.
+    async fn load_config(&self) {
+        let config = match Config::load(&self.work_dir()) {
+            Ok(config) => config.unwrap_or_default(),
+            Err(err) => {
+                self.client.log_message(MessageType::WARNING, err).await;
+                Config::default()
+            }
+        };
+        let palette = match Palette::load(config.palette(), &self.work_dir()) {
+            Ok(palette) => palette,
+            Err(err) => {
+                self.client.log_message(MessageType::WARNING, err).await;
+                Palette::default()
+            }
+        };
+        let tailwind_theme = match TailwindTheme::load(config.tailwind(), &self.work_dir()) {
+            Ok(theme) => theme,
+            Err(err) => {
+                self.client.log_message(MessageType::WARNING, err).await;
+                TailwindTheme::default()
+            }
+        };
+        *self.config.write().unwrap() = config;
+        *self.palette.write().unwrap() = palette;
+        *self.tailwind_theme.write().unwrap() = Arc::new(tailwind_theme);
+
+        self.rescan_documents().await;
+    }
+
+    fn settings(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Update the settings from the editor, and rescan the opened documents if they changed.
+    async fn update_settings(&self, value: serde_json::Value) {
+        let settings = match Settings::from_value(value) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => return,
+            Err(err) => {
+                self.client.log_message(MessageType::WARNING, err).await;
+                return;
+            }
+        };
+
+        if *self.settings.read().unwrap() == settings {
+            return;
+        }
+        *self.settings.write().unwrap() = settings;
+
+        self.rescan_documents().await;
+    }
+
+    /// Pull the settings by `workspace/configuration`, if the client supports it.
+    async fn pull_settings(&self) {
+        if !*self.supports_configuration.read().unwrap() {
+            return;
+        }
+
+        let items = vec![ConfigurationItem {
+            scope_uri: None,
+            section: Some(SETTINGS_SECTION.into()),
+        }];
+        match self.client.configuration(items).await {
+            Ok(values) => {
+                if let Some(value) = values.into_iter().next() {
+                    self.update_settings(value).await;
+                }
+            }
+            Err(err) => {
+                self.client
+                    .log_message(
+                        MessageType::INFO,
+                        format!("Failed to pull the settings: {}", err),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn rescan_documents(&self) {
+        let documents = self
+            .documents
+            .read()
             .unwrap()
-            .get_mut(&uri)
-            .map(|old| std::mem::replace(old, doc.clone()));
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for document in documents.iter() {
+            self.scan_document(document).await;
+            self.diagnose_document(document).await;
+        }
+    }
+
+    /// Whether the file is the project config, or a file it loads colors from.
+    fn is_config_file(&self, uri: &Url) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+
+        let work_dir = self.work_dir();
+        let config = self.config.read().unwrap();
+        CONFIG_FILES.iter().any(|name| path == work_dir.join(name))
+            || [&config.palette().tokens, &config.tailwind().theme]
+                .into_iter()
+                .flatten()
+                .any(|file| path == work_dir.join(file))
+    }
+
+    /// The path of the document relative to the work dir, if it's a file.
+    fn relative_path(&self, uri: &Url) -> Option<PathBuf> {
+        let path = uri.to_file_path().ok()?;
+        let work_dir = self.work_dir();
+        Some(
+            path.strip_prefix(&work_dir)
+                .map(|path| path.to_path_buf())
+                .unwrap_or(path),
+        )
+    }
+
+    /// Return the parse options of the document, or `None` if it's ignored by the config.
+    fn parse_options(&self, document: &Document) -> Option<ParseOptions> {
+        let relative_path = self.relative_path(&document.uri);
+
+        let config = self.config.read().unwrap();
+        if config.is_ignored(relative_path.as_deref()) {
+            return None;
+        }
+
+        Some(config.parse_options(
+            self.base_parse_options(),
+            &document.language_id,
+            relative_path.as_deref(),
+        ))
+    }
+
+    /// The parse options of the editor settings, before the project config.
+    fn base_parse_options(&self) -> ParseOptions {
+        let mut options = ParseOptions {
+            tailwind_theme: self.tailwind_theme.read().unwrap().clone(),
+            ..Default::default()
+        };
+        self.settings.read().unwrap().formats.apply(&mut options);
+        options
+    }
+
+    fn upsert_document(&self, doc: Document) {
+        let uri = doc.uri.clone();
+        self.documents.write().unwrap().insert(uri, doc);
     }
 
-    fn get_document(&self, uri: &Url) -> Option<Arc<TextDocumentItem>> {
+    fn get_document(&self, uri: &Url) -> Option<Document> {
         self.documents.read().unwrap().get(uri).cloned()
     }
 
@@ -45,16 +264,41 @@ impl Backend {
         self.documents.write().unwrap().remove(uri);
         self.colors.write().unwrap().remove(uri);
         self.diagnostics.write().unwrap().remove(uri);
+        self.usages.write().unwrap().remove(uri);
+        self.scopes.write().unwrap().remove(uri);
+    }
+
+    /// The Tree-sitter scope of the document, built again if it wasn't scanned at this version.
+    fn document_scope(
+        &self,
+        document: &Document,
+        text: &str,
+        options: &ParseOptions,
+    ) -> Option<Arc<Scope>> {
+        let scanned = self
+            .scopes
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .filter(|(version, _)| *version == document.version)
+            .map(|(_, scope)| scope.clone());
+        scanned.unwrap_or_else(|| Scope::new(text, options).map(Arc::new))
     }
 
-    async fn send_diagnostics(&self, document: &TextDocumentItem, diagnostics: Vec<Diagnostic>) {
+    /// Publish the diagnostics of the document, replacing the previous ones.
+    async fn send_diagnostics(&self, document: &Document, diagnostics: Vec<Diagnostic>) {
+        if diagnostics.is_empty() {
+            if self.diagnostics.read().unwrap().contains_key(&document.uri) {
+                self.clear_diagnostics(&document.uri).await;
+            }
+            return;
+        }
+
         if let Ok(mut map) = self.diagnostics.write() {
-            map.entry(document.uri.clone())
-                .and_modify(|old_diagnostics| old_diagnostics.extend_from_slice(&diagnostics))
-                .or_insert_with(|| diagnostics.clone());
+            map.insert(document.uri.clone(), diagnostics.clone());
         }
         self.client
-            .publish_diagnostics(document.uri.clone(), diagnostics, None)
+            .publish_diagnostics(document.uri.clone(), diagnostics, Some(document.version))
             .await;
     }
 
@@ -79,18 +323,1015 @@ impl Backend {
         }
     }
 
+    /// Check the document and publish the diagnostics, if they are enabled.
+    async fn diagnose_document(&self, document: &Document) {
+        let settings = self.settings();
+        let mut diagnostics = vec![];
+        if let (true, Some(options)) = (settings.diagnostics.enabled, self.parse_options(document))
+        {
+            let text = document.text();
+            let scope = self.document_scope(document, &text, &options);
+            if settings.diagnostics.malformed {
+                diagnostics.extend(Self::malformed_diagnostics(
+                    &text,
+                    self.position_encoding(),
+                    &options,
+                    scope.as_deref(),
+                ));
+            }
+            if settings.diagnostics.contrast {
+                diagnostics.extend(Self::contrast_diagnostics(
+                    document,
+                    &text,
+                    self.position_encoding(),
+                    &options,
+                    scope.as_deref(),
+                    &settings.diagnostics,
+                ));
+            }
+            if settings.diagnostics.near_duplicates {
+                diagnostics.extend(self.near_duplicate_diagnostics(
+                    document,
+                    &options,
+                    settings.diagnostics.near_duplicate_threshold,
+                ));
+            }
+            diagnostics.extend(self.palette_diagnostics(document, &options));
+        }
+
+        self.send_diagnostics(document, diagnostics).await;
+    }
+
+    /// Diagnose the document once the typing pauses, unless it changes again meanwhile.
+    fn schedule_diagnostics(&self, uri: Url, version: i32) {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(DIAGNOSTICS_DELAY).await;
+            let Some(document) = backend.get_document(&uri) else {
+                return;
+            };
+            // A later change has scheduled its own.
+            if document.version == version {
+                backend.diagnose_document(&document).await;
+            }
+        });
+    }
+
+    /// The diagnostics of the malformed color literals, with the quick-fix in `data.fix`.
+    fn malformed_diagnostics(
+        text: &str,
+        encoding: PositionEncoding,
+        options: &ParseOptions,
+        scope: Option<&Scope>,
+    ) -> Vec<Diagnostic> {
+        let lines = text.lines().collect::<Vec<_>>();
+        lint(text, options, scope)
+            .into_iter()
+            .map(|lint| Diagnostic {
+                range: encoding.range(lines[lint.line], lint.line, lint.range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(lint.kind.code().into())),
+                source: Some(LSP_NAME.into()),
+                message: lint.message,
+                data: lint.fix.map(|fix| serde_json::json!({ "fix": fix })),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// The diagnostics of the low contrast foreground colors, related to their backgrounds.
+    fn contrast_diagnostics(
+        document: &Document,
+        text: &str,
+        encoding: PositionEncoding,
+        options: &ParseOptions,
+        scope: Option<&Scope>,
+        settings: &DiagnosticsSettings,
+    ) -> Vec<Diagnostic> {
+        let lines = text.lines().collect::<Vec<_>>();
+        let range =
+            |line: usize, range: std::ops::Range<usize>| encoding.range(lines[line], line, range);
+
+        check_contrast(text, options, scope, &settings.contrast_options())
+            .into_iter()
+            .map(|issue| Diagnostic {
+                range: range(issue.foreground.line, issue.foreground.range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String("low-contrast".into())),
+                source: Some(LSP_NAME.into()),
+                message: issue.message,
+                related_information: Some(vec![DiagnosticRelatedInformation {
+                    location: Location::new(
+                        document.uri.clone(),
+                        range(issue.background.line, issue.background.range),
+                    ),
+                    message: "The background color".into(),
+                }]),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// The number of usages of the colors in the opened documents and the workspace index.
+    fn color_usages(&self) -> ColorUsages {
+        let mut counts = HashMap::new();
+        let colors = self.colors.read().unwrap();
+        for info in colors.values().flatten() {
+            let color = info.color;
+            let rgba = csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha)
+                .to_rgba8();
+            *counts.entry(rgba).or_default() += 1;
+        }
+        for (uri, indexed) in self.color_index.read().unwrap().iter() {
+            if colors.contains_key(uri) {
+                continue;
+            }
+            for color in indexed.iter() {
+                *counts.entry(color.rgba).or_default() += 1;
+            }
+        }
+        ColorUsages::new(counts)
+    }
+
+    /// The diagnostics of the colors nearly the same as a more used color of the workspace,
+    /// with the canonical color in the original notation in `data.fix`.
+    fn near_duplicate_diagnostics(
+        &self,
+        document: &Document,
+        options: &ParseOptions,
+        threshold: f32,
+    ) -> Vec<Diagnostic> {
+        let usages = self.color_usages();
+        let colors = self
+            .colors
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .cloned()
+            .unwrap_or_default();
+
+        colors
+            .iter()
+            .filter_map(|info| {
+                let color = info.color;
+                let rgba =
+                    csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha)
+                        .to_rgba8();
+                let ([r, g, b, a], delta_e) = usages.canonical(rgba, threshold)?;
+                let canonical = csscolorparser::Color::from_rgba8(r, g, b, a);
+                let original = document.text_at(info.range, self.position_encoding());
+                let fix = rewrite_color(&original, &canonical, options.hex_literal_order)
+                    .filter(|fix| *fix != original);
+                Some(Diagnostic {
+                    range: info.range,
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    code: Some(NumberOrString::String("near-duplicate".into())),
+                    source: Some(LSP_NAME.into()),
+                    message: format!(
+                        "`{}` is nearly the same as `{}` (ΔE {:.2}), consider using one color",
+                        original,
+                        canonical.to_css_hex(),
+                        delta_e
+                    ),
+                    data: fix.map(|fix| serde_json::json!({ "fix": fix })),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// The diagnostics of the colors not in the palette, with the nearest palette colors in
+    /// the original notation in `data.fixes`.
+    fn palette_diagnostics(&self, document: &Document, options: &ParseOptions) -> Vec<Diagnostic> {
+        let palette = self.palette.read().unwrap();
+        if palette.is_empty() {
+            return vec![];
+        }
+        let colors = self
+            .colors
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .cloned()
+            .unwrap_or_default();
+
+        colors
+            .iter()
+            .filter_map(|info| {
+                let color = info.color;
+                let color =
+                    csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha);
+                if palette.contains(&color) {
+                    return None;
+                }
+
+                let nearest = palette.nearest(&color, 3);
+                let (closest, delta_e) = nearest.first()?;
+                let original = document.text_at(info.range, self.position_encoding());
+                // Keep the alpha of the original color.
+                let fixes = nearest
+                    .iter()
+                    .filter_map(|(entry, _)| {
+                        let replacement = csscolorparser::Color {
+                            a: color.a,
+                            ..entry.color.clone()
+                        };
+                        rewrite_color(&original, &replacement, options.hex_literal_order)
+                    })
+                    .collect::<Vec<_>>();
+                Some(Diagnostic {
+                    range: info.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String("not-in-palette".into())),
+                    source: Some(LSP_NAME.into()),
+                    message: format!(
+                        "`{}` is not in the palette, the nearest is {} (ΔE {:.2})",
+                        original,
+                        closest.label(),
+                        delta_e
+                    ),
+                    data: Some(serde_json::json!({ "fixes": fixes })),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
     /// Scan all colors vars in the document
-    async fn scan_document(&self, document: &TextDocumentItem) {
-        let input = document.text.as_str();
-        let nodes = crate::parser::parse(input);
+    ///
+    /// The Tree-sitter scope is kept for the diagnostics of the same version.
+    async fn scan_document(&self, document: &Document) {
+        let colors = match self.parse_options(document) {
+            Some(options) => {
+                let text = document.text();
+                let scope = Scope::new(&text, &options).map(Arc::new);
+                let colors = Self::scan_text(
+                    &text,
+                    0,
+                    self.position_encoding(),
+                    &options,
+                    scope.as_deref(),
+                );
+                self.scopes
+                    .write()
+                    .unwrap()
+                    .insert(document.uri.clone(), (document.version, scope));
+                colors
+            }
+            None => vec![],
+        };
+
+        if let Ok(mut map) = self.colors.write() {
+            map.insert(document.uri.clone(), colors);
+        }
+    }
+
+    /// Rescan the colors on the lines touched by `edit`, and shift the cached colors after it.
+    ///
+    /// Not for Tree-sitter mode, the lines can't be parsed alone.
+    async fn scan_lines(&self, document: &Document, edit: LineEdit) {
+        let Some(options) = self.parse_options(document) else {
+            return;
+        };
+
+        let text = document.lines_text(edit.start..=edit.new_end);
+        let rescanned = Self::scan_text(
+            &text,
+            edit.start as u32,
+            self.position_encoding(),
+            &options,
+            None,
+        );
+
+        if let Ok(mut map) = self.colors.write() {
+            let colors = map.entry(document.uri.clone()).or_default();
+            edit.splice(colors, rescanned);
+        }
+    }
+
+    /// Rescan the variables and `var()` usages on the lines touched by `edit`, and shift the
+    /// ones after it.
+    ///
+    /// The design tokens are parsed again, their declarations span lines.
+    fn update_variable_lines(&self, document: &Document, edit: LineEdit) {
+        let path = document.uri.to_file_path().ok();
+        let syntax = Syntax::detect(&document.language_id, path.as_deref());
+        if syntax == Syntax::Tokens {
+            self.update_variables(document);
+            return;
+        }
+
+        let text = document.lines_text(edit.start..=edit.new_end);
+        if let Some(variables) = self.variables.write().unwrap().get_mut(&document.uri) {
+            variables.splice(&edit, parse_variables(&text, syntax));
+        }
+        if let Some(usages) = self.usages.write().unwrap().get_mut(&document.uri) {
+            splice_usages(usages, &edit, parse_usages(&text));
+        }
+    }
+
+    /// Update the variables declared and referenced in the document, and its `var()` usages.
+    fn update_variables(&self, document: &Document) {
+        let path = document.uri.to_file_path().ok();
+        let syntax = Syntax::detect(&document.language_id, path.as_deref());
+        let text = document.text();
+        let variables = parse_variables(&text, syntax);
+        self.variables
+            .write()
+            .unwrap()
+            .insert(document.uri.clone(), variables);
+        self.usages
+            .write()
+            .unwrap()
+            .insert(document.uri.clone(), parse_usages(&text));
+    }
+
+    /// Index the colors and variables of the files in the work dir in the background,
+    /// the opened documents are kept as they are.
+    ///
+    /// The file changes received meanwhile are applied after it, and only the latest indexing
+    /// is kept when the config changes during one.
+    async fn index_workspace(&self) {
+        let generation = {
+            let mut pending = self.pending_changes.lock().unwrap();
+            pending.get_or_insert_with(Vec::new);
+            self.index_generation.fetch_add(1, Ordering::SeqCst) + 1
+        };
+
+        let work_dir = self.work_dir();
+        let config = self.config.read().unwrap().clone();
+        let options = self.base_parse_options();
+        let indexed = tokio::task::spawn_blocking(move || {
+            find_files(&work_dir, &config, |_| true)
+                .into_iter()
+                .filter_map(|path| {
+                    let uri = Url::from_file_path(&path).ok()?;
+                    let index = index_file(&work_dir, &path, &config, &options)?;
+                    Some((uri, index))
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let changes = {
+            let mut pending = self.pending_changes.lock().unwrap();
+            if self.index_generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            let documents = self.documents.read().unwrap();
+            let mut color_index = self.color_index.write().unwrap();
+            let mut variables = self.variables.write().unwrap();
+            color_index.clear();
+            variables.retain(|uri, _| documents.contains_key(uri));
+            for (uri, index) in indexed {
+                if documents.contains_key(&uri) {
+                    continue;
+                }
+                color_index.insert(uri.clone(), index.colors);
+                if let Some(file_variables) = index.variables {
+                    variables.insert(uri, file_variables);
+                }
+            }
+            pending.take().unwrap_or_default()
+        };
+        self.update_index(changes).await;
+
+        // The near-duplicates depend on the colors of the workspace.
+        if self.settings().diagnostics.near_duplicates {
+            self.rediagnose_documents().await;
+        }
+    }
+
+    /// Update the index of the changed files, or queue them while the workspace is indexed.
+    async fn apply_file_changes(&self, changes: Vec<FileEvent>) {
+        let changes = {
+            let mut pending = self.pending_changes.lock().unwrap();
+            match pending.as_mut() {
+                Some(pending) => {
+                    pending.extend(changes);
+                    return;
+                }
+                None => changes,
+            }
+        };
+        self.update_index(changes).await;
+    }
+
+    /// Index the workspace in a background task, see [`Self::index_workspace`].
+    fn spawn_index_workspace(&self) {
+        // Queue the file changes from now on, until the indexing is done.
+        self.pending_changes
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Vec::new);
+        let backend = self.clone();
+        tokio::spawn(async move { backend.index_workspace().await });
+    }
+
+    async fn rediagnose_documents(&self) {
+        let documents = self
+            .documents
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for document in documents.iter() {
+            self.diagnose_document(document).await;
+        }
+    }
+
+    /// Update the index of the changed files, the opened documents are kept as they are, and
+    /// the entries of the files out of the workspace or not on the disk are dropped.
+    async fn update_index(&self, changes: Vec<FileEvent>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        let work_dir = self.work_dir();
+        let config = self.config.read().unwrap().clone();
+        let options = self.base_parse_options();
+        let changes = {
+            let documents = self.documents.read().unwrap();
+            changes
+                .into_iter()
+                .filter(|change| !documents.contains_key(&change.uri))
+                .collect::<Vec<_>>()
+        };
+        let indexed = tokio::task::spawn_blocking(move || {
+            changes
+                .into_iter()
+                .map(|change| {
+                    let index = change
+                        .uri
+                        .to_file_path()
+                        .ok()
+                        .filter(|path| {
+                            change.typ != FileChangeType::DELETED
+                                && is_workspace_file(&work_dir, path, &config)
+                        })
+                        .and_then(|path| index_file(&work_dir, &path, &config, &options));
+                    (change.uri, index)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let documents = self.documents.read().unwrap();
+        let mut color_index = self.color_index.write().unwrap();
+        let mut variables = self.variables.write().unwrap();
+        for (uri, index) in indexed {
+            // Opened while it was read.
+            if documents.contains_key(&uri) {
+                continue;
+            }
+            color_index.remove(&uri);
+            variables.remove(&uri);
+            if let Some(index) = index {
+                color_index.insert(uri.clone(), index.colors);
+                if let Some(file_variables) = index.variables {
+                    variables.insert(uri, file_variables);
+                }
+            }
+        }
+    }
+
+    /// Find the declaration of a variable, the ones in `uri` come first,
+    /// then the other documents by their uri.
+    fn lookup_variable(&self, uri: &Url, name: &str) -> Option<(Url, Declaration)> {
+        let variables = self.variables.read().unwrap();
+        let find = |variables: &Variables| {
+            variables
+                .declarations
+                .iter()
+                .find(|decl| decl.name == name)
+                .cloned()
+        };
+        if let Some(decl) = variables.get(uri).and_then(find) {
+            return Some((uri.clone(), decl));
+        }
+        variables
+            .iter()
+            .filter(|(other, _)| *other != uri)
+            .find_map(|(other, variables)| Some((other.clone(), find(variables)?)))
+    }
+
+    /// The name of the variable at the `position` of the document.
+    fn variable_at(&self, document: &Document, position: Position) -> Option<String> {
+        let line = position.line as usize;
+        let text = document.lines_text(line..=line);
+        let line_text = text.lines().next().unwrap_or_default();
+        let encoding = self.position_encoding();
+        // The byte offset of the position.
+        let offset = line_text
+            .char_indices()
+            .map(|(ix, _)| ix)
+            .chain([line_text.len()])
+            .find(|ix| encoding.character(line_text, *ix) >= position.character)?;
+
+        let variables = self.variables.read().unwrap();
+        variables
+            .get(&document.uri)?
+            .name_at(line, offset)
+            .map(|name| name.to_string())
+    }
+
+    /// The ranges and texts of the byte ranges at the lines of a document, as `(line, range)`,
+    /// the text of the documents not opened is read from the file.
+    async fn file_ranges(
+        &self,
+        uri: &Url,
+        ranges: Vec<(usize, Range<usize>)>,
+    ) -> Vec<(lsp_types::Range, String)> {
+        let text = match self.get_document(uri) {
+            Some(document) => document.text(),
+            None => {
+                let Ok(path) = uri.to_file_path() else {
+                    return vec![];
+                };
+                match tokio::task::spawn_blocking(move || std::fs::read_to_string(path)).await {
+                    Ok(Ok(text)) => text,
+                    _ => return vec![],
+                }
+            }
+        };
+        let lines = text.lines().collect::<Vec<_>>();
+        let encoding = self.position_encoding();
+        ranges
+            .into_iter()
+            .filter_map(|(line, range)| {
+                let line_text = lines.get(line)?;
+                let text = line_text.get(range.clone())?.to_string();
+                Some((encoding.range(line_text, line, range), text))
+            })
+            .collect()
+    }
+
+    /// The locations of the byte ranges at the lines of a document, see [`Self::file_ranges`].
+    async fn file_locations(&self, uri: &Url, ranges: Vec<(usize, Range<usize>)>) -> Vec<Location> {
+        self.file_ranges(uri, ranges)
+            .await
+            .into_iter()
+            .map(|(range, _)| Location::new(uri.clone(), range))
+            .collect()
+    }
+
+    /// The occurrences of the same color as the one at `position`, as their ranges and texts,
+    /// in the opened documents and the workspace index, whatever the notation.
+    async fn same_colors(
+        &self,
+        uri: &Url,
+        position: Position,
+    ) -> Option<BTreeMap<Url, Vec<(lsp_types::Range, String)>>> {
+        let key = |color: &lsp_types::Color| {
+            csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha).to_rgba8()
+        };
+        let colors = self.colors.read().unwrap().clone();
+        let rgba = colors
+            .get(uri)?
+            .iter()
+            .find(|info| info.range.start <= position && position <= info.range.end)
+            .map(|info| key(&info.color))?;
+
+        let mut occurrences = BTreeMap::new();
+        let encoding = self.position_encoding();
+        for (uri, infos) in colors.iter() {
+            let Some(document) = self.get_document(uri) else {
+                continue;
+            };
+            let ranges = infos
+                .iter()
+                .filter(|info| key(&info.color) == rgba)
+                .map(|info| (info.range, document.text_at(info.range, encoding)))
+                .collect::<Vec<_>>();
+            if !ranges.is_empty() {
+                occurrences.insert(uri.clone(), ranges);
+            }
+        }
+
+        let indexed = self
+            .color_index
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(uri, _)| !colors.contains_key(uri))
+            .map(|(uri, indexed)| {
+                let ranges = indexed
+                    .iter()
+                    .filter(|color| color.rgba == rgba)
+                    .map(|color| (color.line, color.range.clone()))
+                    .collect::<Vec<_>>();
+                (uri.clone(), ranges)
+            })
+            .filter(|(_, ranges)| !ranges.is_empty())
+            .collect::<Vec<_>>();
+        for (uri, ranges) in indexed {
+            let ranges = self.file_ranges(&uri, ranges).await;
+            occurrences.insert(uri, ranges);
+        }
+
+        Some(occurrences)
+    }
+
+    /// The locations of the same color as the one at `position`, see [`Self::same_colors`].
+    async fn color_references(&self, uri: &Url, position: Position) -> Option<Vec<Location>> {
+        let occurrences = self.same_colors(uri, position).await?;
+        Some(
+            occurrences
+                .into_iter()
+                .flat_map(|(uri, ranges)| {
+                    ranges
+                        .into_iter()
+                        .map(move |(range, _)| Location::new(uri.clone(), range))
+                })
+                .collect(),
+        )
+    }
+
+    /// The channel order of the 8-digit `0x` literals of a document, opened or not.
+    fn hex_literal_order(&self, uri: &Url) -> HexLiteralOrder {
+        if let Some(document) = self.get_document(uri) {
+            return self
+                .parse_options(&document)
+                .unwrap_or_default()
+                .hex_literal_order;
+        }
+
+        let relative_path = self.relative_path(uri);
+        let language_id = relative_path
+            .as_deref()
+            .map(language_id)
+            .unwrap_or_default();
+        self.config
+            .read()
+            .unwrap()
+            .parse_options(
+                self.base_parse_options(),
+                &language_id,
+                relative_path.as_deref(),
+            )
+            .hex_literal_order
+    }
+
+    /// The colors of the `var()` usages in the document, at the property names.
+    ///
+    /// The usages only falling back to a color literal are skipped, the literal is a color already.
+    fn variable_colors(&self, document: &Document) -> Vec<(Usage, ColorInformation)> {
+        if self.parse_options(document).is_none() {
+            return vec![];
+        }
+
+        let lookup = |name: &str| {
+            self.lookup_variable(&document.uri, name)
+                .map(|(_, decl)| decl.value)
+        };
+        let usages = self
+            .usages
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .cloned()
+            .unwrap_or_default();
+        if usages.is_empty() {
+            return vec![];
+        }
+
+        let text = document.text();
+        let lines = text.lines().collect::<Vec<_>>();
+        let encoding = self.position_encoding();
+        usages
+            .into_iter()
+            .filter(|usage| {
+                lookup(&usage.name).is_some()
+                    || usage
+                        .fallback
+                        .as_ref()
+                        .is_some_and(|(_, fallback)| fallback.starts_with("var("))
+            })
+            .filter_map(|usage| {
+                let color = resolve_usage(&usage, &lookup)?;
+                let info = ColorInformation {
+                    range: encoding.range(lines[usage.line], usage.line, usage.name_range.clone()),
+                    color: lsp_types::Color {
+                        red: color.r,
+                        green: color.g,
+                        blue: color.b,
+                        alpha: color.a,
+                    },
+                };
+                Some((usage, info))
+            })
+            .collect()
+    }
+
+    /// The literal a `var()` usage resolves to, as its uri, line, byte range and text,
+    /// either the value of a declaration or a fallback.
+    fn variable_source(
+        &self,
+        uri: &Url,
+        usage: &Usage,
+    ) -> Option<(Url, usize, Range<usize>, String)> {
+        // Where the current usage is, its ranges are relative to `offset` of the line.
+        let (mut current_uri, mut line, mut offset) = (uri.clone(), usage.line, 0);
+        let mut current = usage.clone();
+        for _ in 0..=MAX_DEPTH {
+            if let Some((decl_uri, decl)) = self.lookup_variable(uri, &current.name) {
+                match parse_var(&decl.value) {
+                    Some(next) => {
+                        (current_uri, line, offset) =
+                            (decl_uri, decl.value_line, decl.value_range.start);
+                        current = next;
+                    }
+                    None => return Some((decl_uri, decl.value_line, decl.value_range, decl.value)),
+                }
+                continue;
+            }
+
+            let (range, fallback) = current.fallback.clone()?;
+            match parse_var(&fallback) {
+                Some(next) => {
+                    offset += range.start;
+                    current = next;
+                }
+                None => {
+                    let range = offset + range.start..offset + range.end;
+                    return Some((current_uri, line, range, fallback));
+                }
+            }
+        }
+        None
+    }
+
+    /// The presentations of a `var()` usage at `range`, they edit the literal it resolves to.
+    ///
+    /// A color presentation can only edit its document, so there are none when the
+    /// literal is declared in another file.
+    fn variable_presentations(
+        &self,
+        document: &Document,
+        usage: &Usage,
+        range: lsp_types::Range,
+        color: lsp_types::Color,
+    ) -> Vec<ColorPresentation> {
+        let Some((uri, line, source_range, original)) = self.variable_source(&document.uri, usage)
+        else {
+            return vec![];
+        };
+        if uri != document.uri {
+            return vec![];
+        }
+
+        let encoding = self.position_encoding();
+        let text = document.text();
+        let Some(line_text) = text.lines().nth(line) else {
+            return vec![];
+        };
+        let source_range = encoding.range(line_text, line, source_range);
+        let usage_text = document.text_at(range, encoding);
+        let hex_literal_order = self
+            .parse_options(document)
+            .unwrap_or_default()
+            .hex_literal_order;
+        let color = csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha);
+
+        let settings = self.settings();
+        color_presentations(
+            &original,
+            &color,
+            &settings.presentations,
+            settings.alpha,
+            hex_literal_order,
+        )
+        .into_iter()
+        .map(|label| ColorPresentation {
+            // Keep the usage as it is.
+            text_edit: Some(TextEdit::new(range, usage_text.clone())),
+            additional_text_edits: Some(vec![TextEdit::new(source_range, label.clone())]),
+            label,
+        })
+        .collect()
+    }
+
+    /// The "Convert to" actions of the colors in the `range`.
+    fn conversion_actions(&self, uri: &Url, range: lsp_types::Range) -> Vec<CodeAction> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+        let hex_literal_order = self
+            .parse_options(&document)
+            .unwrap_or_default()
+            .hex_literal_order;
+        let colors = self
+            .colors
+            .read()
+            .unwrap()
+            .get(uri)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut actions = vec![];
+        for info in colors
+            .iter()
+            .filter(|info| info.range.start <= range.end && range.start <= info.range.end)
+        {
+            let original = document.text_at(info.range, self.position_encoding());
+            let color = csscolorparser::Color::new(
+                info.color.red,
+                info.color.green,
+                info.color.blue,
+                info.color.alpha,
+            );
+
+            for (title, text) in color_conversions(&original, &color, hex_literal_order) {
+                let changes = HashMap::from([(uri.clone(), vec![TextEdit::new(info.range, text)])]);
+                actions.push(CodeAction {
+                    title,
+                    kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                    edit: Some(WorkspaceEdit::new(changes)),
+                    ..Default::default()
+                });
+            }
+        }
+
+        actions
+    }
+
+    /// The edits to normalize all the colors of the document into the notation configured
+    /// for its language, empty if there is none.
+    fn normalize_edits(&self, document: &Document) -> Vec<TextEdit> {
+        let relative_path = self.relative_path(&document.uri);
+        let Some(format) = self
+            .config
+            .read()
+            .unwrap()
+            .normalize_format(&document.language_id, relative_path.as_deref())
+        else {
+            return vec![];
+        };
+
+        let colors = self
+            .colors
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .cloned()
+            .unwrap_or_default();
+        colors
+            .iter()
+            .filter_map(|info| {
+                let original = document.text_at(info.range, self.position_encoding());
+                let color = csscolorparser::Color::new(
+                    info.color.red,
+                    info.color.green,
+                    info.color.blue,
+                    info.color.alpha,
+                );
+                normalize_color(&original, &color, format)
+                    .map(|text| TextEdit::new(info.range, text))
+            })
+            .collect()
+    }
+
+    /// The source action to normalize all the colors of the document.
+    fn normalize_action(&self, uri: &Url, kind: CodeActionKind) -> Option<CodeAction> {
+        let document = self.get_document(uri)?;
+        let edits = self.normalize_edits(&document);
+        if edits.is_empty() {
+            return None;
+        }
+
+        Some(CodeAction {
+            title: format!("Normalize {} colors", edits.len()),
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+            ..Default::default()
+        })
+    }
+
+    /// Run `color-lsp.normalizeColors`.
+    async fn normalize_colors(
+        &self,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Option<serde_json::Value>> {
+        let Some(args) = arguments
+            .into_iter()
+            .next()
+            .and_then(|arg| serde_json::from_value::<NormalizeColorsArgs>(arg).ok())
+        else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "Expected `{ uri, dry_run }` argument",
+            ));
+        };
+        let Some(document) = self.get_document(&args.uri) else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "The document is not opened",
+            ));
+        };
+
+        let edits = self.normalize_edits(&document);
+        let count = edits.len();
+        if args.dry_run {
+            self.client
+                .show_message(
+                    MessageType::INFO,
+                    format!("{} colors would be normalized", count),
+                )
+                .await;
+        } else if count > 0 {
+            let edit = WorkspaceEdit::new(HashMap::from([(args.uri, edits)]));
+            if let Err(err) = self.client.apply_edit(edit).await {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Failed to normalize the colors: {}", err),
+                    )
+                    .await;
+            }
+        }
+
+        Ok(Some(serde_json::json!({ "edits": count })))
+    }
+
+    /// Run `color-lsp.findNearDuplicates`, returns the clusters of near-duplicate colors.
+    async fn find_near_duplicates(
+        &self,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Option<serde_json::Value>> {
+        let args = match arguments.into_iter().next() {
+            Some(arg) => serde_json::from_value::<NearDuplicatesArgs>(arg).map_err(|_| {
+                tower_lsp::jsonrpc::Error::invalid_params("Expected `{ threshold }` argument")
+            })?,
+            None => NearDuplicatesArgs::default(),
+        };
+        let threshold = args
+            .threshold
+            .unwrap_or(self.settings().diagnostics.near_duplicate_threshold);
+
+        let hex =
+            |[r, g, b, a]: [u8; 4]| csscolorparser::Color::from_rgba8(r, g, b, a).to_css_hex();
+        let clusters = self.color_usages().clusters(threshold);
+        let count = clusters
+            .iter()
+            .map(|cluster| cluster.duplicates.len())
+            .sum::<usize>();
+        self.client
+            .show_message(
+                MessageType::INFO,
+                format!(
+                    "{} near-duplicate colors could be consolidated into {} colors",
+                    count,
+                    clusters.len()
+                ),
+            )
+            .await;
+
+        let clusters = clusters
+            .into_iter()
+            .map(|cluster| {
+                let duplicates = cluster
+                    .duplicates
+                    .into_iter()
+                    .map(|(rgba, delta_e)| {
+                        serde_json::json!({
+                            "color": hex(rgba),
+                            "delta_e": (delta_e as f64 * 100.).round() / 100.,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({ "canonical": hex(cluster.canonical), "duplicates": duplicates })
+            })
+            .collect::<Vec<_>>();
+        Ok(Some(serde_json::json!({ "clusters": clusters })))
+    }
+
+    /// Parse colors in the `text`, which starts at `line_offset` of the document.
+    fn scan_text(
+        text: &str,
+        line_offset: u32,
+        encoding: PositionEncoding,
+        options: &ParseOptions,
+        scope: Option<&Scope>,
+    ) -> Vec<ColorInformation> {
+        let nodes = crate::parser::parse_in_scope(text, options, scope);
+        let lines = text.lines().collect::<Vec<_>>();
         let mut colors = vec![];
         for node in nodes.iter() {
+            let line_text = lines[node.position.line as usize];
+            let start = node.position.character as usize;
+            let end = start + node.matched.len();
+            let line = node.position.line + line_offset;
             let info = ColorInformation {
                 range: lsp_types::Range {
-                    start: node.position,
+                    start: lsp_types::Position {
+                        line,
+                        character: encoding.character(line_text, start),
+                    },
                     end: lsp_types::Position {
-                        line: node.position.line,
-                        character: node.position.character + node.matched.len() as u32,
+                        line,
+                        character: encoding.character(line_text, end),
                     },
                 },
                 color: node.lsp_color(),
@@ -98,9 +1339,7 @@ impl Backend {
             colors.push(info);
         }
 
-        if let Ok(mut map) = self.colors.write() {
-            map.insert(document.uri.clone(), colors);
-        }
+        colors
     }
 }
 
@@ -112,12 +1351,29 @@ impl LanguageServer for Backend {
             self.set_work_dir(root_path.clone());
         }
 
+        let position_encoding = PositionEncoding::negotiate(&params.capabilities);
+        *self.position_encoding.write().unwrap() = position_encoding;
+
+        *self.supports_configuration.write().unwrap() = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.configuration)
+            .unwrap_or(false);
+        if let Some(options) = params.initialization_options {
+            match Settings::from_value(options) {
+                Ok(settings) => *self.settings.write().unwrap() = settings.unwrap_or_default(),
+                Err(err) => self.client.log_message(MessageType::WARNING, err).await,
+            }
+        }
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: LSP_NAME.into(),
                 version: Some(env!("CARGO_PKG_VERSION").into()),
             }),
             capabilities: ServerCapabilities {
+                position_encoding: Some(position_encoding.kind()),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -128,7 +1384,7 @@ impl LanguageServer for Backend {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
                     TextDocumentSyncOptions {
                         open_close: Some(true),
-                        change: Some(TextDocumentSyncKind::FULL),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
                         ..Default::default()
                     },
                 )),
@@ -137,18 +1393,61 @@ impl LanguageServer for Backend {
                     CodeActionOptions {
                         code_action_kinds: Some(vec![
                             CodeActionKind::QUICKFIX,
-                            CodeActionKind::SOURCE_FIX_ALL,
+                            CodeActionKind::REFACTOR_REWRITE,
+                            FIX_ALL_KIND,
+                            ORGANIZE_COLORS_KIND,
                         ]),
                         ..Default::default()
                     },
                 )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        NORMALIZE_COLORS_COMMAND.into(),
+                        FIND_NEAR_DUPLICATES_COMMAND.into(),
+                    ],
+                    ..Default::default()
+                }),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
                 ..ServerCapabilities::default()
             },
         })
     }
 
-    async fn initialized(&self, _: InitializedParams) {}
+    async fn initialized(&self, _: InitializedParams) {
+        self.pull_settings().await;
+        self.load_config().await;
+
+        // Watch the files to reload the config and update the index when they are changed,
+        // before the indexing, which queues the changes until it's done.
+        let watchers = vec![FileSystemWatcher {
+            glob_pattern: GlobPattern::String("**/*".into()),
+            kind: None,
+        }];
+        let registration = Registration {
+            id: "color-lsp-file-watcher".into(),
+            method: "workspace/didChangeWatchedFiles".into(),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        };
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Failed to watch the files: {}", err),
+                )
+                .await;
+        }
+
+        self.spawn_index_workspace();
+    }
 
     async fn shutdown(&self) -> Result<()> {
         Ok(())
@@ -156,13 +1455,20 @@ impl LanguageServer for Backend {
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let DidOpenTextDocumentParams { text_document } = params;
-        self.upsert_document(Arc::new(text_document.clone()));
-        self.scan_document(&text_document).await;
+        let document = Document::new(text_document);
+        self.update_variables(&document);
+        self.scan_document(&document).await;
+        self.diagnose_document(&document).await;
+        self.upsert_document(document);
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let DidCloseTextDocumentParams { text_document } = params;
+        self.clear_diagnostics(&text_document.uri).await;
         self.remove_document(&text_document.uri);
+        // Drop the unsaved changes, and index the file from the disk if it's in the workspace.
+        let change = FileEvent::new(text_document.uri, FileChangeType::CHANGED);
+        self.apply_file_changes(vec![change]).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -172,19 +1478,215 @@ impl LanguageServer for Backend {
         } = params;
         let VersionedTextDocumentIdentifier { uri, version } = text_document;
 
-        assert_eq!(content_changes.len(), 1);
-        let change = content_changes.into_iter().next().unwrap();
-        assert!(change.range.is_none());
+        let Some(mut document) = self.get_document(&uri) else {
+            return;
+        };
+        document.version = version;
 
-        let updated_doc =
-            TextDocumentItem::new(uri.clone(), "".to_string(), version, change.text.clone());
+        // In Tree-sitter mode, the document is scanned once after all the changes.
+        let mut rescan = self
+            .parse_options(&document)
+            .is_some_and(|options| options.tree_sitter);
+        let encoding = self.position_encoding();
+        for change in content_changes.iter() {
+            match document.apply_change(change, encoding) {
+                Some(edit) => {
+                    if !rescan {
+                        self.scan_lines(&document, edit).await;
+                    }
+                    self.update_variable_lines(&document, edit);
+                }
+                None => {
+                    rescan = true;
+                    self.update_variables(&document);
+                }
+            }
+        }
+        if rescan {
+            self.scan_document(&document).await;
+        }
 
-        self.upsert_document(Arc::new(updated_doc.clone()));
-        self.scan_document(&updated_doc).await;
+        self.upsert_document(document);
+        self.schedule_diagnostics(uri, version);
     }
 
     async fn did_save(&self, _: DidSaveTextDocumentParams) {}
 
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        if params
+            .changes
+            .iter()
+            .any(|change| self.is_config_file(&change.uri))
+        {
+            // The ignored files and detectors may change, index them again.
+            self.load_config().await;
+            self.spawn_index_workspace();
+            return;
+        }
+
+        self.apply_file_changes(params.changes).await;
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Some clients only notify the change, the settings must be pulled.
+        if *self.supports_configuration.read().unwrap() {
+            self.pull_settings().await;
+        } else {
+            self.update_settings(params.settings).await;
+        }
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+
+        let Some(document) = self.get_document(&text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(name) = self.variable_at(&document, position) else {
+            return Ok(None);
+        };
+        let Some((uri, decl)) = self.lookup_variable(&document.uri, &name) else {
+            return Ok(None);
+        };
+        let location = self
+            .file_locations(&uri, vec![(decl.line, decl.name_range)])
+            .await
+            .into_iter()
+            .next();
+
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let ReferenceParams {
+            text_document_position,
+            context,
+            ..
+        } = params;
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = text_document_position;
+
+        let Some(document) = self.get_document(&text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(name) = self.variable_at(&document, position) else {
+            // The usages of the color literal, there is no declaration of a color.
+            return Ok(self.color_references(&document.uri, position).await);
+        };
+
+        // Collect the ranges first, the unopened files are read to locate them.
+        let mut ranges = vec![];
+        for (uri, variables) in self.variables.read().unwrap().iter() {
+            let mut file_ranges = vec![];
+            if context.include_declaration {
+                file_ranges.extend(
+                    variables
+                        .declarations
+                        .iter()
+                        .filter(|decl| decl.name == name)
+                        .map(|decl| (decl.line, decl.name_range.clone())),
+                );
+            }
+            file_ranges.extend(
+                variables
+                    .references
+                    .iter()
+                    .filter(|reference| reference.name == name)
+                    .map(|reference| (reference.line, reference.range.clone())),
+            );
+            if !file_ranges.is_empty() {
+                ranges.push((uri.clone(), file_ranges));
+            }
+        }
+
+        let mut locations = vec![];
+        for (uri, file_ranges) in ranges {
+            locations.extend(self.file_locations(&uri, file_ranges).await);
+        }
+        Ok(Some(locations))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params;
+
+        let Some(document) = self.get_document(&text_document.uri) else {
+            return Ok(None);
+        };
+        let range = self
+            .colors
+            .read()
+            .unwrap()
+            .get(&document.uri)
+            .and_then(|colors| {
+                colors
+                    .iter()
+                    .find(|info| info.range.start <= position && position <= info.range.end)
+                    .map(|info| info.range)
+            });
+
+        Ok(
+            range.map(|range| PrepareRenameResponse::RangeWithPlaceholder {
+                range,
+                placeholder: document.text_at(range, self.position_encoding()),
+            }),
+        )
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let RenameParams {
+            text_document_position,
+            new_name,
+            ..
+        } = params;
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = text_document_position;
+
+        let Ok(color) = try_parse_color(new_name.trim()) else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "`{}` is not a color",
+                new_name
+            )));
+        };
+        let Some(occurrences) = self.same_colors(&text_document.uri, position).await else {
+            return Ok(None);
+        };
+
+        // Each occurrence is written in its own notation.
+        let changes = occurrences
+            .into_iter()
+            .map(|(uri, ranges)| {
+                let hex_literal_order = self.hex_literal_order(&uri);
+                let edits = ranges
+                    .into_iter()
+                    .filter_map(|(range, original)| {
+                        let text = rewrite_color(&original, &color, hex_literal_order)?;
+                        Some(TextEdit::new(range, text))
+                    })
+                    .collect::<Vec<_>>();
+                (uri, edits)
+            })
+            .filter(|(_, edits)| !edits.is_empty())
+            .collect::<HashMap<_, _>>();
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
     async fn formatting(&self, _: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
         Ok(None)
     }
@@ -193,27 +1695,152 @@ impl LanguageServer for Backend {
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
 
-        if let Some(colors) = self.colors.read().unwrap().get(&uri) {
-            for color_info in colors.iter() {
-                if position >= color_info.range.start && position <= color_info.range.end {
-                    let contents = HoverContents::Markup(MarkupContent {
-                        kind: MarkupKind::Markdown,
-                        value: color_summary(color_info.color),
-                    });
-
-                    return Ok(Some(Hover {
-                        contents,
-                        range: Some(color_info.range),
-                    }));
-                }
+        let settings = self.settings();
+        let mut colors = self
+            .colors
+            .read()
+            .unwrap()
+            .get(&uri)
+            .cloned()
+            .unwrap_or_default();
+        let document = self.get_document(&uri);
+        if let Some(document) = &document {
+            colors.extend(
+                self.variable_colors(document)
+                    .into_iter()
+                    .map(|(_, info)| info),
+            );
+        }
+
+        for color_info in colors.iter() {
+            if position >= color_info.range.start && position <= color_info.range.end {
+                // Note how a CSS Color 4/5 color out of the sRGB gamut is displayed.
+                let mapping = document.as_ref().and_then(|document| {
+                    let text = document.text_at(color_info.range, self.position_encoding());
+                    evaluate(&text).and_then(|(_, mapping)| mapping)
+                });
+                let summary = color_summary(color_info.color, &settings.hover);
+                let value = match (summary, mapping) {
+                    (Some(summary), Some(mapping)) => format!("{}\n\n{}", summary, mapping),
+                    (Some(summary), None) => summary,
+                    (None, Some(mapping)) => mapping.to_string(),
+                    (None, None) => return Ok(None),
+                };
+                let contents = HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                });
+
+                return Ok(Some(Hover {
+                    contents,
+                    range: Some(color_info.range),
+                }));
             }
         }
 
         Ok(None)
     }
 
-    async fn code_action(&self, _: CodeActionParams) -> Result<Option<CodeActionResponse>> {
-        return Ok(None);
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let CodeActionParams {
+            text_document,
+            range,
+            context,
+            ..
+        } = params;
+
+        let mut actions: CodeActionResponse = vec![];
+        if is_requested(&context, &CodeActionKind::QUICKFIX) {
+            actions.extend(
+                quick_fixes(&text_document.uri, &context.diagnostics)
+                    .map(CodeActionOrCommand::CodeAction),
+            );
+        }
+        if is_requested(&context, &CodeActionKind::REFACTOR_REWRITE) {
+            actions.extend(
+                self.conversion_actions(&text_document.uri, range)
+                    .into_iter()
+                    .map(CodeActionOrCommand::CodeAction),
+            );
+        }
+        // Both kinds are the same action, only offer it once.
+        if let Some(kind) = [FIX_ALL_KIND, ORGANIZE_COLORS_KIND]
+            .into_iter()
+            .find(|kind| is_requested(&context, kind))
+        {
+            if let Some(action) = self.normalize_action(&text_document.uri, kind) {
+                actions.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+
+        if actions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            NORMALIZE_COLORS_COMMAND => self.normalize_colors(params.arguments).await,
+            FIND_NEAR_DUPLICATES_COMMAND => self.find_near_duplicates(params.arguments).await,
+            _ => Err(tower_lsp::jsonrpc::Error::method_not_found()),
+        }
+    }
+
+    async fn color_presentation(
+        &self,
+        params: ColorPresentationParams,
+    ) -> Result<Vec<ColorPresentation>> {
+        let ColorPresentationParams {
+            text_document,
+            color,
+            range,
+            ..
+        } = params;
+
+        let document = self.get_document(&text_document.uri);
+
+        // At a `var()` usage, the color is edited where it's declared.
+        let usage = document.as_ref().and_then(|document| {
+            self.variable_colors(document)
+                .into_iter()
+                .find(|(_, info)| info.range == range)
+        });
+        if let (Some(document), Some((usage, _))) = (document.as_ref(), usage) {
+            return Ok(self.variable_presentations(document, &usage, range, color));
+        }
+
+        let original = document
+            .as_ref()
+            .map(|document| document.text_at(range, self.position_encoding()))
+            .unwrap_or_default();
+        let hex_literal_order = document
+            .as_ref()
+            .and_then(|document| self.parse_options(document))
+            .unwrap_or_default()
+            .hex_literal_order;
+        let color = csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha);
+
+        let settings = self.settings();
+        let presentations = color_presentations(
+            &original,
+            &color,
+            &settings.presentations,
+            settings.alpha,
+            hex_literal_order,
+        )
+        .into_iter()
+        .map(|label| ColorPresentation {
+            text_edit: Some(TextEdit::new(range, label.clone())),
+            label,
+            additional_text_edits: None,
+        })
+        .collect();
+
+        Ok(presentations)
     }
 
     async fn document_color(&self, params: DocumentColorParams) -> Result<Vec<ColorInformation>> {
@@ -224,13 +1851,20 @@ impl LanguageServer for Backend {
         //     )
         //     .await;
 
-        let colors = self
+        let mut colors = self
             .colors
             .read()
             .unwrap()
             .get(&params.text_document.uri)
             .cloned()
             .unwrap_or_default();
+        if let Some(document) = self.get_document(&params.text_document.uri) {
+            colors.extend(
+                self.variable_colors(&document)
+                    .into_iter()
+                    .map(|(_, info)| info),
+            );
+        }
 
         // self.client
         //     .log_message(MessageType::INFO, format!("document_color {:?}\n", colors))
@@ -240,16 +1874,82 @@ impl LanguageServer for Backend {
     }
 }
 
+/// The quick-fixes of our diagnostics, from the replacement in `data.fix`, or the
+/// replacements in `data.fixes` with the first one preferred.
+fn quick_fixes<'a>(
+    uri: &'a Url,
+    diagnostics: &'a [Diagnostic],
+) -> impl Iterator<Item = CodeAction> + 'a {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.source.as_deref() == Some(LSP_NAME))
+        .flat_map(move |diagnostic| {
+            let data = diagnostic.data.as_ref();
+            let fixes = match data.and_then(|data| data.get("fixes")) {
+                Some(fixes) => fixes.as_array().cloned().unwrap_or_default(),
+                None => data
+                    .and_then(|data| data.get("fix"))
+                    .cloned()
+                    .into_iter()
+                    .collect(),
+            };
+            fixes
+                .into_iter()
+                .filter_map(|fix| fix.as_str().map(String::from))
+                .enumerate()
+                .map(move |(i, fix)| {
+                    let edit = TextEdit::new(diagnostic.range, fix.clone());
+                    CodeAction {
+                        title: format!("Replace with `{}`", fix),
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        diagnostics: Some(vec![diagnostic.clone()]),
+                        edit: Some(WorkspaceEdit::new(HashMap::from([(
+                            uri.clone(),
+                            vec![edit],
+                        )]))),
+                        is_preferred: Some(i == 0),
+                        ..Default::default()
+                    }
+                })
+        })
+}
+
+/// Whether the client asks for the code actions of `kind`.
+fn is_requested(context: &CodeActionContext, kind: &CodeActionKind) -> bool {
+    match &context.only {
+        Some(only) => only.iter().any(|requested| {
+            kind.as_str() == requested.as_str()
+                || kind
+                    .as_str()
+                    .strip_prefix(requested.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        }),
+        None => true,
+    }
+}
+
 pub async fn start() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
     let (service, socket) = LspService::new(|client| Backend {
         client,
-        work_dir: RwLock::new(PathBuf::new()),
-        documents: RwLock::new(HashMap::new()),
-        diagnostics: RwLock::new(HashMap::new()),
-        colors: RwLock::new(HashMap::new()),
+        work_dir: Arc::new(RwLock::new(PathBuf::new())),
+        position_encoding: Arc::new(RwLock::new(PositionEncoding::default())),
+        config: Arc::new(RwLock::new(Config::default())),
+        palette: Arc::new(RwLock::new(Palette::default())),
+        tailwind_theme: Arc::new(RwLock::new(Arc::default())),
+        settings: Arc::new(RwLock::new(Settings::default())),
+        supports_configuration: Arc::new(RwLock::new(false)),
+        documents: Arc::new(RwLock::new(HashMap::new())),
+        diagnostics: Arc::new(RwLock::new(HashMap::new())),
+        colors: Arc::new(RwLock::new(HashMap::new())),
+        variables: Arc::new(RwLock::new(BTreeMap::new())),
+        usages: Arc::new(RwLock::new(HashMap::new())),
+        scopes: Arc::new(RwLock::new(HashMap::new())),
+        color_index: Arc::new(RwLock::new(BTreeMap::new())),
+        pending_changes: Arc::default(),
+        index_generation: Arc::default(),
     });
 
     Server::new(stdin, stdout, socket).serve(service).await;
@@ -1,7 +1,3 @@
-mod lsp;
-mod parser;
-mod utils;
-
 #[tokio::main]
 async fn main() {
     if std::env::args()
@@ -21,5 +17,5 @@ async fn main() {
         return;
     }
 
-    lsp::start().await;
+    color_lsp::start().await;
 }
@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+
+use csscolorparser::Color;
+use serde::Deserialize;
+
+use crate::delta_e::ciede2000;
+use crate::parser::try_parse_color;
+use crate::variables::{parse_variables, Syntax, MAX_DEPTH};
+
+/// The approved palette in the project config.
+///
+/// ```toml
+/// [palette]
+/// colors = ["#3366ff", "#333333", "white"]
+/// # A design token file, relative to the workspace root.
+/// tokens = "design/colors.tokens.json"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct PaletteConfig {
+    pub(crate) colors: Vec<String>,
+    pub(crate) tokens: Option<PathBuf>,
+}
+
+/// A color of the palette.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PaletteColor {
+    /// The token name, or the color as written in the config.
+    pub(crate) name: String,
+    pub(crate) color: Color,
+}
+
+impl PaletteColor {
+    /// The name with the hex color if it's a token, e.g. `brand.primary` (#3366ff).
+    pub(crate) fn label(&self) -> String {
+        if try_parse_color(&self.name).is_ok() {
+            format!("`{}`", self.name)
+        } else {
+            format!("`{}` ({})", self.name, self.color.to_css_hex())
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Palette {
+    colors: Vec<PaletteColor>,
+}
+
+impl Palette {
+    /// Load the palette of the config, the token file is relative to the `work_dir`.
+    pub(crate) fn load(config: &PaletteConfig, work_dir: &Path) -> Result<Self, String> {
+        let mut colors = config
+            .colors
+            .iter()
+            .map(|name| {
+                let color = try_parse_color(name)
+                    .map_err(|err| format!("Invalid palette color `{}`: {}", name, err))?;
+                Ok(PaletteColor {
+                    name: name.clone(),
+                    color,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if let Some(tokens) = config.tokens.as_ref() {
+            let path = work_dir.join(tokens);
+            let text = std::fs::read_to_string(&path)
+                .map_err(|err| format!("Failed to read the palette {}: {}", path.display(), err))?;
+            colors.extend(Self::token_colors(&text));
+        }
+
+        Ok(Self { colors })
+    }
+
+    /// The color tokens of a design token file, the `{path.to.token}` aliases are resolved.
+    fn token_colors(text: &str) -> Vec<PaletteColor> {
+        let declarations = parse_variables(text, Syntax::Tokens).declarations;
+        let resolve = |value: &str| {
+            let mut value = value.to_string();
+            for _ in 0..MAX_DEPTH {
+                let Some(name) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) else {
+                    return try_parse_color(&value).ok();
+                };
+                value = declarations
+                    .iter()
+                    .find(|decl| decl.name == name)?
+                    .value
+                    .clone();
+            }
+            None
+        };
+
+        declarations
+            .iter()
+            .filter_map(|decl| {
+                Some(PaletteColor {
+                    name: decl.name.clone(),
+                    color: resolve(&decl.value)?,
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Whether the color is in the palette, whatever its alpha.
+    ///
+    /// The fully transparent colors are always allowed.
+    pub(crate) fn contains(&self, color: &Color) -> bool {
+        let [r, g, b, a] = color.to_rgba8();
+        a == 0
+            || self.colors.iter().any(|entry| {
+                let [er, eg, eb, _] = entry.color.to_rgba8();
+                [er, eg, eb] == [r, g, b]
+            })
+    }
+
+    /// The `count` nearest palette colors by CIEDE2000, with their distance.
+    pub(crate) fn nearest(&self, color: &Color, count: usize) -> Vec<(&PaletteColor, f32)> {
+        let mut nearest = self
+            .colors
+            .iter()
+            .map(|entry| (entry, ciede2000(&entry.color, color)))
+            .collect::<Vec<_>>();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        // The same color may be in the list and the tokens.
+        nearest.dedup_by_key(|(entry, _)| entry.color.to_rgba8());
+        nearest.truncate(count);
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use csscolorparser::Color;
+    use indoc::indoc;
+
+    use super::{Palette, PaletteConfig};
+
+    #[test]
+    fn test_load() {
+        let config = PaletteConfig {
+            colors: vec!["#3366ff".into(), "white".into()],
+            tokens: None,
+        };
+        let palette = Palette::load(&config, Path::new(".")).unwrap();
+        assert!(palette.contains(&Color::from_rgba8(0x33, 0x66, 0xff, 255)));
+        assert!(palette.contains(&Color::from_rgba8(0x33, 0x66, 0xff, 128)));
+        assert!(palette.contains(&Color::from_rgba8(255, 255, 255, 255)));
+        assert!(palette.contains(&Color::from_rgba8(1, 2, 3, 0)));
+        assert!(!palette.contains(&Color::from_rgba8(0x33, 0x66, 0xfe, 255)));
+
+        let config = PaletteConfig {
+            colors: vec!["#zzz".into()],
+            tokens: None,
+        };
+        assert!(Palette::load(&config, Path::new(".")).is_err());
+
+        let config = PaletteConfig {
+            colors: vec![],
+            tokens: Some("missing.tokens.json".into()),
+        };
+        assert!(Palette::load(&config, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_token_colors() {
+        let colors = Palette::token_colors(indoc! {r##"
+            {
+              "color": {
+                "blue": { "$value": "#3366ff" },
+                "primary": { "$value": "{color.blue}" },
+                "loop": { "$value": "{color.loop}" }
+              },
+              "space": { "sm": { "$value": "4px" } }
+            }
+        "##});
+        let colors = colors
+            .into_iter()
+            .map(|entry| (entry.name, entry.color.to_css_hex()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            [
+                ("color.blue".to_string(), "#3366ff".to_string()),
+                ("color.primary".into(), "#3366ff".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nearest() {
+        let config = PaletteConfig {
+            colors: vec![
+                "#3366ff".into(),
+                "#333".into(),
+                "#ff0000".into(),
+                "#3366FF".into(),
+            ],
+            tokens: None,
+        };
+        let palette = Palette::load(&config, Path::new(".")).unwrap();
+        let nearest = palette
+            .nearest(&Color::from_rgba8(0x34, 0x34, 0x34, 255), 2)
+            .into_iter()
+            .map(|(entry, _)| entry.label())
+            .collect::<Vec<_>>();
+        assert_eq!(nearest, ["`#333`", "`#3366ff`"]);
+    }
+}
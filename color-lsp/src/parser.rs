@@ -1,6 +1,15 @@
-use csscolorparser::{Color, ParseColorError};
+use std::ops::Range;
+use std::sync::Arc;
+
+use csscolorparser::{Color, ParseColorError, NAMED_COLORS};
+use serde::Deserialize;
 use tower_lsp::lsp_types;
 
+use crate::constructor::match_constructor;
+use crate::css_color::match_css_color;
+use crate::syntax_tree::Scope;
+use crate::tailwind::{match_tailwind, TailwindTheme};
+
 #[derive(Debug, Clone)]
 pub struct ColorNode {
     pub color: Color,
@@ -20,7 +29,7 @@ impl PartialEq for ColorNode {
 impl ColorNode {
     /// Create a new ColorNode
     ///
-    /// `line`, `character` is 0-based
+    /// `line` is 0-based, `character` is the 0-based byte offset in the line.
     pub fn new(matched: &str, color: Color, line: usize, character: usize) -> Self {
         Self {
             matched: matched.to_string(),
@@ -45,7 +54,12 @@ impl ColorNode {
     }
 }
 
-fn try_parse_color(s: &str) -> Result<Color, ParseColorError> {
+pub(crate) fn try_parse_color(s: &str) -> Result<Color, ParseColorError> {
+    // Colors are always ASCII, and csscolorparser may panic on slicing multi-byte chars.
+    if !s.is_ascii() {
+        return Err(ParseColorError::InvalidUnknown);
+    }
+
     if let Ok(color) = try_parse_gpui_color(s) {
         return Ok(color);
     }
@@ -54,7 +68,7 @@ fn try_parse_color(s: &str) -> Result<Color, ParseColorError> {
 }
 
 /// Try to parse gpui color that values are 0..1
-fn try_parse_gpui_color(s: &str) -> Result<Color, ParseColorError> {
+pub(crate) fn try_parse_gpui_color(s: &str) -> Result<Color, ParseColorError> {
     let s = s.trim();
 
     /// Parse and ensure all value in 0..1
@@ -109,120 +123,363 @@ fn try_parse_gpui_color(s: &str) -> Result<Color, ParseColorError> {
     Err(ParseColorError::InvalidUnknown)
 }
 
-fn is_hex_char(c: &char) -> bool {
-    matches!(c, '#' | 'a'..='f' | 'A'..='F' | '0'..='9')
+/// The color functions to detect, ref https://github.com/mazznoer/csscolorparser-rs
+pub(crate) const COLOR_FUNCTIONS: [&str; 11] = [
+    "hsl", "hsla", "rgb", "rgba", "hwb", "hwba", "oklab", "oklch", "lab", "lch", "hsv",
+];
+
+/// The separators that end a CSS declaration value or a markup text, see [`parse_line`].
+pub(crate) const VALUE_END_CHARS: [u8; 7] = [b';', b'{', b'}', b'"', b'\'', b'<', b'>'];
+
+fn is_hex_char(b: &u8) -> bool {
+    *b == b'#' || b.is_ascii_hexdigit()
+}
+
+/// The channel order of 8-digit `0x` hex literals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HexLiteralOrder {
+    /// `0xRRGGBBAA`
+    #[default]
+    Rgba,
+    /// `0xAARRGGBB`
+    Argb,
 }
 
-fn is_hex_digit(c: &char) -> bool {
-    matches!(c, 'a'..='f' | 'A'..='F' | '0'..='9')
+/// Options to control which colors are detected by [`parse_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Detect `#RGB`, `#RGBA`, `#RRGGBB` and `#RRGGBBAA`.
+    pub hex: bool,
+    /// Detect `0xRGB`, `0xRRGGBB` and `0xRRGGBBAA` literals.
+    pub hex_literal: bool,
+    /// Detect color functions, e.g. `rgb()`, `hsla()` and `oklch()`.
+    pub functions: bool,
+    /// Detect CSS named colors, e.g. `tomato`.
+    pub named: bool,
+    /// Detect Tailwind CSS color utilities, e.g. `bg-blue-500` and `text-[#ff0]/50`.
+    ///
+    /// On by default, as the options know no language: the project config turns it off for the
+    /// documents of the languages without utility classes.
+    pub tailwind: bool,
+    /// Detect shader vectors of 0..1 floats, e.g. `vec3<f32>(1.0, 0.5, 0.2)`, off by default.
+    pub shader: bool,
+    /// The minimum number of digits of `#` and `0x` hex colors.
+    pub min_hex_length: usize,
+    /// The channel order of 8-digit `0x` literals.
+    pub hex_literal_order: HexLiteralOrder,
+    /// The Tailwind CSS palette and the custom colors of the project.
+    pub tailwind_theme: Arc<TailwindTheme>,
+    /// Only detect the colors in the string literals, CSS values and color calls found by
+    /// Tree-sitter, for the languages with a grammar.
+    pub tree_sitter: bool,
+    /// The language id of the document, for the Tree-sitter mode.
+    pub language_id: String,
+    /// The Tree-sitter queries of the language, besides the default ones.
+    pub queries: Vec<String>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            hex: true,
+            hex_literal: true,
+            functions: true,
+            named: true,
+            tailwind: true,
+            shader: false,
+            min_hex_length: 3,
+            hex_literal_order: HexLiteralOrder::default(),
+            tailwind_theme: Arc::default(),
+            tree_sitter: false,
+            language_id: String::new(),
+            queries: vec![],
+        }
+    }
 }
 
 /// Parse the text and return a list of ColorNode
 pub fn parse(text: &str) -> Vec<ColorNode> {
+    parse_with_options(text, &ParseOptions::default())
+}
+
+/// Parse the text with options and return a list of ColorNode
+pub fn parse_with_options(text: &str, options: &ParseOptions) -> Vec<ColorNode> {
+    parse_in_scope(text, options, Scope::new(text, options).as_ref())
+}
+
+/// Parse the text with options, keeping the colors in the prebuilt Tree-sitter `scope` of it.
+pub(crate) fn parse_in_scope(
+    text: &str,
+    options: &ParseOptions,
+    scope: Option<&Scope>,
+) -> Vec<ColorNode> {
     let mut nodes = Vec::new();
 
     for (ix, line_text) in text.lines().enumerate() {
-        let line_len = line_text.len();
-        // offset is 0-based character index
-        let mut offset = 0;
-        let mut token = String::new();
-        while offset < line_text.chars().count() {
-            let c = line_text.chars().nth(offset).unwrap_or(' ');
-            match c {
-                '#' => {
-                    token.clear();
-
-                    // Find the hex color code
-                    let hex = line_text
-                        .chars()
-                        .skip(offset)
-                        .take_while(is_hex_char)
-                        .take(9)
-                        .collect::<String>();
-                    if let Some(node) = match_color(&hex, ix, offset) {
+        parse_line(line_text, ix, options, &mut nodes);
+    }
+
+    if let Some(scope) = scope {
+        nodes.retain(|node| {
+            let start = node.position.character as usize;
+            scope.contains(
+                node.position.line as usize,
+                start..start + node.matched.len(),
+            )
+        });
+    }
+
+    nodes
+}
+
+/// Scan a line in a single forward pass over the bytes.
+///
+/// All the prefixes (`#`, `0x`, function names) are ASCII, so multi-byte
+/// UTF-8 characters are simply treated as separators.
+fn parse_line(line_text: &str, ix: usize, options: &ParseOptions, nodes: &mut Vec<ColorNode>) {
+    let bytes = line_text.as_bytes();
+    // offset is 0-based byte index
+    let mut offset = 0;
+    // The start of the current `[a-zA-Z]+` word, which may be a function name.
+    let mut word_start = 0;
+    // The index of the next `)` (or the line length if there is none),
+    // cached to avoid rescanning the rest of the line for every unclosed function.
+    let mut next_close: Option<usize> = None;
+    // Whether we are in the value of a color property, e.g. after `color:` or `border:`.
+    let mut in_color_value = false;
+
+    while offset < bytes.len() {
+        let byte = bytes[offset];
+        if options.named && word_start < offset && !byte.is_ascii_alphabetic() && byte != b'(' {
+            if let Some(node) = match_named_color(line_text, ix, word_start..offset, in_color_value)
+            {
+                nodes.push(node);
+            }
+        }
+
+        match byte {
+            b'#' if options.hex => {
+                // Find the hex color code
+                let len = bytes[offset..]
+                    .iter()
+                    .take_while(|b| is_hex_char(b))
+                    .take(9)
+                    .count();
+                let hex = &line_text[offset..offset + len];
+                if len > options.min_hex_length {
+                    if let Some(node) = match_color(hex, ix, offset) {
                         nodes.push(node);
-                        offset += hex.chars().count();
+                        offset += len;
+                        word_start = offset;
                         continue;
                     }
                 }
-                '0' => {
-                    token.clear();
-
-                    // Check if this is a Rust hex literal (0x or 0X)
-                    if let Some(next_char) = line_text.chars().nth(offset + 1) {
-                        if next_char == 'x' || next_char == 'X' {
-                            // Find the hex color code
-                            let hex_digits = line_text
-                                .chars()
-                                .skip(offset + 2)
-                                .take_while(is_hex_digit)
-                                .take(8)
-                                .collect::<String>();
-
-                            // Convert 0x format to # format for parsing
-                            if !hex_digits.is_empty()
-                                && (hex_digits.len() == 3
-                                    || hex_digits.len() == 6
-                                    || hex_digits.len() == 8)
-                            {
-                                let hex_color = format!("#{}", hex_digits);
-                                if let Ok(color) = try_parse_color(&hex_color) {
-                                    // Store the original 0x format
-                                    let original = format!("0{}{}", next_char, hex_digits);
-                                    let node = ColorNode::new(&original, color, ix, offset);
-                                    nodes.push(node);
-                                    offset += 2 + hex_digits.chars().count();
-                                    continue;
-                                }
-                            }
+            }
+            // Rust hex literal (0x or 0X)
+            b'0' if options.hex_literal && matches!(bytes.get(offset + 1), Some(b'x' | b'X')) => {
+                let len = bytes[offset + 2..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .take(8)
+                    .count();
+
+                // Convert 0x format to # format for parsing
+                if matches!(len, 3 | 6 | 8) && len >= options.min_hex_length {
+                    let hex_digits = &line_text[offset + 2..offset + 2 + len];
+                    let hex_color = match options.hex_literal_order {
+                        HexLiteralOrder::Argb if len == 8 => {
+                            format!("#{}{}", &hex_digits[2..], &hex_digits[..2])
                         }
+                        _ => format!("#{}", hex_digits),
+                    };
+                    if let Ok(color) = try_parse_color(&hex_color) {
+                        // Store the original 0x format
+                        let original = &line_text[offset..offset + 2 + len];
+                        nodes.push(ColorNode::new(original, color, ix, offset));
+                        offset += 2 + len;
+                        word_start = offset;
+                        continue;
+                    }
+                }
+            }
+            b'a'..=b'z' if options.tailwind && word_start == offset => {
+                if let Some(node) = match_tailwind(line_text, ix, offset, &options.tailwind_theme) {
+                    offset += node.matched.len();
+                    word_start = offset;
+                    nodes.push(node);
+                    continue;
+                }
+                offset += 1;
+                continue;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' => {
+                offset += 1;
+                continue;
+            }
+            b'(' if options.functions || options.shader => {
+                // The constructors first, e.g. gpui `rgb(0x3366ff)` or bevy `Color::hsl(...)`
+                if let Some((range, color)) = match_constructor(line_text, offset, options) {
+                    nodes.push(ColorNode::new(
+                        &line_text[range.clone()],
+                        color,
+                        ix,
+                        range.start,
+                    ));
+                    offset = range.end;
+                    word_start = offset;
+                    continue;
+                }
+
+                // CSS Color 4/5, e.g. `color(display-p3 1 0 0)` or `rgb(from red r g b)`
+                if options.functions {
+                    if let Some((range, color)) = match_css_color(line_text, offset) {
+                        nodes.push(ColorNode::new(
+                            &line_text[range.clone()],
+                            color,
+                            ix,
+                            range.start,
+                        ));
+                        offset = range.end;
+                        word_start = offset;
+                        continue;
                     }
                 }
-                'a'..='z' | 'A'..='Z' | '(' => {
-                    // Avoid `Ok(hsla(`, to get `hsla(`
-                    if token.contains('(') {
-                        token.clear();
+
+                // Avoid `Ok(hsla(`, to get `hsla(`
+                let fname = &line_text[word_start..offset];
+                if options.functions && COLOR_FUNCTIONS.contains(&fname) {
+                    // Find until the closing parenthesis
+                    if next_close.is_none_or(|close| close < offset) {
+                        next_close = Some(
+                            line_text[offset..]
+                                .find(')')
+                                .map_or(bytes.len(), |pos| offset + pos),
+                        );
                     }
 
-                    token.push(c);
-                    match token.as_ref() {
-                        // Ref https://github.com/mazznoer/csscolorparser-rs
-                        "hsl(" | "hsla(" | "rgb(" | "rgba(" | "hwb(" | "hwba(" | "oklab("
-                        | "oklch(" | "lab(" | "lch(" | "hsv(" => {
-                            // Find until the closing parenthesis
-                            let end = line_text
-                                .chars()
-                                .skip(offset)
-                                .position(|c| c == ')')
-                                .unwrap_or(0);
-                            let token_offset = offset.saturating_sub(token.chars().count()) + 1;
-
-                            let range =
-                                (offset + 1).min(line_len)..(offset + end + 1).min(line_len);
-                            for c in line_text.chars().skip(range.start).take(range.len()) {
-                                token.push(c)
-                            }
-
-                            if let Some(node) = match_color(&token, ix, token_offset) {
-                                token.clear();
-                                nodes.push(node);
-                                offset += end + 1;
-                                continue;
-                            }
+                    let close = next_close.unwrap_or(bytes.len());
+                    if close < bytes.len() {
+                        let token = &line_text[word_start..=close];
+                        if let Some(node) = match_color(token, ix, word_start) {
+                            nodes.push(node);
+                            offset = close + 1;
+                            word_start = offset;
+                            continue;
                         }
-                        _ => {}
                     }
                 }
-                _ => {
-                    token.clear();
-                }
             }
+            b':' => {
+                in_color_value = is_color_property(property_name(&line_text[..offset]));
+            }
+            byte if VALUE_END_CHARS.contains(&byte) => {
+                in_color_value = false;
+            }
+            _ => {}
+        }
+
+        offset += 1;
+        word_start = offset;
+    }
 
-            offset += 1;
+    if options.named {
+        if let Some(node) = match_named_color(line_text, ix, word_start..offset, in_color_value) {
+            nodes.push(node);
         }
     }
+}
 
-    nodes
+/// Whether the word is a CSS named color or `transparent`, case-insensitive.
+pub(crate) fn is_named_color(word: &str) -> bool {
+    word.eq_ignore_ascii_case("transparent")
+        || NAMED_COLORS.contains_key(word.to_ascii_lowercase().as_str())
+}
+
+/// Match a CSS named color (or `transparent`) word, only in the contexts that are plausibly colors:
+///
+/// - The value of a color property, e.g. `color: rebeccapurple;` or `border: 1px solid red`.
+/// - A quoted string value of JSON/YAML, e.g. `"background": "white"` or `fg: 'red'`.
+/// - A quoted string value of a color attribute or key, e.g. `fill="tomato"` or `color = "red"`.
+///
+/// So words like `red` in prose, identifiers like `red_500` and functions like `tan()` are ignored.
+/// `currentColor` is not a color value, so it's never matched.
+fn match_named_color(
+    line_text: &str,
+    line_ix: usize,
+    range: Range<usize>,
+    in_color_value: bool,
+) -> Option<ColorNode> {
+    let bytes = line_text.as_bytes();
+    let word = &line_text[range.clone()];
+    if !is_named_color(word) {
+        return None;
+    }
+
+    let prev = range.start.checked_sub(1).map(|ix| bytes[ix]);
+    let next = bytes.get(range.end).copied();
+    let is_ident_char =
+        |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'$' | b'@' | b'#');
+    if prev.is_some_and(is_ident_char) || next.is_some_and(|b| is_ident_char(b) || b == b'(') {
+        return None;
+    }
+
+    let quoted = match (prev, next) {
+        (Some(open @ (b'"' | b'\'')), Some(close)) if open == close => {
+            let before = line_text[..range.start - 1].trim_end();
+            match before.strip_suffix('=') {
+                // Only the color attributes, e.g. `fill="tomato"` but not `class="red"`
+                Some(key) => is_color_property(property_name(key)),
+                None => before.ends_with(':'),
+            }
+        }
+        _ => false,
+    };
+
+    if in_color_value || quoted {
+        match_color(word, line_ix, range.start)
+    } else {
+        None
+    }
+}
+
+/// Return the property name before the `:` at the end of `text`, e.g. `background-color` in `a { background-color:`.
+fn property_name(text: &str) -> &str {
+    let text = text.trim_end();
+    let start = text
+        .bytes()
+        .rposition(|b| !(b.is_ascii_alphanumeric() || b == b'-' || b == b'_'))
+        .map_or(0, |ix| ix + 1);
+    &text[start..]
+}
+
+/// Whether the CSS property (or a config key like `foreground_color`) takes a color value.
+fn is_color_property(name: &str) -> bool {
+    if name.starts_with("--") {
+        return true;
+    }
+
+    let name = name.trim_start_matches('-').to_ascii_lowercase();
+    // Skip the vendor prefix, e.g. `-webkit-text-stroke`
+    let name = ["webkit-", "moz-", "ms-", "o-"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(&name);
+
+    name.contains("color")
+        || name.ends_with("shadow")
+        || matches!(name, "fill" | "stroke" | "outline" | "background")
+        || [
+            "background-",
+            "border",
+            "outline-",
+            "column-rule",
+            "text-decoration",
+            "text-emphasis",
+            "text-stroke",
+        ]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
 }
 
 fn match_color(part: &str, line_ix: usize, character: usize) -> Option<ColorNode> {
@@ -238,7 +495,10 @@ mod tests {
     use csscolorparser::Color;
     use tower_lsp::lsp_types;
 
-    use crate::parser::{match_color, parse, try_parse_gpui_color, ColorNode};
+    use crate::parser::{
+        match_color, parse, parse_with_options, try_parse_gpui_color, ColorNode, HexLiteralOrder,
+        ParseOptions,
+    };
 
     #[test]
     fn test_match_color() {
@@ -335,6 +595,219 @@ mod tests {
         assert!((colors[0].color.b - 0.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_parse_long_line() {
+        let rule = ".a{color:#ff0;background:rgba(0,0,0,.5)}.b{margin:0 auto}";
+        let text = rule.repeat(10_000);
+        let colors = parse(&text);
+        assert_eq!(colors.len(), 20_000);
+        assert_eq!(colors[2], ColorNode::must_parse("#ff0", 0, rule.len() + 9));
+        assert_eq!(
+            colors[3],
+            ColorNode::must_parse("rgba(0,0,0,.5)", 0, rule.len() + 25)
+        );
+
+        let colors = parse(&"rgb(".repeat(10_000));
+        assert!(colors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_non_ascii() {
+        let text = "/* 颜色 */ color: hsl(120, 😀 50%, 50%); background: 😀#ff0;";
+        let colors = parse(text);
+        assert_eq!(colors.len(), 1);
+        assert_eq!(
+            colors[0],
+            ColorNode::must_parse("#ff0", 0, text.find('#').unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_named_colors() {
+        let matched = |text: &str| {
+            parse(text)
+                .into_iter()
+                .map(|node| node.matched)
+                .collect::<Vec<_>>()
+        };
+
+        // CSS property values
+        assert_eq!(matched("a { color: rebeccapurple; }"), ["rebeccapurple"]);
+        assert_eq!(
+            matched("a{border:1px solid Red;background:transparent}"),
+            ["Red", "transparent"]
+        );
+        assert_eq!(
+            matched("box-shadow: 0 0 1px tomato, 0 0 2px navy !important;"),
+            ["tomato", "navy"]
+        );
+        assert_eq!(
+            matched("background: linear-gradient(white, black);"),
+            ["white", "black"]
+        );
+        assert_eq!(matched("  --brand: gold;"), ["gold"]);
+        assert_eq!(matched(r#"<p style="color: red">red</p>"#), ["red"]);
+
+        // SVG/HTML color attributes
+        assert_eq!(
+            matched(r#"<circle fill="tomato" stroke='blue' class="red" />"#),
+            ["tomato", "blue"]
+        );
+
+        // JSON/YAML/TOML values
+        assert_eq!(
+            matched(r#"  "editor.background": "white", "name": "Blue Theme","#),
+            ["white"]
+        );
+        assert_eq!(matched("accent: 'Orange'"), ["Orange"]);
+        assert_eq!(matched(r#"link_color = "teal""#), ["teal"]);
+        assert_eq!(matched(r#"name = "tan""#), Vec::<String>::new());
+
+        // Not colors
+        for text in [
+            "The red car is blue",
+            "Note: red is a color",
+            "font-family: Tan, serif;",
+            "width: tan(45deg);",
+            "color: currentColor;",
+            "color: red-500;",
+            "let c = colors.red;",
+            "let c = \"red\";",
+            "fn red() -> Color",
+        ] {
+            assert!(matched(text).is_empty(), "{text:?} should not match");
+        }
+
+        let colors = parse("a { color: red; }");
+        assert_eq!(colors[0], ColorNode::must_parse("red", 0, 11));
+        assert_eq!(colors[0].color, Color::new(1., 0., 0., 1.));
+    }
+
+    #[test]
+    fn test_parse_with_options() {
+        let text =
+            "mask = 0xFFF; color: #fff; fg = 0x80FF0000; bg: rgb(0, 0, 255); a { color: red }";
+        let matched = |options: &ParseOptions| {
+            parse_with_options(text, options)
+                .into_iter()
+                .map(|node| node.matched)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matched(&ParseOptions::default()),
+            ["0xFFF", "#fff", "0x80FF0000", "rgb(0, 0, 255)", "red"]
+        );
+        assert_eq!(
+            matched(&ParseOptions {
+                min_hex_length: 6,
+                ..Default::default()
+            }),
+            ["0x80FF0000", "rgb(0, 0, 255)", "red"]
+        );
+        assert_eq!(
+            matched(&ParseOptions {
+                hex: false,
+                hex_literal: false,
+                ..Default::default()
+            }),
+            ["rgb(0, 0, 255)", "red"]
+        );
+        assert_eq!(
+            matched(&ParseOptions {
+                functions: false,
+                named: false,
+                ..Default::default()
+            }),
+            ["0xFFF", "#fff", "0x80FF0000"]
+        );
+
+        let colors = parse_with_options(
+            text,
+            &ParseOptions {
+                hex_literal_order: HexLiteralOrder::Argb,
+                ..Default::default()
+            },
+        );
+        assert_eq!(colors[2].color.to_rgba8(), [255, 0, 0, 128]);
+        assert_eq!(colors[0].color.to_rgba8(), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_parse_shader() {
+        let text =
+            "let c = vec3<f32>(1.0, 0.5, 0.2) * vec4(0.1, 0.2, 0.3, 1.0) + vec3(x, 0.5, 0.2) + vec4<f32>(0.2, 0.4, 0.6, 0.5);";
+        assert!(parse(text).is_empty());
+
+        let options = ParseOptions {
+            shader: true,
+            functions: false,
+            ..Default::default()
+        };
+        let colors = parse_with_options(text, &options);
+        assert_eq!(
+            colors
+                .iter()
+                .map(|node| node.matched.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "vec3<f32>(1.0, 0.5, 0.2)",
+                "vec4(0.1, 0.2, 0.3, 1.0)",
+                "vec4<f32>(0.2, 0.4, 0.6, 0.5)"
+            ]
+        );
+        assert_eq!(colors[0].position, lsp_types::Position::new(0, 8));
+        assert_eq!(colors[1].color.to_rgba8(), [26, 51, 77, 255]);
+    }
+
+    #[test]
+    fn test_parse_css_color() {
+        let text = "a { color: color(display-p3 0.2 0.4 0.6); background: color-mix(in srgb, red 25%, blue); border-color: rgb(from #336699 r g calc(b + 20)) rgb(0 0 255); }";
+        let colors = parse(text);
+        assert_eq!(
+            colors
+                .iter()
+                .map(|node| (node.matched.as_str(), node.color.to_css_hex()))
+                .collect::<Vec<_>>(),
+            [
+                ("color(display-p3 0.2 0.4 0.6)", "#1b689d".into()),
+                ("color-mix(in srgb, red 25%, blue)", "#4000bf".into()),
+                ("rgb(from #336699 r g calc(b + 20))", "#3366ad".into()),
+                ("rgb(0 0 255)", "#0000ff".into()),
+            ]
+        );
+        assert_eq!(colors[1].position, lsp_types::Position::new(0, 54));
+
+        // The colors of a mix with a variable are still detected.
+        let colors = parse("a { color: color-mix(in srgb, red, var(--accent)); }");
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors[0].matched, "red");
+    }
+
+    #[test]
+    fn test_parse_constructors() {
+        let text = "const a = Color(0x80336699), b = Color.fromRGBO(51, 102, 153, 0.5);\nval c = Color(0.2f, 0.4f, 0.6f); MyColor(1, 2, 3) #colorLiteral(red: 1, green: 0, blue: 0, alpha: 1)\nrgb(0x336699) Color::hsl(210., 0.5, 0.4)";
+        let colors = parse(text);
+        assert_eq!(
+            colors
+                .iter()
+                .map(|node| (node.matched.as_str(), node.color.to_css_hex()))
+                .collect::<Vec<_>>(),
+            [
+                ("Color(0x80336699)", "#33669980".to_string()),
+                ("Color.fromRGBO(51, 102, 153, 0.5)", "#33669980".to_string()),
+                ("Color(0.2f, 0.4f, 0.6f)", "#336699".to_string()),
+                (
+                    "#colorLiteral(red: 1, green: 0, blue: 0, alpha: 1)",
+                    "#ff0000".to_string()
+                ),
+                ("rgb(0x336699)", "#336699".to_string()),
+                ("Color::hsl(210., 0.5, 0.4)", "#336699".to_string()),
+            ]
+        );
+        assert_eq!(colors[2].position, lsp_types::Position::new(1, 8));
+    }
+
     #[test]
     fn test_try_parse_gpui_color() {
         assert_eq!(
@@ -416,7 +889,8 @@ mod tests {
             colors[7],
             ColorNode::must_parse("hsl(225, 100%, 70%)", 8, 10)
         );
-        assert_eq!(colors[8], ColorNode::must_parse("#EEAAFF", 9, 9));
+        // `中文` is 6 bytes in UTF-8
+        assert_eq!(colors[8], ColorNode::must_parse("#EEAAFF", 9, 13));
 
         let colors = parse(include_str!("../../tests/test.rs"));
         assert_eq!(colors.len(), 5);
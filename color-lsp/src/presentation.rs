@@ -0,0 +1,1073 @@
+use csscolorparser::Color;
+use serde::Deserialize;
+
+use crate::constructor::Constructor;
+use crate::css_color::{format_predefined, is_expression, predefined_space, Space};
+use crate::parser::{is_named_color, try_parse_gpui_color, HexLiteralOrder};
+use crate::tailwind::{is_color_utility, rewrite_utility};
+use crate::utils::format_trimmed;
+
+/// The notation family of a color literal, used to write an edited color back
+/// in the same form the user originally wrote it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorFormat {
+    /// CSS named color like `tomato`, or lowercase hex when the color has no name.
+    Named,
+    /// `#RRGGBB`, or `#RRGGBBAA` when `alpha` is set or the color is translucent.
+    Hex { upper: bool, alpha: bool },
+    /// `0xRRGGBB`, or `0xRRGGBBAA` (`0xAARRGGBB` in ARGB order) when `alpha` is set or the color is translucent.
+    HexLiteral {
+        upper: bool,
+        alpha: bool,
+        order: HexLiteralOrder,
+    },
+    /// `rgb(r, g, b)` / `rgba(r, g, b, a)`, channels in 0..255.
+    Rgb { alpha: bool },
+    /// `hsl(h, s%, l%)` / `hsla(h, s%, l%, a)`
+    Hsl { alpha: bool },
+    /// `hsv(h, s%, v%)` / `hsva(h, s%, v%, a)`
+    Hsv { alpha: bool },
+    /// `hwb(h w% b%)`
+    Hwb,
+    /// `oklab(l a b)`
+    Oklab,
+    /// `oklch(l c h)`
+    Oklch,
+    /// `lab(l a b)`
+    Lab,
+    /// `lch(l c h)`
+    Lch,
+    /// gpui `rgb(0.2, 0.4, 0.6)` / `rgba(0.2, 0.4, 0.6, 1.)`, all values in 0..1.
+    FloatRgb { alpha: bool },
+    /// gpui `hsl(0.3, 1., 0.5)` / `hsla(0.3, 1., 0.5, 1.)`, all values in 0..1.
+    FloatHsl { alpha: bool },
+    /// `color(display-p3 r g b)` in a predefined space of CSS Color 4.
+    Predefined(Space),
+    /// A constructor of a UI framework, e.g. Flutter `Color.fromARGB(255, 51, 102, 153)`.
+    Constructor(Constructor),
+}
+
+impl ColorFormat {
+    /// Detect the format of a matched color literal, e.g. `#FF0000` or `hsla(0.3, 1.0, 0.5, 1.0)`.
+    pub(crate) fn detect(matched: &str) -> Option<Self> {
+        let matched = matched.trim();
+        let upper = !matched.chars().skip(1).any(|c| c.is_ascii_lowercase());
+
+        if let Some(digits) = matched.strip_prefix('#') {
+            return Some(Self::Hex {
+                upper,
+                alpha: digits.len() == 4 || digits.len() == 8,
+            });
+        }
+
+        if let Some(digits) = matched
+            .strip_prefix("0x")
+            .or_else(|| matched.strip_prefix("0X"))
+        {
+            let upper = !digits.chars().any(|c| c.is_ascii_lowercase());
+            return Some(Self::HexLiteral {
+                upper,
+                alpha: digits.len() == 8,
+                order: HexLiteralOrder::Rgba,
+            });
+        }
+
+        if is_named_color(matched) {
+            return Some(Self::Named);
+        }
+
+        if let Some((constructor, _)) = Constructor::parse(matched) {
+            return Some(Self::Constructor(constructor));
+        }
+
+        // `color-mix()` and the relative colors are kept as written.
+        if is_expression(matched) {
+            return None;
+        }
+        if let Some(space) = predefined_space(matched) {
+            return Some(Self::Predefined(space));
+        }
+
+        let fname = matched[..matched.find('(')?]
+            .trim_end()
+            .to_ascii_lowercase();
+        let alpha = fname.ends_with('a');
+        // Rust float literals always have a `.`, so `rgb(0, 0, 0)` is still a CSS color.
+        if matched.contains('.') && try_parse_gpui_color(matched).is_ok() {
+            return match fname.as_str() {
+                "rgb" | "rgba" => Some(Self::FloatRgb { alpha }),
+                "hsl" | "hsla" => Some(Self::FloatHsl { alpha }),
+                _ => None,
+            };
+        }
+
+        match fname.as_str() {
+            "rgb" | "rgba" => Some(Self::Rgb { alpha }),
+            "hsl" | "hsla" => Some(Self::Hsl { alpha }),
+            "hsv" | "hsva" => Some(Self::Hsv { alpha }),
+            "hwb" | "hwba" => Some(Self::Hwb),
+            "oklab" => Some(Self::Oklab),
+            "oklch" => Some(Self::Oklch),
+            "lab" => Some(Self::Lab),
+            "lch" => Some(Self::Lch),
+            _ => None,
+        }
+    }
+
+    /// Set whether to write the hex digits in uppercase, for the hex formats.
+    pub(crate) fn set_upper(&mut self, value: bool) {
+        match self {
+            Self::Hex { upper, .. } | Self::HexLiteral { upper, .. } => *upper = value,
+            Self::Constructor(constructor) => constructor.set_upper(value),
+            _ => {}
+        }
+    }
+
+    /// Set whether to always use the alpha notation, for the formats that have one.
+    fn set_alpha(&mut self, value: bool) {
+        match self {
+            Self::Hex { alpha, .. }
+            | Self::HexLiteral { alpha, .. }
+            | Self::Rgb { alpha }
+            | Self::Hsl { alpha }
+            | Self::Hsv { alpha }
+            | Self::FloatRgb { alpha }
+            | Self::FloatHsl { alpha } => *alpha = value,
+            Self::Constructor(constructor) => constructor.set_alpha(value),
+            _ => {}
+        }
+    }
+
+    /// Whether the notation is valid CSS, e.g. in a Tailwind CSS arbitrary value.
+    fn is_css(&self) -> bool {
+        !matches!(
+            self,
+            Self::HexLiteral { .. }
+                | Self::Hsv { .. }
+                | Self::FloatRgb { .. }
+                | Self::FloatHsl { .. }
+                | Self::Constructor(_)
+        )
+    }
+
+    /// Format the color in this notation.
+    pub(crate) fn format(&self, color: &Color) -> String {
+        let [r, g, b, a] = color.to_rgba8();
+        let translucent = a < 255;
+
+        match *self {
+            Self::Constructor(constructor) => constructor.format(color),
+            Self::Predefined(space) => format_predefined(space, color),
+            Self::Named => {
+                if [r, g, b, a] == [0, 0, 0, 0] {
+                    return "transparent".into();
+                }
+
+                match color.name() {
+                    Some(name) if !translucent => name.into(),
+                    _ => format!("#{}", hex_digits(color, false, translucent)),
+                }
+            }
+            Self::Hex { upper, alpha } => {
+                format!("#{}", hex_digits(color, upper, alpha || translucent))
+            }
+            Self::HexLiteral {
+                upper,
+                alpha,
+                order,
+            } => {
+                let digits = hex_digits(color, upper, alpha || translucent);
+                match order {
+                    HexLiteralOrder::Argb if digits.len() == 8 => {
+                        format!("0x{}{}", &digits[6..], &digits[..6])
+                    }
+                    _ => format!("0x{}", digits),
+                }
+            }
+            Self::Rgb { alpha } => {
+                if alpha || translucent {
+                    format!("rgba({}, {}, {}, {})", r, g, b, format_alpha(color.a))
+                } else {
+                    format!("rgb({}, {}, {})", r, g, b)
+                }
+            }
+            Self::Hsl { alpha } => {
+                let [h, s, l, _] = color.to_hsla();
+                let (h, s, l) = (
+                    format_trimmed(hue(h), 1, true),
+                    format_trimmed(s * 100., 1, true),
+                    format_trimmed(l * 100., 1, true),
+                );
+                if alpha || translucent {
+                    format!("hsla({}, {}%, {}%, {})", h, s, l, format_alpha(color.a))
+                } else {
+                    format!("hsl({}, {}%, {}%)", h, s, l)
+                }
+            }
+            Self::Hsv { alpha } => {
+                let [h, s, v, _] = color.to_hsva();
+                let (h, s, v) = (
+                    format_trimmed(hue(h), 1, true),
+                    format_trimmed(s * 100., 1, true),
+                    format_trimmed(v * 100., 1, true),
+                );
+                if alpha || translucent {
+                    format!("hsva({}, {}%, {}%, {})", h, s, v, format_alpha(color.a))
+                } else {
+                    format!("hsv({}, {}%, {}%)", h, s, v)
+                }
+            }
+            Self::Hwb => {
+                let [h, w, b, _] = color.to_hwba();
+                format!(
+                    "hwb({} {}% {}%{})",
+                    format_trimmed(hue(h), 1, true),
+                    format_trimmed(w * 100., 1, true),
+                    format_trimmed(b * 100., 1, true),
+                    alpha_suffix(color.a)
+                )
+            }
+            Self::Oklab => {
+                let [l, a, b, _] = color.to_oklaba();
+                format!(
+                    "oklab({} {} {}{})",
+                    format_trimmed(l, 3, true),
+                    format_trimmed(a, 3, true),
+                    format_trimmed(b, 3, true),
+                    alpha_suffix(color.a)
+                )
+            }
+            Self::Oklch => {
+                let [l, c, h, _] = color.to_oklcha();
+                format!(
+                    "oklch({} {} {}{})",
+                    format_trimmed(l, 3, true),
+                    format_trimmed(c, 3, true),
+                    format_trimmed(hue(h.to_degrees()), 1, true),
+                    alpha_suffix(color.a)
+                )
+            }
+            Self::Lab => {
+                let [l, a, b, _] = color.to_laba();
+                format!(
+                    "lab({} {} {}{})",
+                    format_trimmed(l, 2, true),
+                    format_trimmed(a, 2, true),
+                    format_trimmed(b, 2, true),
+                    alpha_suffix(color.a)
+                )
+            }
+            Self::Lch => {
+                let [l, c, h, _] = color.to_lcha();
+                format!(
+                    "lch({} {} {}{})",
+                    format_trimmed(l, 2, true),
+                    format_trimmed(c, 2, true),
+                    format_trimmed(hue(h.to_degrees()), 1, true),
+                    alpha_suffix(color.a)
+                )
+            }
+            Self::FloatRgb { alpha } => {
+                let (r, g, b) = (
+                    format_trimmed(color.r, 3, false),
+                    format_trimmed(color.g, 3, false),
+                    format_trimmed(color.b, 3, false),
+                );
+                if alpha || translucent {
+                    let a = format_trimmed(color.a, 3, false);
+                    format!("rgba({}, {}, {}, {})", r, g, b, a)
+                } else {
+                    format!("rgb({}, {}, {})", r, g, b)
+                }
+            }
+            Self::FloatHsl { alpha } => {
+                let [h, s, l, _] = color.to_hsla();
+                let (h, s, l) = (
+                    format_trimmed(hue(h) / 360., 3, false),
+                    format_trimmed(s, 3, false),
+                    format_trimmed(l, 3, false),
+                );
+                if alpha || translucent {
+                    let a = format_trimmed(color.a, 3, false);
+                    format!("hsla({}, {}, {}, {})", h, s, l, a)
+                } else {
+                    format!("hsl({}, {}, {})", h, s, l)
+                }
+            }
+        }
+    }
+
+    /// A short description of the notation, e.g. `#RRGGBB` or `rgba()`.
+    ///
+    /// `translucent` tells whether the color will be written with alpha.
+    pub(crate) fn notation(&self, translucent: bool) -> String {
+        let with_alpha = |alpha: bool, name: &str| {
+            if alpha || translucent {
+                format!("{}a()", name)
+            } else {
+                format!("{}()", name)
+            }
+        };
+
+        match *self {
+            Self::Named => "named color".into(),
+            Self::Hex { alpha, .. } if alpha || translucent => "#RRGGBBAA".into(),
+            Self::Hex { .. } => "#RRGGBB".into(),
+            Self::HexLiteral { alpha, order, .. } if alpha || translucent => match order {
+                HexLiteralOrder::Rgba => "0xRRGGBBAA".into(),
+                HexLiteralOrder::Argb => "0xAARRGGBB".into(),
+            },
+            Self::HexLiteral { .. } => "0xRRGGBB".into(),
+            Self::Rgb { alpha } => with_alpha(alpha, "rgb"),
+            Self::Hsl { alpha } => with_alpha(alpha, "hsl"),
+            Self::Hsv { alpha } => with_alpha(alpha, "hsv"),
+            Self::Hwb => "hwb()".into(),
+            Self::Oklab => "oklab()".into(),
+            Self::Oklch => "oklch()".into(),
+            Self::Lab => "lab()".into(),
+            Self::Lch => "lch()".into(),
+            Self::FloatRgb { alpha } => format!("{} floats", with_alpha(alpha, "rgb")),
+            Self::FloatHsl { alpha } => format!("{} floats", with_alpha(alpha, "hsl")),
+            Self::Constructor(constructor) => constructor.notation(),
+            Self::Predefined(space) => format!("color({})", space.name()),
+        }
+    }
+}
+
+/// The formats that can be offered by the color picker, configured by the `presentations` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PresentationFormat {
+    Named,
+    Hex,
+    HexAlpha,
+    HexLiteral,
+    Rgb,
+    Hsl,
+    Hsv,
+    Hwb,
+    Oklab,
+    Oklch,
+    Lab,
+    Lch,
+    FloatRgb,
+    FloatHsl,
+}
+
+impl PresentationFormat {
+    pub(crate) fn color_format(&self, hex_literal_order: HexLiteralOrder) -> ColorFormat {
+        match self {
+            Self::Named => ColorFormat::Named,
+            Self::Hex => ColorFormat::Hex {
+                upper: true,
+                alpha: false,
+            },
+            Self::HexAlpha => ColorFormat::Hex {
+                upper: true,
+                alpha: true,
+            },
+            Self::HexLiteral => ColorFormat::HexLiteral {
+                upper: true,
+                alpha: false,
+                order: hex_literal_order,
+            },
+            Self::Rgb => ColorFormat::Rgb { alpha: false },
+            Self::Hsl => ColorFormat::Hsl { alpha: false },
+            Self::Hsv => ColorFormat::Hsv { alpha: false },
+            Self::Hwb => ColorFormat::Hwb,
+            Self::Oklab => ColorFormat::Oklab,
+            Self::Oklch => ColorFormat::Oklch,
+            Self::Lab => ColorFormat::Lab,
+            Self::Lch => ColorFormat::Lch,
+            Self::FloatRgb => ColorFormat::FloatRgb { alpha: true },
+            Self::FloatHsl => ColorFormat::FloatHsl { alpha: true },
+        }
+    }
+}
+
+/// The formats offered by the color picker by default, in order, after the original notation.
+pub(crate) const DEFAULT_PRESENTATIONS: [PresentationFormat; 9] = [
+    PresentationFormat::Hex,
+    PresentationFormat::HexAlpha,
+    PresentationFormat::Rgb,
+    PresentationFormat::Hsl,
+    PresentationFormat::Hwb,
+    PresentationFormat::Oklch,
+    PresentationFormat::Lab,
+    PresentationFormat::FloatHsl,
+    PresentationFormat::HexLiteral,
+];
+
+/// How to write the alpha channel in the color presentations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AlphaMode {
+    /// Keep the alpha notation of the format, and add alpha when the color is translucent.
+    #[default]
+    Auto,
+    /// Always use the alpha notation, e.g. `#RRGGBBAA` and `rgba()`.
+    Always,
+    /// Drop the alpha channel, e.g. `#RRGGBB` and `rgb()`.
+    Never,
+}
+
+/// Return the labels for `textDocument/colorPresentation`.
+///
+/// The first one keeps the notation of `original` (if it can be detected),
+/// followed by the `formats` without duplicates.
+///
+/// The `0x` literals are written in `hex_literal_order`, and a Tailwind CSS utility is
+/// written with the CSS formats as arbitrary values, e.g. `bg-[#3b82f6]`.
+pub(crate) fn color_presentations(
+    original: &str,
+    color: &Color,
+    formats: &[PresentationFormat],
+    alpha_mode: AlphaMode,
+    hex_literal_order: HexLiteralOrder,
+) -> Vec<String> {
+    let is_utility = is_color_utility(original);
+    let mut labels: Vec<String> = vec![];
+    let color = match alpha_mode {
+        AlphaMode::Never => Color::new(color.r, color.g, color.b, 1.),
+        _ => color.clone(),
+    };
+    let original_format = if is_utility {
+        None
+    } else {
+        ColorFormat::detect(original)
+    };
+    // The constructors are code, only offer the other constructors of the same framework.
+    let constructors = match original_format {
+        Some(ColorFormat::Constructor(constructor)) => Some(constructor.alternatives()),
+        _ => None,
+    };
+    let is_constructor = constructors.is_some();
+    let formats = original_format.into_iter().chain(
+        formats
+            .iter()
+            .map(|format| format.color_format(hex_literal_order))
+            .filter(|format| !is_constructor && (!is_utility || format.is_css()))
+            .chain(
+                constructors
+                    .into_iter()
+                    .flatten()
+                    .map(ColorFormat::Constructor),
+            ),
+    );
+
+    for mut format in formats {
+        if let ColorFormat::HexLiteral { order, .. } = &mut format {
+            *order = hex_literal_order;
+        }
+        match alpha_mode {
+            AlphaMode::Auto => {}
+            AlphaMode::Always => format.set_alpha(true),
+            AlphaMode::Never => format.set_alpha(false),
+        }
+
+        let mut label = format.format(&color);
+        if is_utility {
+            label = rewrite_utility(original, &label).unwrap_or(label);
+        }
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+
+    labels
+}
+
+/// The target formats of the "Convert to" code actions.
+const CONVERSION_FORMATS: [ColorFormat; 5] = [
+    ColorFormat::Hex {
+        upper: false,
+        alpha: false,
+    },
+    ColorFormat::Rgb { alpha: false },
+    ColorFormat::Hsl { alpha: false },
+    ColorFormat::Oklch,
+    ColorFormat::HexLiteral {
+        upper: true,
+        alpha: false,
+        order: HexLiteralOrder::Rgba,
+    },
+];
+
+/// Return the `(title, text)` of the conversions of the `original` color literal,
+/// e.g. `("Convert to rgb()", "rgb(255, 0, 0)")`.
+///
+/// The notation of `original` is not offered, and the hex digits keep its case.
+pub(crate) fn color_conversions(
+    original: &str,
+    color: &Color,
+    hex_literal_order: HexLiteralOrder,
+) -> Vec<(String, String)> {
+    let original_format = ColorFormat::detect(original);
+    if is_color_utility(original) || matches!(original_format, Some(ColorFormat::Constructor(_))) {
+        return vec![];
+    }
+
+    let upper = match original_format {
+        Some(ColorFormat::Hex { upper, .. }) | Some(ColorFormat::HexLiteral { upper, .. }) => {
+            Some(upper)
+        }
+        _ => None,
+    };
+    let translucent = color.to_rgba8()[3] < 255;
+
+    let mut conversions = vec![];
+    for mut format in CONVERSION_FORMATS {
+        if original_format.is_some_and(|original| {
+            std::mem::discriminant(&original) == std::mem::discriminant(&format)
+        }) {
+            continue;
+        }
+
+        match &mut format {
+            ColorFormat::Hex { upper: value, .. } => *value = upper.unwrap_or(*value),
+            ColorFormat::HexLiteral {
+                upper: value,
+                order,
+                ..
+            } => {
+                *value = upper.unwrap_or(*value);
+                *order = hex_literal_order;
+            }
+            _ => {}
+        }
+
+        let text = format.format(color);
+        if text != original {
+            let title = format!("Convert to {}", format.notation(translucent));
+            conversions.push((title, text));
+        }
+    }
+
+    conversions
+}
+
+/// Return the `original` color literal written in `format`, or `None` if it's already in that notation.
+///
+/// The numbers of the color functions already in `format` are kept as written, only the
+/// hex colors are rewritten in the same notation, e.g. `#FFF` to `#ffffff`.
+///
+/// The `0x` literals are numbers in code, so they are only normalized into `0x` literals,
+/// and the other notations are never normalized into them.
+pub(crate) fn normalize_color(
+    original: &str,
+    color: &Color,
+    format: ColorFormat,
+) -> Option<String> {
+    let original_format = ColorFormat::detect(original);
+    // The constructors are code and the CSS expressions are computed, they are never normalized.
+    if is_color_utility(original)
+        || is_expression(original)
+        || matches!(original_format, Some(ColorFormat::Constructor(_)))
+    {
+        return None;
+    }
+
+    let is_literal =
+        |format: Option<ColorFormat>| matches!(format, Some(ColorFormat::HexLiteral { .. }));
+    if is_literal(original_format) != is_literal(Some(format)) {
+        return None;
+    }
+
+    let is_hex = matches!(
+        format,
+        ColorFormat::Hex { .. } | ColorFormat::HexLiteral { .. }
+    );
+    if !is_hex
+        && original_format.is_some_and(|original| {
+            std::mem::discriminant(&original) == std::mem::discriminant(&format)
+        })
+    {
+        return None;
+    }
+
+    let text = format.format(color);
+    (text != original).then_some(text)
+}
+
+/// Return the `color` written in the notation of the `original` literal, to rename a color
+/// keeping each occurrence in its own form, `None` if the notation is unknown.
+///
+/// A Tailwind CSS utility is rewritten with a hex arbitrary value, e.g. `bg-[#ff0000]`.
+pub(crate) fn rewrite_color(
+    original: &str,
+    color: &Color,
+    hex_literal_order: HexLiteralOrder,
+) -> Option<String> {
+    if is_color_utility(original) {
+        let hex = ColorFormat::Hex {
+            upper: false,
+            alpha: false,
+        };
+        return rewrite_utility(original, &hex.format(color));
+    }
+
+    let mut format = ColorFormat::detect(original)?;
+    if let ColorFormat::HexLiteral { order, .. } = &mut format {
+        *order = hex_literal_order;
+    }
+    Some(format.format(color))
+}
+
+fn hex_digits(color: &Color, upper: bool, alpha: bool) -> String {
+    let [r, g, b, a] = color.to_rgba8();
+    let digits = if alpha {
+        format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    } else {
+        format!("{:02x}{:02x}{:02x}", r, g, b)
+    };
+
+    if upper {
+        digits.to_ascii_uppercase()
+    } else {
+        digits
+    }
+}
+
+/// Normalize hue degrees into 0..360, achromatic colors have a `NaN` hue.
+fn hue(h: f32) -> f32 {
+    if h.is_nan() {
+        0.
+    } else {
+        h.rem_euclid(360.)
+    }
+}
+
+fn format_alpha(a: f32) -> String {
+    format_trimmed(a, 3, true)
+}
+
+fn alpha_suffix(a: f32) -> String {
+    if a < 1. {
+        format!(" / {}", format_alpha(a))
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csscolorparser::Color;
+
+    use super::{
+        color_conversions, color_presentations, normalize_color, rewrite_color, AlphaMode,
+        ColorFormat, DEFAULT_PRESENTATIONS,
+    };
+    use crate::css_color::Space;
+    use crate::parser::HexLiteralOrder;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            ColorFormat::detect("#ff0000"),
+            Some(ColorFormat::Hex {
+                upper: false,
+                alpha: false
+            })
+        );
+        assert_eq!(
+            ColorFormat::detect("#FF000080"),
+            Some(ColorFormat::Hex {
+                upper: true,
+                alpha: true
+            })
+        );
+        assert_eq!(
+            ColorFormat::detect("0XAABBCC"),
+            Some(ColorFormat::HexLiteral {
+                upper: true,
+                alpha: false,
+                order: HexLiteralOrder::Rgba,
+            })
+        );
+        assert_eq!(
+            ColorFormat::detect("rgb(100, 200, 100)"),
+            Some(ColorFormat::Rgb { alpha: false })
+        );
+        assert_eq!(
+            ColorFormat::detect("hsla(20, 100%, 50%, .5)"),
+            Some(ColorFormat::Hsl { alpha: true })
+        );
+        assert_eq!(
+            ColorFormat::detect("hsla(0.3, 1.0, 0.5, 1.0)"),
+            Some(ColorFormat::FloatHsl { alpha: true })
+        );
+        assert_eq!(
+            ColorFormat::detect("rgb(0., 1., 0.2)"),
+            Some(ColorFormat::FloatRgb { alpha: false })
+        );
+        assert_eq!(
+            ColorFormat::detect("oklch(0.628 0.258 29.23)"),
+            Some(ColorFormat::Oklch)
+        );
+        assert_eq!(
+            ColorFormat::detect("rgb(0, 0, 0)"),
+            Some(ColorFormat::Rgb { alpha: false })
+        );
+        assert_eq!(
+            ColorFormat::detect("rebeccapurple"),
+            Some(ColorFormat::Named)
+        );
+        assert_eq!(
+            ColorFormat::detect("color(display-p3 1 0 0)"),
+            Some(ColorFormat::Predefined(Space::DisplayP3))
+        );
+        assert_eq!(ColorFormat::detect("rgb(from red r g b)"), None);
+        assert_eq!(ColorFormat::detect("color(from red srgb r g b)"), None);
+        assert_eq!(ColorFormat::detect("color-mix(in srgb, red, blue)"), None);
+        assert_eq!(ColorFormat::detect("foo(1, 2, 3)"), None);
+        assert_eq!(ColorFormat::detect("unknown"), None);
+        assert_eq!(ColorFormat::detect("Transparent"), Some(ColorFormat::Named));
+    }
+
+    #[test]
+    fn test_format() {
+        let color = Color::from_rgba8(238, 204, 0, 255);
+        let translucent = Color::from_rgba8(238, 204, 0, 128);
+
+        let cases = [
+            (
+                ColorFormat::Hex {
+                    upper: true,
+                    alpha: false,
+                },
+                "#EECC00",
+                "#EECC0080",
+            ),
+            (
+                ColorFormat::Hex {
+                    upper: false,
+                    alpha: true,
+                },
+                "#eecc00ff",
+                "#eecc0080",
+            ),
+            (
+                ColorFormat::HexLiteral {
+                    upper: false,
+                    alpha: false,
+                    order: HexLiteralOrder::Rgba,
+                },
+                "0xeecc00",
+                "0xeecc0080",
+            ),
+            (
+                ColorFormat::HexLiteral {
+                    upper: true,
+                    alpha: true,
+                    order: HexLiteralOrder::Argb,
+                },
+                "0xFFEECC00",
+                "0x80EECC00",
+            ),
+            (
+                ColorFormat::Rgb { alpha: false },
+                "rgb(238, 204, 0)",
+                "rgba(238, 204, 0, 0.502)",
+            ),
+            (
+                ColorFormat::Hsl { alpha: false },
+                "hsl(51.4, 100%, 46.7%)",
+                "hsla(51.4, 100%, 46.7%, 0.502)",
+            ),
+            (
+                ColorFormat::Hwb,
+                "hwb(51.4 0% 6.7%)",
+                "hwb(51.4 0% 6.7% / 0.502)",
+            ),
+            (
+                ColorFormat::FloatHsl { alpha: true },
+                "hsla(0.143, 1., 0.467, 1.)",
+                "hsla(0.143, 1., 0.467, 0.502)",
+            ),
+            (
+                ColorFormat::FloatRgb { alpha: false },
+                "rgb(0.933, 0.8, 0.)",
+                "rgba(0.933, 0.8, 0., 0.502)",
+            ),
+        ];
+
+        for (format, opaque, alpha) in cases {
+            assert_eq!(format.format(&color), opaque);
+            assert_eq!(format.format(&translucent), alpha);
+        }
+    }
+
+    #[test]
+    fn test_color_presentations() {
+        let presentations = |original: &str, color: &Color| {
+            color_presentations(
+                original,
+                color,
+                &DEFAULT_PRESENTATIONS,
+                AlphaMode::Auto,
+                HexLiteralOrder::Rgba,
+            )
+        };
+
+        let color = Color::from_hsla(0.3 * 360., 1.0, 0.5, 1.0);
+        let labels = presentations("hsla(0.3, 1.0, 0.5, 1.0)", &color);
+        assert_eq!(labels[0], "hsla(0.3, 1., 0.5, 1.)");
+        assert_eq!(
+            labels[1..],
+            [
+                "#33FF00",
+                "#33FF00FF",
+                "rgb(51, 255, 0)",
+                "hsl(108, 100%, 50%)",
+                "hwb(108 0% 0%)",
+                "oklch(0.87 0.29 141.5)",
+                "lab(88.07 -83.11 83.59)",
+                "0x33FF00",
+            ]
+        );
+
+        let color = Color::from_rgba8(255, 0, 0, 255);
+        let labels = presentations("#ff0000", &color);
+        assert_eq!(labels[0], "#ff0000");
+        assert_eq!(labels[1], "#FF0000");
+
+        let labels = presentations("unknown", &color);
+        assert_eq!(labels[0], "#FF0000");
+
+        let labels = presentations("tomato", &color);
+        assert_eq!(labels[0], "red");
+        let labels = presentations("tomato", &Color::from_rgba8(255, 0, 1, 255));
+        assert_eq!(labels[0], "#ff0001");
+        let labels = presentations("tomato", &Color::new(0., 0., 0., 0.));
+        assert_eq!(labels[0], "transparent");
+
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        let labels = color_presentations(
+            "0xFF336699",
+            &color,
+            &DEFAULT_PRESENTATIONS,
+            AlphaMode::Auto,
+            HexLiteralOrder::Argb,
+        );
+        assert_eq!(labels[0], "0x80FF0000");
+
+        let labels = presentations("color(srgb-linear 1 0 0)", &color);
+        assert_eq!(labels[0], "color(srgb-linear 1 0 0 / 0.502)");
+
+        let labels = presentations("Color.fromARGB(255, 51, 102, 153)", &color);
+        assert_eq!(
+            labels,
+            [
+                "Color.fromARGB(128, 255, 0, 0)",
+                "Color(0x80FF0000)",
+                "Color.fromRGBO(255, 0, 0, 0.502)",
+            ]
+        );
+        let labels = presentations("Color(51, 102, 153)", &color);
+        assert_eq!(labels[0], "Color(255, 0, 0, 128)");
+        assert_eq!(labels[2], "Color(1f, 0f, 0f, 0.502f)");
+    }
+
+    #[test]
+    fn test_color_presentations_with_settings() {
+        use super::PresentationFormat::*;
+
+        let color = Color::from_rgba8(255, 0, 0, 255);
+        let labels = color_presentations(
+            "#f00",
+            &color,
+            &[Rgb, Oklch],
+            AlphaMode::Auto,
+            HexLiteralOrder::Rgba,
+        );
+        assert_eq!(
+            labels,
+            ["#ff0000", "rgb(255, 0, 0)", "oklch(0.628 0.258 29.2)"]
+        );
+
+        let labels = color_presentations(
+            "#f00",
+            &color,
+            &[Rgb, Hwb],
+            AlphaMode::Always,
+            HexLiteralOrder::Rgba,
+        );
+        assert_eq!(labels, ["#ff0000ff", "rgba(255, 0, 0, 1)", "hwb(0 0% 0%)"]);
+
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        let labels = color_presentations(
+            "rgba(0, 0, 0, 50%)",
+            &color,
+            &[HexAlpha, FloatRgb],
+            AlphaMode::Never,
+            HexLiteralOrder::Rgba,
+        );
+        assert_eq!(labels, ["rgb(255, 0, 0)", "#FF0000", "rgb(1., 0., 0.)"]);
+
+        // Tailwind CSS utilities are written with arbitrary values.
+        let labels = color_presentations(
+            "text-red-500/50",
+            &color,
+            &[Hex, Rgb, HexLiteral, FloatRgb],
+            AlphaMode::Auto,
+            HexLiteralOrder::Rgba,
+        );
+        assert_eq!(
+            labels,
+            ["text-[#FF000080]", "text-[rgba(255,_0,_0,_0.502)]"]
+        );
+    }
+
+    #[test]
+    fn test_color_conversions() {
+        let color = Color::from_rgba8(255, 0, 0, 255);
+        assert_eq!(
+            color_conversions("#FF0000", &color, HexLiteralOrder::Rgba),
+            [
+                ("Convert to rgb()".to_string(), "rgb(255, 0, 0)".to_string()),
+                ("Convert to hsl()".into(), "hsl(0, 100%, 50%)".into()),
+                (
+                    "Convert to oklch()".into(),
+                    "oklch(0.628 0.258 29.2)".into()
+                ),
+                ("Convert to 0xRRGGBB".into(), "0xFF0000".into()),
+            ]
+        );
+
+        let titles = |original: &str, color: &Color, order: HexLiteralOrder| {
+            color_conversions(original, color, order)
+                .into_iter()
+                .map(|(title, _)| title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles("rgb(255, 0, 0)", &color, HexLiteralOrder::Rgba),
+            [
+                "Convert to #RRGGBB",
+                "Convert to hsl()",
+                "Convert to oklch()",
+                "Convert to 0xRRGGBB"
+            ]
+        );
+
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        let conversions = color_conversions("0x80ff0000", &color, HexLiteralOrder::Argb);
+        assert_eq!(
+            conversions,
+            [
+                ("Convert to #RRGGBBAA".to_string(), "#ff000080".to_string()),
+                ("Convert to rgba()".into(), "rgba(255, 0, 0, 0.502)".into()),
+                (
+                    "Convert to hsla()".into(),
+                    "hsla(0, 100%, 50%, 0.502)".into()
+                ),
+                (
+                    "Convert to oklch()".into(),
+                    "oklch(0.628 0.258 29.2 / 0.502)".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_color() {
+        let hex = ColorFormat::Hex {
+            upper: false,
+            alpha: false,
+        };
+        let color = Color::from_rgba8(255, 255, 255, 255);
+        assert_eq!(normalize_color("#FFF", &color, hex), Some("#ffffff".into()));
+        assert_eq!(
+            normalize_color("white", &color, hex),
+            Some("#ffffff".into())
+        );
+        assert_eq!(normalize_color("#ffffff", &color, hex), None);
+        assert_eq!(normalize_color("0xFFFFFF", &color, hex), None);
+        assert_eq!(normalize_color("Color(0xFFFFFFFF)", &color, hex), None);
+
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        assert_eq!(
+            normalize_color("rgba(255, 0, 0, 0.5)", &color, hex),
+            Some("#ff000080".into())
+        );
+
+        let float_hsl = ColorFormat::FloatHsl { alpha: true };
+        let color = Color::from_hsla(0.3 * 360., 1.0, 0.5, 1.0);
+        assert_eq!(
+            normalize_color("#33FF00", &color, float_hsl),
+            Some("hsla(0.3, 1., 0.5, 1.)".into())
+        );
+        assert_eq!(
+            normalize_color("hsla(0.3, 1.0, 0.5, 1.0)", &color, float_hsl),
+            None
+        );
+
+        let literal = ColorFormat::HexLiteral {
+            upper: true,
+            alpha: false,
+            order: HexLiteralOrder::Rgba,
+        };
+        assert_eq!(
+            normalize_color("0x33ff00", &color, literal),
+            Some("0x33FF00".into())
+        );
+        assert_eq!(normalize_color("#33ff00", &color, literal), None);
+
+        let color = Color::from_rgba8(51, 102, 173, 255);
+        for original in [
+            "rgb(from #336699 r g calc(b + 20))",
+            "color-mix(in srgb, red, blue)",
+        ] {
+            assert_eq!(normalize_color(original, &color, hex), None);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_color() {
+        let color = Color::from_rgba8(255, 0, 0, 255);
+        let rewrite = |original: &str| rewrite_color(original, &color, HexLiteralOrder::Rgba);
+        assert_eq!(rewrite("#3366FF"), Some("#FF0000".into()));
+        assert_eq!(rewrite("#36f"), Some("#ff0000".into()));
+        assert_eq!(rewrite("rgb(51, 102, 255)"), Some("rgb(255, 0, 0)".into()));
+        assert_eq!(
+            rewrite("hsla(0.6, 1., 0.6, 1.)"),
+            Some("hsla(0., 1., 0.5, 1.)".into())
+        );
+        assert_eq!(rewrite("0x3366ff"), Some("0xff0000".into()));
+        assert_eq!(rewrite("royalblue"), Some("red".into()));
+        assert_eq!(rewrite("not a color"), None);
+        assert_eq!(rewrite("rgb(from #336699 r g calc(b + 20))"), None);
+        assert_eq!(rewrite("bg-blue-500/50"), Some("bg-[#ff0000]".into()));
+        assert_eq!(
+            rewrite("Color(0xff3366ff)"),
+            Some("Color(0xffff0000)".into())
+        );
+        assert_eq!(rewrite("rgb(0x3366ff)"), Some("rgb(0xff0000)".into()));
+        assert_eq!(
+            rewrite("vec3<f32>(0.2, 0.4, 1.0)"),
+            Some("vec3<f32>(1.0, 0.0, 0.0)".into())
+        );
+        assert_eq!(
+            rewrite("Color::srgb_u8(51, 102, 255)"),
+            Some("Color::srgb_u8(255, 0, 0)".into())
+        );
+        assert_eq!(
+            rewrite("Srgb::new(0.2, 0.4, 1.)"),
+            Some("Srgb::new(1., 0., 0.)".into())
+        );
+        assert_eq!(
+            rewrite("UIColor(red: 0.2, green: 0.4, blue: 1, alpha: 1)"),
+            Some("UIColor(red: 1, green: 0, blue: 0, alpha: 1)".into())
+        );
+        assert_eq!(
+            rewrite("Color.fromRGBO(51, 102, 255, 1.0)"),
+            Some("Color.fromRGBO(255, 0, 0, 1.0)".into())
+        );
+
+        let color = Color::from_rgba8(255, 0, 0, 128);
+        assert_eq!(
+            rewrite_color("0xFF3366FF", &color, HexLiteralOrder::Argb),
+            Some("0x80FF0000".into())
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::Detectors;
+use crate::contrast::{ContrastOptions, WcagLevel};
+use crate::presentation::{AlphaMode, PresentationFormat, DEFAULT_PRESENTATIONS};
+
+/// The configuration section of the editor settings.
+pub(crate) const SETTINGS_SECTION: &str = "color-lsp";
+
+/// The runtime settings from the editor, sent as `initializationOptions`
+/// and updated by `workspace/didChangeConfiguration`.
+///
+/// ```json
+/// {
+///   "formats": { "named": false },
+///   "hover": { "formats": true, "contrast": true, "background": "#1e1e1e", "color_picker": false },
+///   "diagnostics": { "enabled": true, "malformed": true, "contrast": true, "contrast_level": "aa" },
+///   "presentations": ["hex", "rgb", "oklch"],
+///   "alpha": "auto"
+/// }
+/// ```
+///
+/// The project config (`.color-lsp.toml`) takes precedence over `formats`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Enable or disable the detectors.
+    pub(crate) formats: Detectors,
+    pub(crate) hover: HoverSettings,
+    pub(crate) diagnostics: DiagnosticsSettings,
+    /// The formats offered by the color picker, after the original notation.
+    pub(crate) presentations: Vec<PresentationFormat>,
+    pub(crate) alpha: AlphaMode,
+}
+
+/// The sections of the hover content.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(crate) struct HoverSettings {
+    /// Show the color in other formats.
+    pub(crate) formats: bool,
+    /// Show the contrast against white, black and the `background`.
+    pub(crate) contrast: bool,
+    /// The theme background color, e.g. `#1e1e1e`.
+    pub(crate) background: Option<String>,
+    /// Show the link to the color picker.
+    pub(crate) color_picker: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct DiagnosticsSettings {
+    pub(crate) enabled: bool,
+    /// Report the malformed color literals, e.g. `#12345` or `rgb(300, 0, 0)`.
+    pub(crate) malformed: bool,
+    /// Report the low contrast of the foreground/background pairs.
+    pub(crate) contrast: bool,
+    /// The WCAG level of the contrast check, "aa" or "aaa".
+    pub(crate) contrast_level: WcagLevel,
+    /// The minimum APCA `Lc` of the contrast check, e.g. 60, not checked if unset.
+    pub(crate) apca_min_lc: Option<f32>,
+    /// Report the colors nearly the same as a more used color of the workspace.
+    pub(crate) near_duplicates: bool,
+    /// The CIEDE2000 distance below which two colors are near-duplicates.
+    pub(crate) near_duplicate_threshold: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            formats: Detectors::default(),
+            hover: HoverSettings::default(),
+            diagnostics: DiagnosticsSettings::default(),
+            presentations: DEFAULT_PRESENTATIONS.to_vec(),
+            alpha: AlphaMode::default(),
+        }
+    }
+}
+
+impl Default for HoverSettings {
+    fn default() -> Self {
+        Self {
+            formats: true,
+            contrast: true,
+            background: None,
+            color_picker: true,
+        }
+    }
+}
+
+impl Default for DiagnosticsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            malformed: true,
+            contrast: true,
+            contrast_level: WcagLevel::default(),
+            apca_min_lc: None,
+            near_duplicates: false,
+            near_duplicate_threshold: 2.,
+        }
+    }
+}
+
+impl DiagnosticsSettings {
+    pub(crate) fn contrast_options(&self) -> ContrastOptions {
+        ContrastOptions {
+            level: self.contrast_level,
+            apca_min_lc: self.apca_min_lc,
+        }
+    }
+}
+
+impl Settings {
+    /// Parse the settings, returns `Ok(None)` if there are none.
+    ///
+    /// The settings may be nested in the `"color-lsp"` section, as in
+    /// `workspace/didChangeConfiguration` of some editors.
+    pub(crate) fn from_value(value: Value) -> Result<Option<Self>, String> {
+        let value = match value {
+            Value::Null => return Ok(None),
+            Value::Object(mut map) if map.contains_key(SETTINGS_SECTION) => {
+                map.remove(SETTINGS_SECTION).unwrap_or_default()
+            }
+            value => value,
+        };
+        if value.is_null() {
+            return Ok(None);
+        }
+
+        let settings: Self =
+            serde_json::from_value(value).map_err(|err| format!("Invalid settings: {}", err))?;
+        if let Some(background) = settings.hover.background.as_deref() {
+            csscolorparser::parse(background).map_err(|err| {
+                format!(
+                    "Invalid settings: hover.background `{}`, {}",
+                    background, err
+                )
+            })?;
+        }
+
+        Ok(Some(settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{HoverSettings, Settings};
+    use crate::contrast::{ContrastOptions, WcagLevel};
+    use crate::parser::ParseOptions;
+    use crate::presentation::{AlphaMode, PresentationFormat};
+
+    #[test]
+    fn test_from_value() {
+        assert_eq!(Settings::from_value(json!(null)), Ok(None));
+        assert_eq!(Settings::from_value(json!({ "color-lsp": null })), Ok(None));
+        assert_eq!(
+            Settings::from_value(json!({})),
+            Ok(Some(Settings::default()))
+        );
+
+        let settings = Settings::from_value(json!({
+            "formats": { "named": false },
+            "hover": { "color_picker": false },
+            "diagnostics": {
+                "enabled": false,
+                "contrast_level": "aaa",
+                "apca_min_lc": 60,
+                "near_duplicates": true
+            },
+            "presentations": ["rgb", "hex_alpha", "oklch"],
+            "alpha": "never"
+        }))
+        .unwrap()
+        .unwrap();
+
+        let mut options = ParseOptions::default();
+        settings.formats.apply(&mut options);
+        assert_eq!(
+            options,
+            ParseOptions {
+                named: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            settings.hover,
+            HoverSettings {
+                formats: true,
+                contrast: true,
+                background: None,
+                color_picker: false
+            }
+        );
+        assert!(!settings.diagnostics.enabled);
+        assert!(settings.diagnostics.near_duplicates);
+        assert_eq!(settings.diagnostics.near_duplicate_threshold, 2.);
+        assert_eq!(
+            settings.diagnostics.contrast_options(),
+            ContrastOptions {
+                level: WcagLevel::Aaa,
+                apca_min_lc: Some(60.)
+            }
+        );
+        assert_eq!(
+            settings.presentations,
+            [
+                PresentationFormat::Rgb,
+                PresentationFormat::HexAlpha,
+                PresentationFormat::Oklch
+            ]
+        );
+        assert_eq!(settings.alpha, AlphaMode::Never);
+
+        let nested = Settings::from_value(json!({ "color-lsp": { "alpha": "always" } }))
+            .unwrap()
+            .unwrap();
+        assert_eq!(nested.alpha, AlphaMode::Always);
+
+        assert!(Settings::from_value(json!({ "alpha": "sometimes" })).is_err());
+        assert!(Settings::from_value(json!({ "presentations": ["cmyk"] })).is_err());
+        assert!(Settings::from_value(json!({ "hover": { "background": "#1e1e1e" } })).is_ok());
+        assert!(Settings::from_value(json!({ "hover": { "background": "dark" } })).is_err());
+    }
+}
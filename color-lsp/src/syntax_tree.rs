@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Language, Parser, Query, QueryCursor};
+
+use crate::parser::ParseOptions;
+
+/// The name of the captures where colors are detected.
+const CAPTURE_NAME: &str = "color";
+
+// The default queries capture the strings, and the calls of the color constructors
+// like `rgb(0x3366ff)` or `Color::from_rgb(51, 102, 255)`.
+const RUST_QUERY: &str = r#"
+(string_literal) @color
+(raw_string_literal) @color
+(call_expression
+  function: (_) @_function
+  (#match? @_function "(?i)(rgb|hs[lv]|hwb|lab|lch|colou?r|hex)")) @color
+"#;
+
+const JAVASCRIPT_QUERY: &str = r#"
+(string) @color
+(template_string) @color
+(call_expression
+  function: (_) @_function
+  (#match? @_function "(?i)(rgb|hs[lv]|hwb|lab|lch|colou?r|hex)")) @color
+"#;
+
+const PYTHON_QUERY: &str = r#"
+(string) @color
+(call
+  function: (_) @_function
+  (#match? @_function "(?i)(rgb|hs[lv]|hwb|lab|lch|colou?r|hex)")) @color
+"#;
+
+const CSS_QUERY: &str = "(declaration) @color";
+
+const JSON_QUERY: &str = "(string) @color";
+
+/// The grammar and the default query of a language id.
+fn grammar(language_id: &str) -> Option<(Language, &'static str)> {
+    let grammar = match language_id.to_ascii_lowercase().as_str() {
+        "rust" => (tree_sitter_rust::LANGUAGE.into(), RUST_QUERY),
+        "javascript" | "javascriptreact" => {
+            (tree_sitter_javascript::LANGUAGE.into(), JAVASCRIPT_QUERY)
+        }
+        "typescript" => (
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            JAVASCRIPT_QUERY,
+        ),
+        "typescriptreact" => (
+            tree_sitter_typescript::LANGUAGE_TSX.into(),
+            JAVASCRIPT_QUERY,
+        ),
+        "python" => (tree_sitter_python::LANGUAGE.into(), PYTHON_QUERY),
+        "css" => (tree_sitter_css::LANGUAGE.into(), CSS_QUERY),
+        "json" | "jsonc" => (tree_sitter_json::LANGUAGE.into(), JSON_QUERY),
+        _ => return None,
+    };
+    Some(grammar)
+}
+
+/// Compile the default query of the language with the `extra` queries, cached by source.
+fn query(language_id: &str, extra: &[String]) -> Option<(Language, Arc<Query>)> {
+    static QUERIES: OnceLock<Mutex<HashMap<String, Arc<Query>>>> = OnceLock::new();
+
+    let (language, default_query) = grammar(language_id)?;
+    let source = std::iter::once(default_query)
+        .chain(extra.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let key = format!("{}\0{}", language_id, source);
+
+    let mut queries = QUERIES.get_or_init(Default::default).lock().ok()?;
+    if let Some(query) = queries.get(&key) {
+        return Some((language, query.clone()));
+    }
+    // The extra queries are checked by the config, keep the default one if they are invalid.
+    let query = Query::new(&language, &source)
+        .or_else(|_| Query::new(&language, default_query))
+        .ok()?;
+    let query = Arc::new(query);
+    queries.insert(key, query.clone());
+    Some((language, query))
+}
+
+/// Check that the `queries` of a language are valid, for the config.
+pub(crate) fn check_queries(language_id: &str, queries: &[String]) -> Result<(), String> {
+    let (language, _) = grammar(language_id)
+        .ok_or_else(|| format!("No Tree-sitter grammar for `{}`", language_id))?;
+    for source in queries {
+        let query = Query::new(&language, source)
+            .map_err(|err| format!("Invalid query for `{}`: {}", language_id, err))?;
+        if query.capture_index_for_name(CAPTURE_NAME).is_none() {
+            return Err(format!(
+                "The query for `{}` has no `@{}` capture: {}",
+                language_id, CAPTURE_NAME, source
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The syntax nodes where colors are detected in Tree-sitter mode, e.g. the string
+/// literals, CSS declarations and color calls, so the colors in comments or
+/// identifiers are ignored.
+pub(crate) struct Scope {
+    /// The byte offset of each line.
+    line_starts: Vec<usize>,
+    /// The byte ranges of the captured nodes, sorted and merged.
+    ranges: Vec<Range<usize>>,
+}
+
+impl Scope {
+    /// Parse the `text`, `None` if the Tree-sitter mode is off or there is no grammar for
+    /// the language, then all the text is scanned.
+    pub(crate) fn new(text: &str, options: &ParseOptions) -> Option<Self> {
+        if !options.tree_sitter {
+            return None;
+        }
+
+        let (language, query) = query(&options.language_id, &options.queries)?;
+        let mut parser = Parser::new();
+        parser.set_language(&language).ok()?;
+        let tree = parser.parse(text, None)?;
+        let capture = query.capture_index_for_name(CAPTURE_NAME)?;
+
+        let mut ranges = vec![];
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(&query, tree.root_node(), text.as_bytes());
+        while let Some(query_match) = matches.next() {
+            ranges.extend(
+                query_match
+                    .captures
+                    .iter()
+                    .filter(|c| c.index == capture)
+                    .map(|c| c.node.byte_range()),
+            );
+        }
+
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = vec![];
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(ix, _)| ix + 1))
+            .collect();
+        Some(Self {
+            line_starts,
+            ranges: merged,
+        })
+    }
+
+    /// Whether the byte `range` in the `line` is in a captured node.
+    pub(crate) fn contains(&self, line: usize, range: Range<usize>) -> bool {
+        let Some(line_start) = self.line_starts.get(line) else {
+            return false;
+        };
+        let (start, end) = (line_start + range.start, line_start + range.end);
+        let ix = self.ranges.partition_point(|node| node.end < end);
+        self.ranges
+            .get(ix)
+            .is_some_and(|node| node.start <= start && end <= node.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::check_queries;
+    use crate::parser::{parse_with_options, ParseOptions};
+
+    fn matched(text: &str, language_id: &str, queries: &[&str]) -> Vec<String> {
+        let options = ParseOptions {
+            tree_sitter: true,
+            language_id: language_id.into(),
+            queries: queries.iter().map(|query| query.to_string()).collect(),
+            ..Default::default()
+        };
+        parse_with_options(text, &options)
+            .into_iter()
+            .map(|node| node.matched)
+            .collect()
+    }
+
+    #[test]
+    fn test_scope() {
+        let text = indoc! {r##"
+            // The brand color #3366ff
+            const MASK: u32 = 0xff00ff;
+            let a = "#ff0000";
+            let b = rgb(0x00ff00);
+            let c = Color::from_hex(0x0000ff);
+        "##};
+        assert_eq!(
+            matched(text, "rust", &[]),
+            ["#ff0000", "rgb(0x00ff00)", "0x0000ff"]
+        );
+        assert_eq!(
+            matched(text, "rust", &["(const_item) @color"]),
+            ["0xff00ff", "#ff0000", "rgb(0x00ff00)", "0x0000ff"]
+        );
+        // Plain text for the languages without a grammar.
+        assert_eq!(matched(text, "go", &[]).len(), 5);
+
+        let text = indoc! {"
+            /* #3366ff */
+            a { color: red; background: #fff; }
+            #abc { color: rgb(1, 2, 3); }
+        "};
+        assert_eq!(matched(text, "css", &[]), ["red", "#fff", "rgb(1, 2, 3)"]);
+
+        let text = indoc! {r#"
+            // red: #3366ff
+            const style = { color: "red", border: `1px solid #fff` };
+            const c = hsl(120, 50%, 50%);
+        "#};
+        assert_eq!(
+            matched(text, "typescript", &[]),
+            ["red", "#fff", "hsl(120, 50%, 50%)"]
+        );
+    }
+
+    #[test]
+    fn test_check_queries() {
+        assert!(check_queries("rust", &["(macro_invocation) @color".into()]).is_ok());
+        assert!(check_queries("rust", &["(macro_invocation) @other".into()]).is_err());
+        assert!(check_queries("rust", &["(not_a_node) @color".into()]).is_err());
+        assert!(check_queries("go", &["(string) @color".into()]).is_err());
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use csscolorparser::Color;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::parser::{try_parse_color, ColorNode};
+use crate::variables::{parse_var, parse_variables, resolve_usage, Syntax};
+
+/// The utilities taking a color, the longer ones first, e.g. `border-t` before `border`.
+const UTILITY_PREFIXES: [&str; 28] = [
+    "inset-shadow",
+    "placeholder",
+    "ring-offset",
+    "drop-shadow",
+    "decoration",
+    "inset-ring",
+    "border-x",
+    "border-y",
+    "border-s",
+    "border-e",
+    "border-t",
+    "border-r",
+    "border-b",
+    "border-l",
+    "outline",
+    "border",
+    "divide",
+    "accent",
+    "shadow",
+    "stroke",
+    "caret",
+    "fill",
+    "from",
+    "ring",
+    "text",
+    "via",
+    "bg",
+    "to",
+];
+
+const SHADES: [&str; 11] = [
+    "50", "100", "200", "300", "400", "500", "600", "700", "800", "900", "950",
+];
+
+/// The default palette of Tailwind CSS v3, the shades are [`SHADES`].
+#[rustfmt::skip]
+const V3_COLORS: [(&str, [&str; 11]); 22] = [
+    ("slate", ["#f8fafc", "#f1f5f9", "#e2e8f0", "#cbd5e1", "#94a3b8", "#64748b", "#475569", "#334155", "#1e293b", "#0f172a", "#020617"]),
+    ("gray", ["#f9fafb", "#f3f4f6", "#e5e7eb", "#d1d5db", "#9ca3af", "#6b7280", "#4b5563", "#374151", "#1f2937", "#111827", "#030712"]),
+    ("zinc", ["#fafafa", "#f4f4f5", "#e4e4e7", "#d4d4d8", "#a1a1aa", "#71717a", "#52525b", "#3f3f46", "#27272a", "#18181b", "#09090b"]),
+    ("neutral", ["#fafafa", "#f5f5f5", "#e5e5e5", "#d4d4d4", "#a3a3a3", "#737373", "#525252", "#404040", "#262626", "#171717", "#0a0a0a"]),
+    ("stone", ["#fafaf9", "#f5f5f4", "#e7e5e4", "#d6d3d1", "#a8a29e", "#78716c", "#57534e", "#44403c", "#292524", "#1c1917", "#0c0a09"]),
+    ("red", ["#fef2f2", "#fee2e2", "#fecaca", "#fca5a5", "#f87171", "#ef4444", "#dc2626", "#b91c1c", "#991b1b", "#7f1d1d", "#450a0a"]),
+    ("orange", ["#fff7ed", "#ffedd5", "#fed7aa", "#fdba74", "#fb923c", "#f97316", "#ea580c", "#c2410c", "#9a3412", "#7c2d12", "#431407"]),
+    ("amber", ["#fffbeb", "#fef3c7", "#fde68a", "#fcd34d", "#fbbf24", "#f59e0b", "#d97706", "#b45309", "#92400e", "#78350f", "#451a03"]),
+    ("yellow", ["#fefce8", "#fef9c3", "#fef08a", "#fde047", "#facc15", "#eab308", "#ca8a04", "#a16207", "#854d0e", "#713f12", "#422006"]),
+    ("lime", ["#f7fee7", "#ecfccb", "#d9f99d", "#bef264", "#a3e635", "#84cc16", "#65a30d", "#4d7c0f", "#3f6212", "#365314", "#1a2e05"]),
+    ("green", ["#f0fdf4", "#dcfce7", "#bbf7d0", "#86efac", "#4ade80", "#22c55e", "#16a34a", "#15803d", "#166534", "#14532d", "#052e16"]),
+    ("emerald", ["#ecfdf5", "#d1fae5", "#a7f3d0", "#6ee7b7", "#34d399", "#10b981", "#059669", "#047857", "#065f46", "#064e3b", "#022c22"]),
+    ("teal", ["#f0fdfa", "#ccfbf1", "#99f6e4", "#5eead4", "#2dd4bf", "#14b8a6", "#0d9488", "#0f766e", "#115e59", "#134e4a", "#042f2e"]),
+    ("cyan", ["#ecfeff", "#cffafe", "#a5f3fc", "#67e8f9", "#22d3ee", "#06b6d4", "#0891b2", "#0e7490", "#155e75", "#164e63", "#083344"]),
+    ("sky", ["#f0f9ff", "#e0f2fe", "#bae6fd", "#7dd3fc", "#38bdf8", "#0ea5e9", "#0284c7", "#0369a1", "#075985", "#0c4a6e", "#082f49"]),
+    ("blue", ["#eff6ff", "#dbeafe", "#bfdbfe", "#93c5fd", "#60a5fa", "#3b82f6", "#2563eb", "#1d4ed8", "#1e40af", "#1e3a8a", "#172554"]),
+    ("indigo", ["#eef2ff", "#e0e7ff", "#c7d2fe", "#a5b4fc", "#818cf8", "#6366f1", "#4f46e5", "#4338ca", "#3730a3", "#312e81", "#1e1b4b"]),
+    ("violet", ["#f5f3ff", "#ede9fe", "#ddd6fe", "#c4b5fd", "#a78bfa", "#8b5cf6", "#7c3aed", "#6d28d9", "#5b21b6", "#4c1d95", "#2e1065"]),
+    ("purple", ["#faf5ff", "#f3e8ff", "#e9d5ff", "#d8b4fe", "#c084fc", "#a855f7", "#9333ea", "#7e22ce", "#6b21a8", "#581c87", "#3b0764"]),
+    ("fuchsia", ["#fdf4ff", "#fae8ff", "#f5d0fe", "#f0abfc", "#e879f9", "#d946ef", "#c026d3", "#a21caf", "#86198f", "#701a75", "#4a044e"]),
+    ("pink", ["#fdf2f8", "#fce7f3", "#fbcfe8", "#f9a8d4", "#f472b6", "#ec4899", "#db2777", "#be185d", "#9d174d", "#831843", "#500724"]),
+    ("rose", ["#fff1f2", "#ffe4e6", "#fecdd3", "#fda4af", "#fb7185", "#f43f5e", "#e11d48", "#be123c", "#9f1239", "#881337", "#4c0519"]),
+];
+
+/// The default palette of Tailwind CSS v4, the `oklch()` values of the shades in [`SHADES`].
+#[rustfmt::skip]
+const V4_COLORS: [(&str, [&str; 11]); 22] = [
+    ("red", ["97.1% 0.013 17.38", "93.6% 0.032 17.717", "88.5% 0.062 18.334", "80.8% 0.114 19.571", "70.4% 0.191 22.216", "63.7% 0.237 25.331", "57.7% 0.245 27.325", "50.5% 0.213 27.518", "44.4% 0.177 26.899", "39.6% 0.141 25.723", "25.8% 0.092 26.042"]),
+    ("orange", ["98% 0.016 73.684", "95.4% 0.038 75.164", "90.1% 0.076 70.697", "83.7% 0.128 66.29", "75% 0.183 55.934", "70.5% 0.213 47.604", "64.6% 0.222 41.116", "55.3% 0.195 38.402", "47% 0.157 37.304", "40.8% 0.123 38.172", "26.6% 0.079 36.259"]),
+    ("amber", ["98.7% 0.022 95.277", "96.2% 0.059 95.617", "92.4% 0.12 95.746", "87.9% 0.169 91.605", "82.8% 0.189 84.429", "76.9% 0.188 70.08", "66.6% 0.179 58.318", "55.5% 0.163 48.998", "47.3% 0.137 46.201", "41.4% 0.112 45.904", "27.9% 0.077 45.635"]),
+    ("yellow", ["98.7% 0.026 102.212", "97.3% 0.071 103.193", "94.5% 0.129 101.54", "90.5% 0.182 98.111", "85.2% 0.199 91.936", "79.5% 0.184 86.047", "68.1% 0.162 75.834", "55.4% 0.135 66.442", "47.6% 0.114 61.907", "42.1% 0.095 57.708", "28.6% 0.066 53.813"]),
+    ("lime", ["98.6% 0.031 120.757", "96.7% 0.067 122.328", "93.8% 0.127 124.321", "89.7% 0.196 126.665", "84.1% 0.238 128.85", "76.8% 0.233 130.85", "64.8% 0.2 131.684", "53.2% 0.157 131.589", "45.3% 0.124 130.933", "40.5% 0.101 131.063", "27.4% 0.072 132.109"]),
+    ("green", ["98.2% 0.018 155.826", "96.2% 0.044 156.743", "92.5% 0.084 155.995", "87.1% 0.15 154.449", "79.2% 0.209 151.711", "72.3% 0.219 149.579", "62.7% 0.194 149.214", "52.7% 0.154 150.069", "44.8% 0.119 151.328", "39.3% 0.095 152.535", "26.6% 0.065 152.934"]),
+    ("emerald", ["97.9% 0.021 166.113", "95% 0.052 163.051", "90.5% 0.093 164.15", "84.5% 0.143 164.978", "76.5% 0.177 163.223", "69.6% 0.17 162.48", "59.6% 0.145 163.225", "50.8% 0.118 165.612", "43.2% 0.095 166.913", "37.8% 0.077 168.94", "26.2% 0.051 172.552"]),
+    ("teal", ["98.4% 0.014 180.72", "95.3% 0.051 180.801", "91% 0.096 180.426", "85.5% 0.138 181.071", "77.7% 0.152 181.912", "70.4% 0.14 182.503", "60% 0.118 184.704", "51.1% 0.096 186.391", "43.7% 0.078 188.216", "38.6% 0.063 188.416", "27.7% 0.046 192.524"]),
+    ("cyan", ["98.4% 0.019 200.873", "95.6% 0.045 203.388", "91.7% 0.08 205.041", "86.5% 0.127 207.078", "78.9% 0.154 211.53", "71.5% 0.143 215.221", "60.9% 0.126 221.723", "52% 0.105 223.128", "45% 0.085 224.283", "39.8% 0.07 227.392", "30.2% 0.056 229.695"]),
+    ("sky", ["97.7% 0.013 236.62", "95.1% 0.026 236.824", "90.1% 0.058 230.902", "82.8% 0.111 230.318", "74.6% 0.16 232.661", "68.5% 0.169 237.323", "58.8% 0.158 241.966", "50% 0.134 242.749", "44.3% 0.11 240.79", "39.1% 0.09 240.876", "29.3% 0.066 243.157"]),
+    ("blue", ["97% 0.014 254.604", "93.2% 0.032 255.585", "88.2% 0.059 254.128", "80.9% 0.105 251.813", "70.7% 0.165 254.624", "62.3% 0.214 259.815", "54.6% 0.245 262.881", "48.8% 0.243 264.376", "42.4% 0.199 265.638", "37.9% 0.146 265.522", "28.2% 0.091 267.935"]),
+    ("indigo", ["96.2% 0.018 272.314", "93% 0.034 272.788", "87% 0.065 274.039", "78.5% 0.115 274.713", "67.3% 0.182 276.935", "58.5% 0.233 277.117", "51.1% 0.262 276.966", "45.7% 0.24 277.023", "39.8% 0.195 277.366", "35.9% 0.144 278.697", "25.7% 0.09 281.288"]),
+    ("violet", ["96.9% 0.016 293.756", "94.3% 0.029 294.588", "89.4% 0.057 293.283", "81.1% 0.111 293.571", "70.2% 0.183 293.541", "60.6% 0.25 292.717", "54.1% 0.281 293.009", "49.1% 0.27 292.581", "43.2% 0.232 292.759", "38% 0.189 293.745", "28.3% 0.141 291.089"]),
+    ("purple", ["97.7% 0.014 308.299", "94.6% 0.033 307.174", "90.2% 0.063 306.703", "82.7% 0.119 306.383", "71.4% 0.203 305.504", "62.7% 0.265 303.9", "55.8% 0.288 302.321", "49.6% 0.265 301.924", "43.8% 0.218 303.724", "38.1% 0.176 304.987", "29.1% 0.149 302.717"]),
+    ("fuchsia", ["97.7% 0.017 320.058", "95.2% 0.037 318.852", "90.3% 0.076 319.62", "83.3% 0.145 321.434", "74% 0.238 322.16", "66.7% 0.295 322.15", "59.1% 0.293 322.896", "51.8% 0.253 323.949", "45.2% 0.211 324.591", "40.1% 0.17 325.612", "29.3% 0.136 325.661"]),
+    ("pink", ["97.1% 0.014 343.198", "94.8% 0.028 342.258", "89.9% 0.061 343.231", "82.3% 0.12 346.018", "71.8% 0.202 349.761", "65.6% 0.241 354.308", "59.2% 0.249 0.584", "52.5% 0.223 3.958", "45.9% 0.187 3.815", "40.8% 0.153 2.432", "28.4% 0.109 3.907"]),
+    ("rose", ["96.9% 0.015 12.422", "94.1% 0.03 12.58", "89.2% 0.058 10.001", "81% 0.117 11.638", "71.2% 0.194 13.428", "64.5% 0.246 16.439", "58.6% 0.253 17.585", "51.4% 0.222 16.935", "45.5% 0.188 13.697", "41% 0.159 10.272", "27.1% 0.105 12.094"]),
+    ("slate", ["98.4% 0.003 247.858", "96.8% 0.007 247.896", "92.9% 0.013 255.508", "86.9% 0.022 252.894", "70.4% 0.04 256.788", "55.4% 0.046 257.417", "44.6% 0.043 257.281", "37.2% 0.044 257.287", "27.9% 0.041 260.031", "20.8% 0.042 265.755", "12.9% 0.042 264.695"]),
+    ("gray", ["98.5% 0.002 247.839", "96.7% 0.003 264.542", "92.8% 0.006 264.531", "87.2% 0.01 258.338", "70.7% 0.022 261.325", "55.1% 0.027 264.364", "44.6% 0.03 256.802", "37.3% 0.034 259.733", "27.8% 0.033 256.848", "21% 0.034 264.665", "13% 0.028 261.692"]),
+    ("zinc", ["98.5% 0 0", "96.7% 0.001 286.375", "92% 0.004 286.32", "87.1% 0.006 286.286", "70.5% 0.015 286.067", "55.2% 0.016 285.938", "44.2% 0.017 285.786", "37% 0.013 285.805", "27.4% 0.006 286.033", "21% 0.006 285.885", "14.1% 0.005 285.823"]),
+    ("neutral", ["98.5% 0 0", "97% 0 0", "92.2% 0 0", "87% 0 0", "70.8% 0 0", "55.6% 0 0", "43.9% 0 0", "37.1% 0 0", "26.9% 0 0", "20.5% 0 0", "14.5% 0 0"]),
+    ("stone", ["98.5% 0.001 106.423", "97% 0.001 106.424", "92.3% 0.003 48.717", "86.9% 0.005 56.366", "70.9% 0.01 56.259", "55.3% 0.013 58.071", "44.4% 0.011 73.639", "37.4% 0.01 67.558", "26.8% 0.007 34.298", "21.6% 0.006 56.043", "14.7% 0.004 49.25"]),
+];
+
+/// The Tailwind CSS settings in the project config.
+///
+/// ```toml
+/// [tailwind]
+/// # The version of the default palette, 3 or 4.
+/// version = 4
+/// # The custom colors, from a JSON export of `tailwind.config` or a CSS file with a v4 `@theme` block.
+/// theme = "src/app.css"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct TailwindConfig {
+    pub(crate) version: Option<u8>,
+    pub(crate) theme: Option<PathBuf>,
+}
+
+/// The version of the Tailwind CSS default palette.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TailwindVersion {
+    V3,
+    #[default]
+    V4,
+}
+
+/// The Tailwind CSS colors, the default palette and the custom colors of the project.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TailwindTheme {
+    version: TailwindVersion,
+    /// The custom colors by name, e.g. `brand-500`, in any CSS notation.
+    colors: BTreeMap<String, String>,
+    /// Whether the custom colors replace the default palette, e.g. `--color-*: initial`.
+    replace_defaults: bool,
+}
+
+impl TailwindTheme {
+    /// Load the custom colors of the config, the theme file is relative to the `work_dir`.
+    ///
+    /// The version defaults to 3 for a JSON theme, and 4 otherwise.
+    pub(crate) fn load(config: &TailwindConfig, work_dir: &Path) -> Result<Self, String> {
+        let is_json = config
+            .theme
+            .as_ref()
+            .is_some_and(|path| path.extension().is_some_and(|ext| ext == "json"));
+        let version = match config.version {
+            Some(3) => TailwindVersion::V3,
+            Some(4) => TailwindVersion::V4,
+            Some(version) => return Err(format!("Unsupported Tailwind CSS version {}", version)),
+            None if is_json => TailwindVersion::V3,
+            None => TailwindVersion::V4,
+        };
+
+        let Some(theme) = config.theme.as_ref() else {
+            return Ok(Self {
+                version,
+                ..Default::default()
+            });
+        };
+        let path = work_dir.join(theme);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read the theme {}: {}", path.display(), err))?;
+        let mut theme = if is_json {
+            Self::from_json(&text)
+                .map_err(|err| format!("Invalid theme {}: {}", path.display(), err))?
+        } else {
+            Self::from_css(&text)
+        };
+        theme.version = version;
+        Ok(theme)
+    }
+
+    /// The colors of a JSON export of `tailwind.config`, `theme.colors` replace the
+    /// default palette and `theme.extend.colors` extend it.
+    fn from_json(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let theme = value.get("theme").unwrap_or(&value);
+
+        let mut colors = BTreeMap::new();
+        let replace_defaults = match theme.get("colors") {
+            Some(value) => {
+                flatten_colors("", value, &mut colors);
+                true
+            }
+            None => false,
+        };
+        if let Some(value) = theme.get("extend").and_then(|extend| extend.get("colors")) {
+            flatten_colors("", value, &mut colors);
+        }
+
+        Ok(Self {
+            colors,
+            replace_defaults,
+            ..Default::default()
+        })
+    }
+
+    /// The `--color-*` variables of the `@theme` blocks of a v4 stylesheet.
+    fn from_css(text: &str) -> Self {
+        let mut colors = BTreeMap::new();
+        let mut replace_defaults = false;
+        for block in theme_blocks(text) {
+            replace_defaults |= block
+                .split(';')
+                .any(|decl| decl.replace(char::is_whitespace, "") == "--color-*:initial");
+            for decl in parse_variables(block, Syntax::Css).declarations {
+                if let Some(name) = decl.name.strip_prefix("--color-") {
+                    colors.insert(name.to_string(), decl.value);
+                }
+            }
+        }
+
+        // Resolve the `var()` of the other theme colors, once the theme is known.
+        let mut theme = Self {
+            colors,
+            replace_defaults,
+            ..Default::default()
+        };
+        let resolved = theme
+            .colors
+            .iter()
+            .filter_map(|(name, value)| {
+                let usage = parse_var(value)?;
+                let color =
+                    resolve_usage(&usage, &|name| theme.value(name.strip_prefix("--color-")?))?;
+                Some((name.clone(), color.to_css_hex()))
+            })
+            .collect::<Vec<_>>();
+        theme.colors.extend(resolved);
+        theme
+    }
+
+    /// The CSS value of a theme color by name, e.g. `blue-500`.
+    fn value(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.colors.get(name) {
+            return Some(value.clone());
+        }
+        if self.replace_defaults {
+            return None;
+        }
+
+        match name {
+            "black" => return Some("#000".into()),
+            "white" => return Some("#fff".into()),
+            "transparent" => return Some("transparent".into()),
+            _ => {}
+        }
+        let (hue, shade) = name.rsplit_once('-')?;
+        let shade = SHADES.iter().position(|s| *s == shade)?;
+        match self.version {
+            TailwindVersion::V3 => V3_COLORS
+                .iter()
+                .find(|(name, _)| *name == hue)
+                .map(|(_, shades)| shades[shade].to_string()),
+            TailwindVersion::V4 => V4_COLORS
+                .iter()
+                .find(|(name, _)| *name == hue)
+                .map(|(_, shades)| format!("oklch({})", shades[shade])),
+        }
+    }
+
+    /// The color of a theme color by name, e.g. `blue-500`.
+    pub(crate) fn color(&self, name: &str) -> Option<Color> {
+        try_parse_color(&self.value(name)?).ok()
+    }
+}
+
+/// Flatten the nested colors of a Tailwind config, `{ "brand": { "500": "#..." } }` is
+/// `brand-500`, and `DEFAULT` is the parent name.
+fn flatten_colors(prefix: &str, value: &Value, colors: &mut BTreeMap<String, String>) {
+    match value {
+        Value::String(value) => {
+            colors.insert(prefix.to_string(), value.clone());
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = match (prefix, key.as_str()) {
+                    (_, "DEFAULT") => prefix.to_string(),
+                    ("", key) => key.to_string(),
+                    (prefix, key) => format!("{}-{}", prefix, key),
+                };
+                flatten_colors(&name, value, colors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The contents of the `@theme { ... }` blocks of a stylesheet.
+fn theme_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("@theme") {
+        rest = &rest[start..];
+        let Some(open) = rest.find('{') else {
+            break;
+        };
+        let mut depth = 0;
+        let close = rest[open..].char_indices().find_map(|(ix, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(open + ix)
+        });
+        let close = close.unwrap_or(rest.len());
+        blocks.push(&rest[open + 1..close]);
+        rest = &rest[close..];
+    }
+    blocks
+}
+
+/// The utility prefix of a Tailwind CSS color class, e.g. `bg` of `bg-blue-500/50`.
+fn utility_prefixes(class: &str) -> impl Iterator<Item = &'static str> + '_ {
+    UTILITY_PREFIXES.iter().copied().filter(|prefix| {
+        class
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('-'))
+    })
+}
+
+/// Whether a Tailwind CSS class may start after this byte, e.g. after a variant `hover:`.
+fn is_class_start(prev: Option<u8>) -> bool {
+    prev.is_none_or(|b| {
+        b.is_ascii_whitespace()
+            || matches!(
+                b,
+                b'"' | b'\'' | b'`' | b':' | b'!' | b'(' | b',' | b'{' | b'.'
+            )
+    })
+}
+
+/// Whether a Tailwind CSS class may end before this byte.
+fn is_class_end(next: Option<u8>) -> bool {
+    next.is_none_or(|b| {
+        b.is_ascii_whitespace()
+            || matches!(b, b'"' | b'\'' | b'`' | b',' | b')' | b';' | b'}' | b']')
+    })
+}
+
+/// Match a Tailwind CSS color utility at `start`, e.g. `bg-blue-500`, `text-slate-900/80`
+/// or `bg-[#ff0]`.
+pub(crate) fn match_tailwind(
+    line_text: &str,
+    line_ix: usize,
+    start: usize,
+    theme: &TailwindTheme,
+) -> Option<ColorNode> {
+    let bytes = line_text.as_bytes();
+    if !is_class_start(start.checked_sub(1).map(|ix| bytes[ix])) {
+        return None;
+    }
+
+    let class = &line_text[start..];
+    utility_prefixes(class).find_map(|prefix| {
+        let (mut color, mut end) = match_value(class, prefix.len() + 1, theme)?;
+        if class.as_bytes().get(end) == Some(&b'/') {
+            let (opacity, len) = match_opacity(&class[end + 1..])?;
+            color.a *= opacity;
+            end += 1 + len;
+        }
+        // The v4 important modifier, e.g. `bg-red-500!`
+        if class.as_bytes().get(end) == Some(&b'!') {
+            end += 1;
+        }
+        if !is_class_end(class.as_bytes().get(end).copied()) {
+            return None;
+        }
+
+        Some(ColorNode::new(&class[..end], color, line_ix, start))
+    })
+}
+
+/// Match the color of a utility at `start`, a theme color or an arbitrary value, with its end.
+fn match_value(class: &str, start: usize, theme: &TailwindTheme) -> Option<(Color, usize)> {
+    let rest = &class[start..];
+    if let Some(value) = rest.strip_prefix('[') {
+        let close = value.find(']')?;
+        // The type hint of an ambiguous value, e.g. `bg-[color:var(--brand)]`
+        let value = &value[..close];
+        let value = value.strip_prefix("color:").unwrap_or(value);
+        let color = try_parse_color(&value.replace('_', " ")).ok()?;
+        return Some((color, start + close + 2));
+    }
+
+    let len = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_')
+        .count();
+    Some((theme.color(&rest[..len])?, start + len))
+}
+
+/// Match an opacity modifier after the `/`, e.g. `50`, `[.35]` or `[35%]`, with its length.
+fn match_opacity(text: &str) -> Option<(f32, usize)> {
+    if let Some(value) = text.strip_prefix('[') {
+        let close = value.find(']')?;
+        let value = &value[..close];
+        let opacity = match value.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.,
+            None => value.parse::<f32>().ok()?,
+        };
+        return (0.0..=1.0)
+            .contains(&opacity)
+            .then_some((opacity, close + 2));
+    }
+
+    let len = text.bytes().take_while(u8::is_ascii_digit).count();
+    let percent = text[..len].parse::<u8>().ok().filter(|p| *p <= 100)?;
+    Some((percent as f32 / 100., len))
+}
+
+/// Whether the class is a Tailwind CSS color utility, matched by [`match_tailwind`].
+pub(crate) fn is_color_utility(class: &str) -> bool {
+    utility_prefixes(class).next().is_some()
+}
+
+/// Write a color as an arbitrary value of the same utility, e.g. `bg-[#3b82f6]` for
+/// `bg-blue-500`, or `None` if `class` is not a color utility.
+pub(crate) fn rewrite_utility(class: &str, value: &str) -> Option<String> {
+    let prefix = utility_prefixes(class).next()?;
+    let important = if class.ends_with('!') { "!" } else { "" };
+    Some(format!(
+        "{}-[{}]{}",
+        prefix,
+        value.replace(' ', "_"),
+        important
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::{match_tailwind, rewrite_utility, TailwindTheme, TailwindVersion};
+
+    fn matched(line: &str, theme: &TailwindTheme) -> Vec<(String, String)> {
+        (0..line.len())
+            .filter(|ix| line.is_char_boundary(*ix))
+            .filter_map(|ix| match_tailwind(line, 0, ix, theme))
+            .map(|node| (node.matched, node.color.to_css_hex()))
+            .collect()
+    }
+
+    #[test]
+    fn test_match_tailwind() {
+        let theme = TailwindTheme::default();
+        assert_eq!(
+            matched(
+                r#"<div class="bg-blue-500 hover:text-slate-900/80 border-t-red-500 to-white p-4">"#,
+                &theme
+            ),
+            [
+                ("bg-blue-500".to_string(), "#2b7fff".to_string()),
+                ("text-slate-900/80".into(), "#0f172bcc".into()),
+                ("border-t-red-500".into(), "#fb2c36".into()),
+                ("to-white".into(), "#ffffff".into()),
+            ]
+        );
+        assert_eq!(
+            matched(
+                "bg-[#ff0] text-[rgb(255_0_0)]/50 fill-[color:#00f] !ring-black/[.25] bg-red-500!",
+                &theme
+            ),
+            [
+                ("bg-[#ff0]".to_string(), "#ffff00".to_string()),
+                ("text-[rgb(255_0_0)]/50".into(), "#ff000080".into()),
+                ("fill-[color:#00f]".into(), "#0000ff".into()),
+                ("ring-black/[.25]".into(), "#00000040".into()),
+                ("bg-red-500!".into(), "#fb2c36".into()),
+            ]
+        );
+        // Not colors, or not whole classes.
+        assert!(matched(
+            "text-lg bg-red bg-red-550 stroke-2 bg-[url(a.png)] bg-blue-500x my-bg-red-500 bg-red-500/200",
+            &theme
+        )
+        .is_empty());
+
+        let theme = TailwindTheme {
+            version: TailwindVersion::V3,
+            ..Default::default()
+        };
+        assert_eq!(
+            matched("bg-blue-500", &theme),
+            [("bg-blue-500".to_string(), "#3b82f6".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let theme = TailwindTheme::from_json(indoc! {r##"
+            {
+              "theme": {
+                "extend": {
+                  "colors": {
+                    "brand": { "DEFAULT": "#3366ff", "500": "#3366ff", "900": "#001a66" },
+                    "accent": "rgb(255 0 128)"
+                  }
+                }
+              }
+            }
+        "##})
+        .unwrap();
+        assert_eq!(theme.color("brand").unwrap().to_css_hex(), "#3366ff");
+        assert_eq!(theme.color("brand-900").unwrap().to_css_hex(), "#001a66");
+        assert_eq!(theme.color("accent").unwrap().to_css_hex(), "#ff0080");
+        assert!(theme.color("red-500").is_some());
+
+        let theme =
+            TailwindTheme::from_json(r##"{ "theme": { "colors": { "primary": "#f00" } } }"##)
+                .unwrap();
+        assert!(theme.color("primary").is_some());
+        assert!(theme.color("red-500").is_none());
+        assert!(TailwindTheme::from_json("{").is_err());
+    }
+
+    #[test]
+    fn test_from_css() {
+        let theme = TailwindTheme::from_css(indoc! {"
+            :root { --color-ignored: #000; }
+            @theme {
+              --font-sans: Inter;
+              --color-brand-500: #3366ff;
+              --color-primary: var(--color-brand-500);
+              --color-danger: var(--color-red-500);
+            }
+        "});
+        assert_eq!(theme.color("brand-500").unwrap().to_css_hex(), "#3366ff");
+        assert_eq!(theme.color("primary").unwrap().to_css_hex(), "#3366ff");
+        assert_eq!(theme.color("danger").unwrap().to_css_hex(), "#fb2c36");
+        assert!(theme.color("ignored").is_none());
+        assert!(theme.color("sans").is_none());
+
+        let theme = TailwindTheme::from_css("@theme { --color-*: initial; --color-ink: #111; }");
+        assert!(theme.color("ink").is_some());
+        assert!(theme.color("white").is_none());
+    }
+
+    #[test]
+    fn test_rewrite_utility() {
+        assert_eq!(
+            rewrite_utility("bg-blue-500/50", "#3b82f680"),
+            Some("bg-[#3b82f680]".into())
+        );
+        assert_eq!(
+            rewrite_utility("border-t-red-500!", "rgb(1 2 3)"),
+            Some("border-t-[rgb(1_2_3)]!".into())
+        );
+        assert_eq!(rewrite_utility("#ff0000", "#fff"), None);
+    }
+}
@@ -1,6 +1,9 @@
 use tower_lsp::lsp_types::Color;
 
-/// Convert lsp_types::Color to markdown to list other color formats (HSLA, HEX, RGBA)
+use crate::contrast::{apca_contrast, blend, contrast_ratio, WcagLevel};
+use crate::settings::HoverSettings;
+
+/// Convert lsp_types::Color to markdown to list other color formats (HSLA, HEX, RGBA) and the contrast
 /// e.g.
 ///
 /// Colorspace Formats:
@@ -11,8 +14,9 @@ use tower_lsp::lsp_types::Color;
 /// - hsla(0.143, 1., 0.467, 1.)
 /// - rgba(238, 204, 0, 100%)
 /// - rgba(0.933, 0.8, 0., 1.)
-#[allow(unused)]
-pub(crate) fn color_summary(color: Color) -> String {
+///
+/// Returns `None` if all the sections are disabled by `settings`.
+pub(crate) fn color_summary(color: Color, settings: &HoverSettings) -> Option<String> {
     let r = (color.red * 255.0).round() as u8;
     let g = (color.green * 255.0).round() as u8;
     let b = (color.blue * 255.0).round() as u8;
@@ -56,12 +60,84 @@ pub(crate) fn color_summary(color: Color) -> String {
     //     &hex[1..]
     // );
 
-    let color_link = format!("\n[Color Picker](https://colorpicker.dev/{})", &hex);
+    let mut sections = vec![];
+    if settings.formats {
+        sections.push(format!(
+            "Colorspace Formats:\n\n```\n{}\n```\n",
+            [
+                hex.as_str(),
+                &hex_alpha,
+                &hsla_percent,
+                &hsla_float,
+                &rgba,
+                &rgba_float
+            ]
+            .join("\n"),
+        ));
+    }
+    if settings.contrast {
+        let color = csscolorparser::Color::new(color.red, color.green, color.blue, color.alpha);
+        let background = settings
+            .background
+            .as_deref()
+            .and_then(|background| csscolorparser::parse(background).ok());
+        sections.push(contrast_table(&color, background.as_ref()));
+    }
+    if settings.color_picker {
+        sections.push(format!("[Color Picker](https://colorpicker.dev/{})", &hex));
+    }
+
+    if sections.is_empty() {
+        return None;
+    }
+    Some(sections.join("\n"))
+}
+
+/// The WCAG 2.1 contrast ratios and APCA `Lc` of the color (as text) on white, black
+/// and the theme `background`, e.g.
+///
+/// | Background | Ratio | AA | AAA | APCA Lc |
+/// |---|---|---|---|---|
+/// | White | 4.49:1 | ✗ | ✗ | 71.2 |
+fn contrast_table(
+    color: &csscolorparser::Color,
+    background: Option<&csscolorparser::Color>,
+) -> String {
+    let white = csscolorparser::Color::new(1., 1., 1., 1.);
+    let black = csscolorparser::Color::new(0., 0., 0., 1.);
+    let mut backgrounds = vec![("White".to_string(), white), ("Black".to_string(), black)];
+    if let Some(background) = background {
+        // A translucent theme background is shown on white.
+        let background = blend(background, &backgrounds[0].1);
+        backgrounds.push((format!("Theme `{}`", background.to_css_hex()), background));
+    }
+
+    let badge = |ratio: f32, level: WcagLevel| {
+        if ratio >= level.min_ratio() {
+            "✓"
+        } else {
+            "✗"
+        }
+    };
+    let rows = backgrounds
+        .iter()
+        .map(|(name, background)| {
+            let color = blend(color, background);
+            let ratio = contrast_ratio(&color, background);
+            format!(
+                "| {} | {:.2}:1 | {} | {} | {:.1} |",
+                name,
+                ratio,
+                badge(ratio, WcagLevel::Aa),
+                badge(ratio, WcagLevel::Aaa),
+                apca_contrast(&color, background)
+            )
+        })
+        .collect::<Vec<_>>();
 
     format!(
-        "Colorspace Formats:\n\n```\n{}\n```\n{}",
-        vec![hex, hex_alpha, hsla_percent, hsla_float, rgba, rgba_float].join("\n"),
-        color_link
+        "Contrast:\n\n| Background | Ratio | AA | AAA | APCA Lc |\n|---|---|---|---|---|\n{}\n",
+        rows.join("\n")
     )
 }
 
@@ -113,6 +189,8 @@ mod tests {
     use indoc::indoc;
     use tower_lsp::lsp_types::Color;
 
+    use crate::settings::HoverSettings;
+
     #[test]
     fn test_color_summary() {
         let color = Color {
@@ -122,10 +200,16 @@ mod tests {
             alpha: 1.0,
         };
 
-        let summary = super::color_summary(color);
+        let summary = super::color_summary(
+            color,
+            &HoverSettings {
+                contrast: false,
+                ..Default::default()
+            },
+        );
         assert_eq!(
-            summary,
-            indoc! {r#"
+            summary.as_deref(),
+            Some(indoc! {r#"
                 Colorspace Formats:
 
                 ```
@@ -137,7 +221,64 @@ mod tests {
                 rgba(0.933, 0.8, 0., 1.)
                 ```
 
-                [Color Picker](https://colorpicker.dev/#EECC00)"#}
+                [Color Picker](https://colorpicker.dev/#EECC00)"#})
+        );
+
+        let summary = super::color_summary(
+            color,
+            &HoverSettings {
+                formats: false,
+                contrast: false,
+                color_picker: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            summary.as_deref(),
+            Some("[Color Picker](https://colorpicker.dev/#EECC00)")
+        );
+
+        let summary = super::color_summary(
+            color,
+            &HoverSettings {
+                formats: false,
+                contrast: false,
+                color_picker: false,
+                ..Default::default()
+            },
+        );
+        assert_eq!(summary, None);
+    }
+
+    #[test]
+    fn test_color_summary_contrast() {
+        let color = Color {
+            red: 0.466,
+            green: 0.466,
+            blue: 0.466,
+            alpha: 1.0,
+        };
+
+        let summary = super::color_summary(
+            color,
+            &HoverSettings {
+                formats: false,
+                contrast: true,
+                background: Some("#1e1e1e".into()),
+                color_picker: false,
+            },
+        );
+        assert_eq!(
+            summary.as_deref(),
+            Some(indoc! {r#"
+                Contrast:
+
+                | Background | Ratio | AA | AAA | APCA Lc |
+                |---|---|---|---|---|
+                | White | 4.49:1 | ✗ | ✗ | 71.2 |
+                | Black | 4.68:1 | ✓ | ✗ | -30.5 |
+                | Theme `#1e1e1e` | 3.71:1 | ✗ | ✗ | -28.6 |
+            "#})
         );
     }
 
@@ -0,0 +1,794 @@
+use std::ops::Range;
+use std::path::Path;
+
+use csscolorparser::Color;
+
+use crate::document::LineEdit;
+use crate::parser::try_parse_color;
+
+/// The max depth of `var()` chains to resolve, to stop at cycles like `--a: var(--a)`.
+pub(crate) const MAX_DEPTH: usize = 8;
+
+/// The syntax of the variables in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Syntax {
+    /// `--name: value` and `var(--name)`
+    Css,
+    /// Sass `$name: value`, besides the CSS custom properties.
+    Sass,
+    /// Less `@name: value`, besides the CSS custom properties.
+    Less,
+    /// Design tokens in JSON, e.g. `{ "color": { "brand": { "$value": "#3366ff" } } }`,
+    /// referenced as `{color.brand}`.
+    Tokens,
+}
+
+impl Syntax {
+    /// The syntax of the files to index in the workspace, `None` for the other files.
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with("tokens.json") {
+            return Some(Self::Tokens);
+        }
+        match name.rsplit_once('.')?.1 {
+            "css" | "pcss" | "postcss" => Some(Self::Css),
+            "scss" | "sass" => Some(Self::Sass),
+            "less" => Some(Self::Less),
+            _ => None,
+        }
+    }
+
+    /// The syntax of an opened document, the custom properties are used in any language.
+    pub(crate) fn detect(language_id: &str, path: Option<&Path>) -> Self {
+        if let Some(syntax) = path.and_then(Self::from_path) {
+            return syntax;
+        }
+        match language_id {
+            "scss" | "sass" => Self::Sass,
+            "less" => Self::Less,
+            _ => Self::Css,
+        }
+    }
+
+    /// The prefixes of the variable names.
+    fn sigils(&self) -> &'static [&'static str] {
+        match self {
+            Self::Css => &["--"],
+            Self::Sass => &["--", "$"],
+            Self::Less => &["--", "@"],
+            Self::Tokens => &[],
+        }
+    }
+}
+
+/// A variable declaration, e.g. `--brand: #3366ff;`, `$brand: #3366ff;` or a design token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Declaration {
+    /// The name, including the `--`, `$` or `@`, or the dotted path of a design token.
+    pub(crate) name: String,
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range of the name in the line.
+    pub(crate) name_range: Range<usize>,
+    /// 0-based line number of the value, a design token `$value` may be on another line.
+    pub(crate) value_line: usize,
+    /// The byte range of the value in its line, without `!important` or `!default`.
+    pub(crate) value_range: Range<usize>,
+    pub(crate) value: String,
+}
+
+/// A variable used by name, e.g. `--brand` in `var(--brand)` or `color.brand` in `{color.brand}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reference {
+    pub(crate) name: String,
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range of the name in the line.
+    pub(crate) range: Range<usize>,
+}
+
+/// A `var(--name, fallback)` usage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Usage {
+    pub(crate) name: String,
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range of the whole `var(...)` in the line.
+    pub(crate) range: Range<usize>,
+    /// The byte range of the name in the line.
+    pub(crate) name_range: Range<usize>,
+    /// The byte range and text of the fallback value.
+    pub(crate) fallback: Option<(Range<usize>, String)>,
+}
+
+/// The variables declared and referenced in a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Variables {
+    pub(crate) declarations: Vec<Declaration>,
+    pub(crate) references: Vec<Reference>,
+}
+
+impl Variables {
+    /// The variable name at the byte `offset` of the `line`, declared or referenced.
+    pub(crate) fn name_at(&self, line: usize, offset: usize) -> Option<&str> {
+        let declarations = self
+            .declarations
+            .iter()
+            .map(|decl| (decl.line, &decl.name_range, &decl.name));
+        let references = self
+            .references
+            .iter()
+            .map(|reference| (reference.line, &reference.range, &reference.name));
+        declarations
+            .chain(references)
+            .find(|(l, range, _)| *l == line && range.start <= offset && offset <= range.end)
+            .map(|(_, _, name)| name.as_str())
+    }
+
+    /// Replace the variables on the lines touched by `edit` with the `rescanned` ones of those
+    /// lines, whose line numbers start at 0, and shift the variables after the edit.
+    pub(crate) fn splice(&mut self, edit: &LineEdit, rescanned: Variables) {
+        splice(&mut self.declarations, edit, rescanned.declarations);
+        splice(&mut self.references, edit, rescanned.references);
+    }
+}
+
+/// Replace the usages on the lines touched by `edit`, see [`Variables::splice`].
+pub(crate) fn splice_usages(usages: &mut Vec<Usage>, edit: &LineEdit, rescanned: Vec<Usage>) {
+    splice(usages, edit, rescanned);
+}
+
+/// An item found on a line, to rescan the lines touched by an edit.
+trait Lined {
+    fn line(&self) -> usize;
+    fn shift(&mut self, delta: i64);
+}
+
+impl Lined for Declaration {
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn shift(&mut self, delta: i64) {
+        self.line = (self.line as i64 + delta) as usize;
+        self.value_line = (self.value_line as i64 + delta) as usize;
+    }
+}
+
+impl Lined for Reference {
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn shift(&mut self, delta: i64) {
+        self.line = (self.line as i64 + delta) as usize;
+    }
+}
+
+impl Lined for Usage {
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn shift(&mut self, delta: i64) {
+        self.line = (self.line as i64 + delta) as usize;
+    }
+}
+
+fn splice<T: Lined>(items: &mut Vec<T>, edit: &LineEdit, mut rescanned: Vec<T>) {
+    let delta = edit.new_end as i64 - edit.old_end as i64;
+    items.retain(|item| item.line() < edit.start || item.line() > edit.old_end);
+    for item in items.iter_mut() {
+        if item.line() > edit.old_end {
+            item.shift(delta);
+        }
+    }
+    for item in rescanned.iter_mut() {
+        item.shift(edit.start as i64);
+    }
+
+    items.extend(rescanned);
+    items.sort_by_key(|item| item.line());
+}
+
+/// Find the variables declared and referenced in the `text`.
+pub(crate) fn parse_variables(text: &str, syntax: Syntax) -> Variables {
+    if syntax == Syntax::Tokens {
+        return parse_tokens(text);
+    }
+
+    let mut variables = Variables::default();
+    for (ix, line_text) in text.lines().enumerate() {
+        for sigil in syntax.sigils() {
+            parse_sigil(line_text, ix, sigil, &mut variables);
+        }
+    }
+    variables
+}
+
+/// Find the custom property declarations in the `text`, the values end at `;`, `}` or the end of line.
+#[cfg(test)]
+pub(crate) fn parse_declarations(text: &str) -> Vec<Declaration> {
+    parse_variables(text, Syntax::Css).declarations
+}
+
+/// Find the names starting with `sigil` in the line, the ones followed by `:` are declarations.
+fn parse_sigil(line_text: &str, ix: usize, sigil: &str, variables: &mut Variables) {
+    let bytes = line_text.as_bytes();
+    let mut offset = 0;
+    while let Some(pos) = line_text[offset..].find(sigil) {
+        let start = offset + pos;
+        let name_start = start + sigil.len();
+        let name_end = name_start + ident_len(&bytes[name_start..]);
+        offset = name_end;
+        if name_end == name_start
+            || start > 0 && is_ident_char(bytes[start - 1])
+            // e.g. `$1` in a regex replacement
+            || sigil != "--" && bytes[name_start].is_ascii_digit()
+        {
+            continue;
+        }
+
+        match parse_declaration(line_text, ix, sigil, start..name_end) {
+            Some(declaration) => variables.declarations.push(declaration),
+            None => variables.references.push(Reference {
+                name: line_text[start..name_end].to_string(),
+                line: ix,
+                range: start..name_end,
+            }),
+        }
+    }
+}
+
+/// Parse the declaration of the name at `name`, if it's followed by `:` at the start of a statement.
+fn parse_declaration(
+    line_text: &str,
+    ix: usize,
+    sigil: &str,
+    name: Range<usize>,
+) -> Option<Declaration> {
+    let before = line_text[..name.start].trim_end();
+    if !before.is_empty() && !before.ends_with(['{', ';', '"', '\'']) {
+        return None;
+    }
+
+    let rest = &line_text[name.end..];
+    // No space is allowed in Less, so `@page :first` is not a declaration.
+    let value = match sigil {
+        "@" => rest.strip_prefix(':')?,
+        _ => rest.trim_start().strip_prefix(':')?,
+    };
+    let value_start = line_text.len() - value.len();
+    let value_end = value
+        .find([';', '}'])
+        .map_or(line_text.len(), |pos| value_start + pos);
+    let mut raw = line_text[value_start..value_end].trim_end();
+    // e.g. `!important`, or Sass `!default` and `!global`
+    while let Some((value, flag)) = raw.rsplit_once('!') {
+        if flag.is_empty() || !flag.bytes().all(|b| b.is_ascii_alphabetic()) {
+            break;
+        }
+        raw = value.trim_end();
+    }
+    let leading = raw.len() - raw.trim_start().len();
+
+    let value_range = value_start + leading..value_start + raw.len();
+    Some(Declaration {
+        name: line_text[name.clone()].to_string(),
+        line: ix,
+        name_range: name,
+        value_line: ix,
+        value: line_text[value_range.clone()].to_string(),
+        value_range,
+    })
+}
+
+/// A JSON token, with its line and byte range, strings are without the quotes.
+enum JsonToken {
+    String(usize, Range<usize>),
+    Punct(u8),
+    /// A number, `true`, `false` or `null`.
+    Literal(usize, Range<usize>),
+}
+
+/// Split the JSON `text` into tokens, strings never span lines in JSON.
+fn json_tokens(text: &str) -> Vec<JsonToken> {
+    let mut tokens = vec![];
+    for (ix, line_text) in text.lines().enumerate() {
+        let bytes = line_text.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => {
+                    let mut end = i + 1;
+                    while end < bytes.len() && bytes[end] != b'"' {
+                        end += if bytes[end] == b'\\' { 2 } else { 1 };
+                    }
+                    let end = end.min(bytes.len());
+                    tokens.push(JsonToken::String(ix, i + 1..end));
+                    i = end + 1;
+                }
+                b @ (b'{' | b'}' | b'[' | b']' | b':' | b',') => {
+                    tokens.push(JsonToken::Punct(b));
+                    i += 1;
+                }
+                b if b.is_ascii_whitespace() => i += 1,
+                _ => {
+                    let len = bytes[i..]
+                        .iter()
+                        .take_while(|b| !b"{}[]:,\"".contains(b) && !b.is_ascii_whitespace())
+                        .count();
+                    tokens.push(JsonToken::Literal(ix, i..i + len));
+                    i += len;
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// The keys of design token metadata, they are not tokens.
+const TOKEN_METADATA_KEYS: [&str; 3] = ["type", "description", "comment"];
+
+/// Find the design tokens and their `{path.to.token}` aliases in a JSON `text`.
+///
+/// A token is a key with a value, or a group with a `$value` (or `value`) key.
+fn parse_tokens(text: &str) -> Variables {
+    let lines = text.lines().collect::<Vec<_>>();
+    let tokens = json_tokens(text);
+
+    // The current key of the objects, `None` for arrays.
+    let mut keys: Vec<Option<(usize, Range<usize>)>> = vec![];
+    let mut variables = Variables::default();
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        let (line, range) = match token {
+            JsonToken::Punct(b'{') | JsonToken::Punct(b'[') => {
+                let key = matches!(token, JsonToken::Punct(b'{')).then(|| (0, 0..0));
+                keys.push(key);
+                continue;
+            }
+            JsonToken::Punct(b'}') | JsonToken::Punct(b']') => {
+                keys.pop();
+                continue;
+            }
+            JsonToken::Punct(_) => continue,
+            JsonToken::String(line, range) | JsonToken::Literal(line, range) => (*line, range),
+        };
+
+        if matches!(iter.peek(), Some(JsonToken::Punct(b':'))) {
+            iter.next();
+            if let Some(Some(key)) = keys.last_mut() {
+                *key = (line, range.clone());
+            }
+            continue;
+        }
+
+        let value = &lines[line][range.clone()];
+        if matches!(token, JsonToken::String(..)) {
+            parse_aliases(value, line, range.start, &mut variables.references);
+        }
+
+        // The path of the keys, skip the values in arrays.
+        let Some(path) = keys.iter().cloned().collect::<Option<Vec<_>>>() else {
+            continue;
+        };
+        let names = path
+            .iter()
+            .map(|(line, range)| &lines[*line][range.clone()])
+            .collect::<Vec<_>>();
+        let (name_len, (name_line, name_range)) = match names.last() {
+            Some(&("$value" | "value")) if names.len() > 1 => {
+                (names.len() - 1, path[path.len() - 2].clone())
+            }
+            Some(key) if key.starts_with('$') || TOKEN_METADATA_KEYS.contains(key) => continue,
+            Some(_) => (names.len(), path[path.len() - 1].clone()),
+            None => continue,
+        };
+
+        variables.declarations.push(Declaration {
+            name: names[..name_len].join("."),
+            line: name_line,
+            name_range,
+            value_line: line,
+            value_range: range.clone(),
+            value: value.to_string(),
+        });
+    }
+    variables
+}
+
+/// Find the `{path.to.token}` aliases in a token value at `offset` of the line.
+fn parse_aliases(value: &str, line: usize, offset: usize, references: &mut Vec<Reference>) {
+    let mut start = 0;
+    while let Some(open) = value[start..].find('{').map(|pos| start + pos) {
+        let Some(close) = value[open..].find('}').map(|pos| open + pos) else {
+            break;
+        };
+        let name = &value[open + 1..close];
+        if !name.is_empty()
+            && name
+                .bytes()
+                .all(|b| is_ident_char(b) || b == b'.' || b == b'$')
+        {
+            references.push(Reference {
+                name: name.to_string(),
+                line,
+                range: offset + open + 1..offset + close,
+            });
+        }
+        start = close + 1;
+    }
+}
+
+/// Find the `var(--name)` and `var(--name, fallback)` usages in the `text`.
+pub(crate) fn parse_usages(text: &str) -> Vec<Usage> {
+    let mut usages = vec![];
+    for (ix, line_text) in text.lines().enumerate() {
+        let mut offset = 0;
+        while let Some(pos) = line_text[offset..].find("var(") {
+            let start = offset + pos;
+            offset = start + 4;
+            if start > 0 && is_ident_char(line_text.as_bytes()[start - 1]) {
+                continue;
+            }
+            if let Some(usage) = parse_usage(line_text, ix, start) {
+                offset = usage.range.end;
+                usages.push(usage);
+            }
+        }
+    }
+    usages
+}
+
+/// Parse the `var(` at `start` of the line.
+fn parse_usage(line_text: &str, line: usize, start: usize) -> Option<Usage> {
+    let bytes = line_text.as_bytes();
+    let args_start = start + 4;
+    let name_start =
+        args_start + (line_text[args_start..].len() - line_text[args_start..].trim_start().len());
+    if !line_text[name_start..].starts_with("--") {
+        return None;
+    }
+    let name_end = name_start + 2 + ident_len(&bytes[name_start + 2..]);
+    if name_end == name_start + 2 {
+        return None;
+    }
+
+    // Find the closing `)`, the fallback may have nested functions.
+    let mut depth = 0;
+    let mut close = None;
+    for (i, &b) in bytes[name_end..].iter().enumerate() {
+        match b {
+            b'(' => depth += 1,
+            b')' if depth == 0 => {
+                close = Some(name_end + i);
+                break;
+            }
+            b')' => depth -= 1,
+            _ => {}
+        }
+    }
+    let close = close?;
+
+    let rest = &line_text[name_end..close];
+    let fallback = match rest.trim_start().strip_prefix(',') {
+        Some(value) => {
+            let value_start = close - value.len();
+            let trimmed = value.trim();
+            let leading = value.len() - value.trim_start().len();
+            let range = value_start + leading..value_start + leading + trimmed.len();
+            Some((range, trimmed.to_string()))
+        }
+        None if rest.trim().is_empty() => None,
+        None => return None,
+    };
+
+    Some(Usage {
+        name: line_text[name_start..name_end].to_string(),
+        line,
+        range: start..close + 1,
+        name_range: name_start..name_end,
+        fallback,
+    })
+}
+
+fn resolve(value: &str, lookup: &impl Fn(&str) -> Option<String>, depth: usize) -> Option<Color> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    let value = value.trim();
+    if value.starts_with("var(") {
+        return resolve_usage_at(&parse_var(value)?, lookup, depth + 1);
+    }
+
+    try_parse_color(value).ok()
+}
+
+/// Parse a value that is a single `var()`, e.g. the value of `--text: var(--brand)`.
+pub(crate) fn parse_var(value: &str) -> Option<Usage> {
+    let value = value.trim();
+    parse_usage(value, 0, 0).filter(|usage| usage.range.end == value.len())
+}
+
+/// Resolve a usage to a color, `lookup` returns the value of a custom property.
+///
+/// `var()` chains are followed, and the fallback is used when the property is not declared.
+pub(crate) fn resolve_usage(
+    usage: &Usage,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Option<Color> {
+    resolve_usage_at(usage, lookup, 0)
+}
+
+fn resolve_usage_at(
+    usage: &Usage,
+    lookup: &impl Fn(&str) -> Option<String>,
+    depth: usize,
+) -> Option<Color> {
+    if let Some(value) = lookup(&usage.name) {
+        return resolve(&value, lookup, depth + 1);
+    }
+
+    let (_, fallback) = usage.fallback.as_ref()?;
+    resolve(fallback, lookup, depth + 1)
+}
+
+fn ident_len(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| is_ident_char(**b)).count()
+}
+
+fn is_ident_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use indoc::indoc;
+
+    use super::{
+        parse_declarations, parse_usages, parse_var, parse_variables, resolve_usage, splice_usages,
+        Syntax,
+    };
+    use crate::document::LineEdit;
+
+    #[test]
+    fn test_parse_declarations() {
+        let text = indoc! {r#"
+            :root {
+              --brand-primary: #3366ff;
+              --brand-text: var(--brand-primary) !important;
+              --gap: 4px; --accent: rgb(255 0 0) }
+            a { color: var(--brand-primary); }
+            .a--b:hover {}
+        "#};
+
+        let declarations = parse_declarations(text)
+            .into_iter()
+            .map(|decl| {
+                let line_text = text.lines().nth(decl.line).unwrap();
+                assert_eq!(&line_text[decl.name_range.clone()], decl.name);
+                assert_eq!(&line_text[decl.value_range.clone()], decl.value);
+                (decl.line, decl.name, decl.value)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            [
+                (1, "--brand-primary".to_string(), "#3366ff".to_string()),
+                (2, "--brand-text".into(), "var(--brand-primary)".into()),
+                (3, "--gap".into(), "4px".into()),
+                (3, "--accent".into(), "rgb(255 0 0)".into()),
+            ]
+        );
+    }
+
+    /// The declarations as `(line, name, value)` and references as `(line, name)`.
+    type Parsed<'a> = (Vec<(usize, &'a str, &'a str)>, Vec<(usize, &'a str)>);
+
+    fn variables(text: &str, syntax: Syntax) -> Parsed<'_> {
+        let lines = text.lines().collect::<Vec<_>>();
+        let variables = parse_variables(text, syntax);
+        let declarations = variables
+            .declarations
+            .iter()
+            .map(|decl| {
+                let name = &lines[decl.line][decl.name_range.clone()];
+                assert_eq!(
+                    name.trim_matches('"'),
+                    decl.name.rsplit('.').next().unwrap()
+                );
+                let value = &lines[decl.value_line][decl.value_range.clone()];
+                assert_eq!(value, decl.value);
+                (decl.line, name, value)
+            })
+            .collect();
+        let references = variables
+            .references
+            .iter()
+            .map(|reference| {
+                (
+                    reference.line,
+                    &lines[reference.line][reference.range.clone()],
+                )
+            })
+            .collect();
+        (declarations, references)
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        let text = indoc! {r#"
+            $brand: #3366ff !default;
+            --accent: $brand;
+            a { color: darken($brand, 10%); border: 1px solid var(--accent); }
+            @include button($color: red);
+            $price: "$1";
+        "#};
+        assert_eq!(
+            variables(text, Syntax::Sass),
+            (
+                vec![
+                    (0, "$brand", "#3366ff"),
+                    (1, "--accent", "$brand"),
+                    (4, "$price", "\"$1\""),
+                ],
+                vec![(1, "$brand"), (2, "--accent"), (2, "$brand"), (3, "$color")]
+            )
+        );
+
+        let text = indoc! {r#"
+            @brand: #3366ff;
+            @media (min-width: 100px) { a { color: @brand; } }
+            @page :first { margin: 1in; }
+        "#};
+        assert_eq!(
+            variables(text, Syntax::Less),
+            (
+                vec![(0, "@brand", "#3366ff")],
+                vec![(1, "@media"), (1, "@brand"), (2, "@page")]
+            )
+        );
+
+        // `$` is not a variable in CSS.
+        assert_eq!(
+            variables("a { --x: $y; }", Syntax::Css),
+            (vec![(0, "--x", "$y")], vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        let text = indoc! {r##"
+            {
+              "color": {
+                "brand": { "$value": "#3366ff", "$type": "color" },
+                "text": {
+                  "value": "{color.brand}",
+                  "description": "The text color"
+                },
+                "gray": "#888",
+                "list": ["#fff", "#000"]
+              },
+              "size": 4
+            }
+        "##};
+        assert_eq!(
+            variables(text, Syntax::Tokens),
+            (
+                vec![
+                    (2, "brand", "#3366ff"),
+                    (3, "text", "{color.brand}"),
+                    (7, "gray", "#888"),
+                    (10, "size", "4"),
+                ],
+                vec![(4, "color.brand")]
+            )
+        );
+
+        let names = parse_variables(text, Syntax::Tokens)
+            .declarations
+            .into_iter()
+            .map(|decl| decl.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["color.brand", "color.text", "color.gray", "size"]);
+    }
+
+    #[test]
+    fn test_syntax() {
+        let syntax =
+            |language_id: &str, path: &str| Syntax::detect(language_id, Some(Path::new(path)));
+        assert_eq!(syntax("css", "a.css"), Syntax::Css);
+        assert_eq!(syntax("scss", "a.scss"), Syntax::Sass);
+        assert_eq!(syntax("less", "a.less"), Syntax::Less);
+        assert_eq!(syntax("json", "design.tokens.json"), Syntax::Tokens);
+        assert_eq!(syntax("json", "package.json"), Syntax::Css);
+        assert_eq!(syntax("scss", "untitled"), Syntax::Sass);
+        assert_eq!(syntax("html", "index.html"), Syntax::Css);
+        assert_eq!(Syntax::from_path(Path::new("index.html")), None);
+    }
+
+    #[test]
+    fn test_parse_usages() {
+        let line = "a { color: var(--brand); background: var( --bg , var(--x, rgb(0 0 0)) ); }";
+        let usages = parse_usages(line)
+            .into_iter()
+            .map(|usage| {
+                (
+                    &line[usage.range],
+                    usage.name,
+                    usage.fallback.map(|(range, text)| {
+                        assert_eq!(&line[range], text);
+                        text
+                    }),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            usages,
+            [
+                ("var(--brand)", "--brand".to_string(), None),
+                (
+                    "var( --bg , var(--x, rgb(0 0 0)) )",
+                    "--bg".into(),
+                    Some("var(--x, rgb(0 0 0))".into())
+                ),
+            ]
+        );
+
+        assert_eq!(parse_usages("var(foo) myvar(--a) var(--a"), []);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let vars = HashMap::from([
+            ("--brand", "#3366ff"),
+            ("--text", "var(--brand)"),
+            ("--loop", "var(--loop)"),
+            ("--size", "4px"),
+        ]);
+        let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+        let hex = |value: &str| {
+            resolve_usage(&parse_var(value)?, &lookup).map(|color| color.to_css_hex())
+        };
+
+        assert_eq!(hex("var(--brand)"), Some("#3366ff".into()));
+        assert_eq!(hex("var(--text)"), Some("#3366ff".into()));
+        assert_eq!(hex("var(--missing, var(--text))"), Some("#3366ff".into()));
+        assert_eq!(hex("var(--missing, red)"), Some("#ff0000".into()));
+        assert_eq!(hex("var(--missing)"), None);
+        assert_eq!(hex("var(--loop)"), None);
+        assert_eq!(hex("var(--size)"), None);
+
+        let usage = &parse_usages("var(--missing, #fff)")[0];
+        assert_eq!(
+            resolve_usage(usage, &lookup).map(|color| color.to_css_hex()),
+            Some("#ffffff".into())
+        );
+    }
+
+    #[test]
+    fn test_splice() {
+        let old = ":root {\n  --a: red;\n}\na { color: var(--a); }\n";
+        let new = ":root {\n  --a: red;\n  --b: var(--a);\n}\na { color: var(--a); }\n";
+        // The line 1 is replaced by the lines 1 and 2.
+        let edit = LineEdit {
+            start: 1,
+            old_end: 1,
+            new_end: 2,
+        };
+        let lines = new.lines().skip(1).take(2).collect::<Vec<_>>().join("\n");
+
+        let mut variables = parse_variables(old, Syntax::Css);
+        variables.splice(&edit, parse_variables(&lines, Syntax::Css));
+        assert_eq!(variables, parse_variables(new, Syntax::Css));
+
+        let mut usages = parse_usages(old);
+        splice_usages(&mut usages, &edit, parse_usages(&lines));
+        assert_eq!(usages, parse_usages(new));
+    }
+}
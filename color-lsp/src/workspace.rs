@@ -0,0 +1,270 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::GitignoreBuilder;
+use ignore::WalkBuilder;
+
+use crate::config::Config;
+use crate::parser::{parse_with_options, ParseOptions};
+use crate::variables::{parse_variables, Syntax, Variables};
+
+/// The directories never walked into, besides the hidden and git ignored ones.
+const SKIP_DIRS: [&str; 3] = ["node_modules", "target", "dist"];
+
+/// Skip the files larger than this, they are likely generated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// The language ids of the file extensions which are not the same, for the config overrides.
+const LANGUAGE_IDS: [(&str, &str); 10] = [
+    ("rs", "rust"),
+    ("js", "javascript"),
+    ("jsx", "javascriptreact"),
+    ("ts", "typescript"),
+    ("tsx", "typescriptreact"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("kt", "kotlin"),
+    ("md", "markdown"),
+    ("yml", "yaml"),
+];
+
+/// A color literal in a file of the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexedColor {
+    /// 0-based line number
+    pub(crate) line: usize,
+    /// The byte range in the line.
+    pub(crate) range: Range<usize>,
+    /// The normalized RGBA, the same color matches across the notations.
+    pub(crate) rgba: [u8; 4],
+}
+
+/// Find the color literals in the `text` of a workspace file.
+pub(crate) fn index_colors(text: &str, options: &ParseOptions) -> Vec<IndexedColor> {
+    parse_with_options(text, options)
+        .into_iter()
+        .map(|node| {
+            let start = node.position.character as usize;
+            IndexedColor {
+                line: node.position.line as usize,
+                range: start..start + node.matched.len(),
+                rgba: node.color.to_rgba8(),
+            }
+        })
+        .collect()
+}
+
+/// The index of a workspace file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileIndex {
+    pub(crate) colors: Vec<IndexedColor>,
+    /// The variables of the stylesheets and design token files.
+    pub(crate) variables: Option<Variables>,
+}
+
+/// Index the colors and variables of a workspace file, `None` if it's not a text file.
+///
+/// `options` are the parse options before the `config` of the file.
+pub(crate) fn index_file(
+    root: &Path,
+    path: &Path,
+    config: &Config,
+    options: &ParseOptions,
+) -> Option<FileIndex> {
+    let text = std::fs::read_to_string(path).ok()?;
+    if text.contains('\0') {
+        return None;
+    }
+
+    let relative_path = path.strip_prefix(root).unwrap_or(path);
+    let options = config.parse_options(options.clone(), &language_id(path), Some(relative_path));
+    Some(FileIndex {
+        colors: index_colors(&text, &options),
+        variables: Syntax::from_path(path).map(|syntax| parse_variables(&text, syntax)),
+    })
+}
+
+/// Guess the language id of a file not opened, by its extension.
+pub(crate) fn language_id(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    LANGUAGE_IDS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map_or(extension.clone(), |(_, language_id)| {
+            language_id.to_string()
+        })
+}
+
+/// Find the files matching `filter` in the `root` directory recursively, excluding the
+/// files ignored by `.gitignore` or the `config`.
+pub(crate) fn find_files(
+    root: &Path,
+    config: &Config,
+    filter: impl Fn(&Path) -> bool,
+) -> Vec<PathBuf> {
+    let walker = WalkBuilder::new(root)
+        // Respect `.gitignore` out of a git repository too.
+        .require_git(false)
+        .filter_entry(|entry| {
+            !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir())
+                || !SKIP_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .build();
+
+    let mut files = walker
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .filter(|path| is_indexed(root, path, config) && filter(path))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Whether a file is small enough, and not ignored by the `config`.
+fn is_indexed(root: &Path, path: &Path, config: &Config) -> bool {
+    let relative_path = path.strip_prefix(root).unwrap_or(path);
+    !config.is_ignored(Some(relative_path))
+        && path
+            .metadata()
+            .is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE)
+}
+
+/// Whether a changed file should be indexed, with the same rules as [`find_files`].
+pub(crate) fn is_workspace_file(root: &Path, path: &Path, config: &Config) -> bool {
+    let Ok(relative_path) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut dirs = relative_path.components().collect::<Vec<_>>();
+    dirs.pop();
+    if relative_path.components().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        name.starts_with('.')
+    }) || dirs
+        .iter()
+        .any(|dir| SKIP_DIRS.contains(&dir.as_os_str().to_string_lossy().as_ref()))
+    {
+        return false;
+    }
+
+    // The `.gitignore` of each directory from the root to the file.
+    let mut dir = root.to_path_buf();
+    for component in std::iter::once(None).chain(dirs.iter().map(Some)) {
+        if let Some(component) = component {
+            dir.push(component);
+        }
+        let mut builder = GitignoreBuilder::new(&dir);
+        if builder.add(dir.join(".gitignore")).is_some() {
+            continue;
+        }
+        if builder.build().is_ok_and(|gitignore| {
+            gitignore
+                .matched_path_or_any_parents(path, false)
+                .is_ignore()
+        }) {
+            return false;
+        }
+    }
+
+    path.is_file() && is_indexed(root, path, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{find_files, index_colors, is_workspace_file, language_id};
+    use crate::config::Config;
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn test_find_files() {
+        let root =
+            std::env::temp_dir().join(format!("color-lsp-find-files-{}", std::process::id()));
+        for (path, text) in [
+            ("src/app.css", "a { color: red; }"),
+            ("src/app.ts", "const a = '#fff';"),
+            ("styles/theme.css", ":root { --x: red; }"),
+            ("node_modules/lib/lib.css", "a { color: red; }"),
+            ("build/out.css", "a { color: red; }"),
+            (".gitignore", "build/\n"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+
+        let is_css = |path: &Path| path.extension().is_some_and(|ext| ext == "css");
+        let mut files = find_files(&root, &Config::default(), is_css);
+        files.sort();
+        assert_eq!(
+            files,
+            [root.join("src/app.css"), root.join("styles/theme.css")]
+        );
+
+        let config = Config::from_toml(r#"ignore = ["styles/**"]"#).unwrap();
+        assert_eq!(
+            find_files(&root, &config, is_css),
+            [root.join("src/app.css")]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_is_workspace_file() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let config = Config::default();
+        assert!(is_workspace_file(
+            &root,
+            &root.join("tests/test.css"),
+            &config
+        ));
+        assert!(is_workspace_file(&root, &root.join("README.md"), &config));
+        // Ignored by `.gitignore`
+        assert!(!is_workspace_file(&root, &root.join("Cargo.lock"), &config));
+        assert!(!is_workspace_file(
+            &root,
+            &root.join("target/debug/color-lsp"),
+            &config
+        ));
+        assert!(!is_workspace_file(&root, &root.join(".git/HEAD"), &config));
+        assert!(!is_workspace_file(&root, Path::new("/tmp/a.css"), &config));
+    }
+
+    #[test]
+    fn test_index_colors() {
+        let colors = index_colors(
+            "a { color: #3366FF; }\nlet c = rgb(51, 102, 255);",
+            &ParseOptions::default(),
+        );
+        let colors = colors
+            .into_iter()
+            .map(|color| (color.line, color.range, color.rgba))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            [
+                (0, 11..18, [51, 102, 255, 255]),
+                (1, 8..25, [51, 102, 255, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_language_id() {
+        assert_eq!(language_id(Path::new("src/main.rs")), "rust");
+        assert_eq!(language_id(Path::new("a.CSS")), "css");
+        assert_eq!(language_id(Path::new("Makefile")), "");
+    }
+}
@@ -1,7 +1,8 @@
 use std::fs;
-use zed_extension_api::{self as zed, Result};
+use zed_extension_api::{self as zed, settings::LspSettings, Result};
 
 const GITHUB_REPO: &str = "huacnlee/color-lsp";
+const SERVER_NAME: &str = "color-lsp";
 
 #[inline]
 fn bin_name() -> &'static str {
@@ -52,7 +53,7 @@ impl ColorHighlightExtension {
         }
 
         if let Some(path) = &self.cached_binary_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+            if fs::metadata(path).is_ok_and(|stat| stat.is_file()) {
                 update_status(id, Status::None);
                 return Ok(path.clone());
             }
@@ -60,7 +61,7 @@ impl ColorHighlightExtension {
 
         if let Some(binary_path) = Self::check_installed() {
             // silent to check for update.
-            let _ = Self::check_to_update(&id);
+            let _ = Self::check_to_update(id);
             return Ok(binary_path);
         }
 
@@ -73,7 +74,7 @@ impl ColorHighlightExtension {
         let entries = fs::read_dir(".").ok()?;
         for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
             let binary_path = entry.path().join(bin_name());
-            if fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
+            if fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
                 return binary_path.to_str().map(|s| s.to_string());
             }
         }
@@ -117,7 +118,7 @@ impl ColorHighlightExtension {
         let bin_name = bin_name();
         let version_binary_path = format!("{version_dir}/{bin_name}");
 
-        if !fs::metadata(&version_binary_path).map_or(false, |stat| stat.is_file()) {
+        if !fs::metadata(&version_binary_path).is_ok_and(|stat| stat.is_file()) {
             update_status(id, Status::Downloading);
 
             let asset = release
@@ -168,6 +169,29 @@ impl zed::Extension for ColorHighlightExtension {
             env: Default::default(),
         })
     }
+
+    fn language_server_initialization_options(
+        &mut self,
+        _: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<zed::serde_json::Value>> {
+        let settings = LspSettings::for_worktree(SERVER_NAME, worktree)?;
+        Ok(settings.initialization_options)
+    }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        _: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<zed::serde_json::Value>> {
+        // Serve the `initialization_options` for `workspace/configuration` as well,
+        // so the changes in `settings.json` are applied without restarting the server.
+        let settings = LspSettings::for_worktree(SERVER_NAME, worktree)?;
+        Ok(settings
+            .settings
+            .or(settings.initialization_options)
+            .map(|settings| zed::serde_json::json!({ SERVER_NAME: settings })))
+    }
 }
 
 zed::register_extension!(ColorHighlightExtension);